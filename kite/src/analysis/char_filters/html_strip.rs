@@ -0,0 +1,280 @@
+use std::char;
+
+use analysis::CharFilter;
+use analysis::char_filters::OffsetMap;
+
+/// Elements that separate words, these are replaced with a newline
+const BLOCK_ELEMENTS: &'static [&'static str] = &[
+    "address", "article", "aside", "blockquote", "br", "dd", "div", "dl", "dt", "fieldset",
+    "figcaption", "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6",
+    "header", "hr", "li", "main", "nav", "ol", "p", "pre", "section", "table",
+    "tbody", "td", "tfoot", "th", "thead", "tr", "ul",
+];
+
+/// Elements that have their content removed along with their tags
+const SKIPPED_ELEMENTS: &'static [&'static str] = &["script", "style"];
+
+fn decode_named_entity(name: &str) -> Option<char> {
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "hellip" => '…',
+        "mdash" => '—',
+        "ndash" => '–',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "laquo" => '«',
+        "raquo" => '»',
+        "euro" => '€',
+        "pound" => '£',
+        "yen" => '¥',
+        "cent" => '¢',
+        "sect" => '§',
+        "deg" => '°',
+        "plusmn" => '±',
+        "times" => '×',
+        "divide" => '÷',
+        "middot" => '·',
+        "bull" => '•',
+        "Agrave" => 'À',
+        "Aacute" => 'Á',
+        "Acirc" => 'Â',
+        "Auml" => 'Ä',
+        "Aring" => 'Å',
+        "AElig" => 'Æ',
+        "Ccedil" => 'Ç',
+        "Egrave" => 'È',
+        "Eacute" => 'É',
+        "Ecirc" => 'Ê',
+        "Ntilde" => 'Ñ',
+        "Ouml" => 'Ö',
+        "Oslash" => 'Ø',
+        "Uuml" => 'Ü',
+        "agrave" => 'à',
+        "aacute" => 'á',
+        "acirc" => 'â',
+        "atilde" => 'ã',
+        "auml" => 'ä',
+        "aring" => 'å',
+        "aelig" => 'æ',
+        "ccedil" => 'ç',
+        "egrave" => 'è',
+        "eacute" => 'é',
+        "ecirc" => 'ê',
+        "euml" => 'ë',
+        "igrave" => 'ì',
+        "iacute" => 'í',
+        "icirc" => 'î',
+        "iuml" => 'ï',
+        "ntilde" => 'ñ',
+        "ograve" => 'ò',
+        "oacute" => 'ó',
+        "ocirc" => 'ô',
+        "otilde" => 'õ',
+        "ouml" => 'ö',
+        "oslash" => 'ø',
+        "ugrave" => 'ù',
+        "uacute" => 'ú',
+        "ucirc" => 'û',
+        "uuml" => 'ü',
+        "yacute" => 'ý',
+        "yuml" => 'ÿ',
+        "szlig" => 'ß',
+        _ => return None,
+    })
+}
+
+/// Decodes the entity at the start of "input" (which must start with "&")
+/// Returns the decoded character and the length of the entity in bytes
+fn decode_entity(input: &str) -> Option<(char, usize)> {
+    // Only look a short way ahead for the ";" (counted in characters so the search
+    // never stops inside a multibyte character)
+    let end = match input.char_indices().take(32).find(|&(_, c)| c == ';') {
+        Some((end, _)) => end,
+        None => return None,
+    };
+
+    let name = &input[1..end];
+    let decoded = if name.starts_with("#x") || name.starts_with("#X") {
+        u32::from_str_radix(&name[2..], 16).ok().and_then(char::from_u32)
+    } else if name.starts_with('#') {
+        name[1..].parse::<u32>().ok().and_then(char::from_u32)
+    } else {
+        decode_named_entity(name)
+    };
+
+    decoded.map(|c| (c, end + 1))
+}
+
+/// Parses the tag at the start of "input" (which must start with "<")
+/// Returns the lowercased element name, whether it's a closing tag and the length of the tag in bytes
+fn parse_tag(input: &str) -> Option<(String, bool, usize)> {
+    let bytes = input.as_bytes();
+    let mut i = 1;
+
+    let is_closing = bytes.get(i) == Some(&b'/');
+    if is_closing {
+        i += 1;
+    }
+
+    // Declarations and processing instructions (eg, "<!DOCTYPE html>" and "<?xml ?>")
+    let is_declaration = !is_closing && (bytes.get(i) == Some(&b'!') || bytes.get(i) == Some(&b'?'));
+    if !is_declaration && !bytes.get(i).map_or(false, |c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    let name_start = i;
+    while bytes.get(i).map_or(false, |c| c.is_ascii_alphanumeric()) {
+        i += 1;
+    }
+    let name = input[name_start..i].to_ascii_lowercase();
+
+    // Find the end of the tag, skipping over quoted attribute values
+    let mut quote = None;
+    while i < bytes.len() {
+        match (quote, bytes[i]) {
+            (None, b'>') => return Some((name, is_closing, i + 1)),
+            (None, b'"') | (None, b'\'') => quote = Some(bytes[i]),
+            (Some(q), c) if q == c => quote = None,
+            _ => {}
+        }
+
+        i += 1;
+    }
+
+    None
+}
+
+/// Removes HTML tags and decodes HTML entities
+///
+/// Tags of block-level elements (such as "p" and "br") are replaced with a
+/// newline so the words either side of them aren't joined together. The
+/// content of "script" and "style" elements, and comments, is removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtmlStripCharFilter;
+
+impl CharFilter for HtmlStripCharFilter {
+    fn filter(&self, input: &str) -> (String, OffsetMap) {
+        let mut output = String::with_capacity(input.len());
+        let mut offset_map = OffsetMap::new();
+        let mut i = 0;
+
+        while i < input.len() {
+            let rest = &input[i..];
+
+            if rest.starts_with("<!--") {
+                i += match rest.find("-->") {
+                    Some(end) => end + 3,
+                    None => rest.len(),
+                };
+                offset_map.add_removal(output.len(), i);
+                continue;
+            }
+
+            if rest.starts_with('<') {
+                if let Some((name, is_closing, length)) = parse_tag(rest) {
+                    let tag_start = i;
+                    i += length;
+
+                    if !is_closing && SKIPPED_ELEMENTS.contains(&name.as_str()) {
+                        // Skip to the end of the closing tag
+                        let closing_tag = format!("</{}", name);
+                        i += match input[i..].to_ascii_lowercase().find(&closing_tag) {
+                            Some(start) => {
+                                let closing_start = i + start;
+                                parse_tag(&input[closing_start..]).map_or(input.len() - i, |(_, _, length)| start + length)
+                            }
+                            None => input.len() - i,
+                        };
+                    } else if BLOCK_ELEMENTS.contains(&name.as_str()) {
+                        let output_start = output.len();
+                        output.push('\n');
+                        offset_map.add_replacement(output_start, output.len(), tag_start, i);
+                        continue;
+                    }
+
+                    offset_map.add_removal(output.len(), i);
+                    continue;
+                }
+            }
+
+            if rest.starts_with('&') {
+                if let Some((c, length)) = decode_entity(rest) {
+                    let output_start = output.len();
+                    output.push(c);
+                    offset_map.add_replacement(output_start, output.len(), i, i + length);
+                    i += length;
+                    continue;
+                }
+            }
+
+            let c = rest.chars().next().unwrap();
+            output.push(c);
+            i += c.len_utf8();
+        }
+
+        (output, offset_map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use analysis::{Analyzer, ChainAnalyzer, CharFilter};
+    use super::HtmlStripCharFilter;
+
+    #[test]
+    fn test_html_strip_char_filter() {
+        let (output, _) = HtmlStripCharFilter.filter("<p class=\"intro\">Hello <b>World</b></p><p>Caf&eacute; &amp; bar&#33;</p>");
+
+        assert_eq!(output, "\nHello World\n\nCafé & bar!\n");
+    }
+
+    #[test]
+    fn test_html_strip_char_filter_skips_scripts_and_comments() {
+        let (output, _) = HtmlStripCharFilter.filter("a<!-- comment --><script type='text/javascript'>var x = '<b>';</script>b<STYLE>p {}</STYLE>c");
+
+        assert_eq!(output, "abc");
+    }
+
+    #[test]
+    fn test_html_strip_char_filter_leaves_invalid_markup() {
+        let (output, _) = HtmlStripCharFilter.filter("1 < 2 && 3 > 2 &unknown;");
+
+        assert_eq!(output, "1 < 2 && 3 > 2 &unknown;");
+    }
+
+    #[test]
+    fn test_html_strip_char_filter_unterminated_entity_before_multibyte_text() {
+        let input = "Fish &chips at the café in Zürich 東京の寿司; best in town";
+        let (output, _) = HtmlStripCharFilter.filter(input);
+
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn test_html_strip_char_filter_offsets() {
+        let input = "<b>caf&eacute;</b> &lt;tag&gt;";
+        let (output, offset_map) = HtmlStripCharFilter.filter(input);
+
+        assert_eq!(output, "café <tag>");
+        assert_eq!(&input[offset_map.correct(0)..offset_map.correct_end(5)], "caf&eacute;");
+        assert_eq!(&input[offset_map.correct(6)..offset_map.correct_end(11)], "&lt;tag&gt;");
+    }
+
+    #[test]
+    fn test_html_strip_in_analyzer() {
+        let analyzer = ChainAnalyzer::standard().char_filter(HtmlStripCharFilter);
+        let terms = analyzer.analyze("<h1>Hello</h1>World &amp; <i>friends</i>").into_iter().map(|token| token.term).collect::<Vec<_>>();
+
+        assert_eq!(terms, ChainAnalyzer::standard().analyze("Hello World & friends").into_iter().map(|token| token.term).collect::<Vec<_>>());
+    }
+}
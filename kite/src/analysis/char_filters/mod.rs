@@ -0,0 +1,292 @@
+pub mod html_strip;
+pub mod pattern_replace;
+
+use std::cmp;
+
+pub use analysis::char_filters::html_strip::HtmlStripCharFilter;
+pub use analysis::char_filters::pattern_replace::PatternReplaceCharFilter;
+
+/// A point where the offsets in the output of a char filter stop lining up with the input
+#[derive(Debug, Clone, PartialEq)]
+struct Correction {
+    output_offset: usize,
+
+    /// The input offset of text starting at "output_offset"
+    start_input_offset: usize,
+
+    /// The input offset of text ending at "output_offset"
+    /// This only differs from the start offset when text was removed at this point
+    end_input_offset: usize,
+
+    /// Set if the text between this correction and the next one replaced some input
+    ///
+    /// Offsets inside replaced text can't be mapped exactly so they are moved
+    /// to the start or end of the text it replaced.
+    replaced: bool,
+}
+
+/// Maps offsets in the output of a char filter back to offsets in its input
+///
+/// This is made up of a list of corrections. Each correction records that an
+/// offset in the output corresponds to an offset in the input. Offsets after a
+/// correction are shifted by the same amount as the correction until the next
+/// correction, unless the text between them was a replacement.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OffsetMap {
+    corrections: Vec<Correction>,
+}
+
+impl OffsetMap {
+    /// Creates a map where every offset maps to itself
+    pub fn new() -> OffsetMap {
+        OffsetMap {
+            corrections: Vec::new(),
+        }
+    }
+
+    /// Records that "output_offset" corresponds to "input_offset"
+    ///
+    /// This should be called after some text is replaced. Corrections must be
+    /// added in order.
+    pub fn add_correction(&mut self, output_offset: usize, input_offset: usize) {
+        if let Some(last_correction) = self.corrections.last_mut() {
+            if last_correction.output_offset == output_offset {
+                last_correction.start_input_offset = input_offset;
+                last_correction.end_input_offset = input_offset;
+                return;
+            }
+        }
+
+        self.corrections.push(Correction {
+            output_offset: output_offset,
+            start_input_offset: input_offset,
+            end_input_offset: input_offset,
+            replaced: false,
+        });
+    }
+
+    /// Records that the input between "input_start" and "input_end" was replaced
+    /// with the output between "output_start" and "output_end"
+    ///
+    /// Text that starts inside the replacement is mapped to the start of the
+    /// replaced input and text that ends inside it is mapped to the end.
+    /// Replacements must be added in order.
+    pub fn add_replacement(&mut self, output_start: usize, output_end: usize, input_start: usize, input_end: usize) {
+        if output_start == output_end {
+            self.add_removal(output_end, input_end);
+            return;
+        }
+
+        let merged = match self.corrections.last_mut() {
+            Some(last_correction) if last_correction.output_offset == output_start => {
+                last_correction.replaced = true;
+                true
+            }
+            _ => false,
+        };
+
+        if !merged {
+            self.corrections.push(Correction {
+                output_offset: output_start,
+                start_input_offset: input_start,
+                end_input_offset: input_start,
+                replaced: true,
+            });
+        }
+
+        self.add_correction(output_end, input_end);
+    }
+
+    /// Records that the input up to "input_offset" was removed at "output_offset"
+    ///
+    /// Text that ends at the output offset will be mapped to the start of the
+    /// removed input and text that starts there will be mapped to the end.
+    pub fn add_removal(&mut self, output_offset: usize, input_offset: usize) {
+        if let Some(last_correction) = self.corrections.last_mut() {
+            if last_correction.output_offset == output_offset {
+                last_correction.start_input_offset = input_offset;
+                return;
+            }
+        }
+
+        let end_input_offset = self.correct(output_offset);
+        self.corrections.push(Correction {
+            output_offset: output_offset,
+            start_input_offset: input_offset,
+            end_input_offset: end_input_offset,
+            replaced: false,
+        });
+    }
+
+    fn find_correction(&self, offset: usize) -> Result<usize, usize> {
+        self.corrections.binary_search_by_key(&offset, |correction| correction.output_offset)
+    }
+
+    /// Returns true if the text at "offset" in the output replaced some input
+    fn is_replaced(&self, offset: usize) -> bool {
+        match self.find_correction(offset) {
+            Ok(i) => self.corrections[i].replaced,
+            Err(0) => false,
+            Err(i) => self.corrections[i - 1].replaced,
+        }
+    }
+
+    fn extrapolate(&self, i: usize, offset: usize, is_end: bool) -> usize {
+        let next_correction = self.corrections.get(i);
+
+        if i > 0 {
+            let correction = &self.corrections[i - 1];
+
+            if correction.replaced {
+                return match (is_end, next_correction) {
+                    (true, Some(next_correction)) => next_correction.end_input_offset,
+                    _ => correction.start_input_offset,
+                };
+            }
+        }
+
+        let input_offset = if i == 0 {
+            offset
+        } else {
+            let correction = &self.corrections[i - 1];
+            correction.start_input_offset + (offset - correction.output_offset)
+        };
+
+        // Never run past the text that the next correction starts from
+        match next_correction {
+            Some(next_correction) => cmp::min(input_offset, next_correction.end_input_offset),
+            None => input_offset,
+        }
+    }
+
+    /// Converts the offset of the start of some text in the output into an offset in the input
+    pub fn correct(&self, offset: usize) -> usize {
+        match self.find_correction(offset) {
+            Ok(i) => self.corrections[i].start_input_offset,
+            Err(i) => self.extrapolate(i, offset, false),
+        }
+    }
+
+    /// Converts the offset of the end of some text in the output into an offset in the input
+    pub fn correct_end(&self, offset: usize) -> usize {
+        match self.find_correction(offset) {
+            Ok(i) => self.corrections[i].end_input_offset,
+            Err(i) => self.extrapolate(i, offset, true),
+        }
+    }
+
+    /// Combines this map with the map of a char filter that was run on the output of this one
+    ///
+    /// The returned map converts offsets in the output of the second filter
+    /// into offsets in the input of the first.
+    pub fn then(&self, next: &OffsetMap) -> OffsetMap {
+        // The combined map can only change where either of the maps change
+        let mut offsets = next.corrections.iter().map(|correction| correction.output_offset).collect::<Vec<usize>>();
+
+        for correction in self.corrections.iter() {
+            let offset = correction.output_offset;
+
+            // Find the offset in the output of "next" that maps to this offset
+            let i = match next.corrections.binary_search_by_key(&offset, |correction| correction.start_input_offset) {
+                Ok(i) => i + 1,
+                Err(i) => i,
+            };
+
+            let segment_start = if i > 0 { (next.corrections[i - 1].output_offset, next.corrections[i - 1].start_input_offset) } else { (0, 0) };
+            if offset < segment_start.1 {
+                continue;
+            }
+
+            if offset > segment_start.1 && i > 0 && next.corrections[i - 1].replaced {
+                // Offset was replaced by "next"
+                continue;
+            }
+
+            let output_offset = segment_start.0 + (offset - segment_start.1);
+            if i < next.corrections.len() && output_offset >= next.corrections[i].output_offset {
+                // Offset was removed by "next"
+                continue;
+            }
+
+            offsets.push(output_offset);
+        }
+
+        offsets.sort();
+        offsets.dedup();
+
+        OffsetMap {
+            corrections: offsets.into_iter()
+                .map(|offset| {
+                    Correction {
+                        output_offset: offset,
+                        start_input_offset: self.correct(next.correct(offset)),
+                        end_input_offset: self.correct_end(next.correct_end(offset)),
+                        replaced: next.is_replaced(offset) || self.is_replaced(next.correct(offset)),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OffsetMap;
+
+    #[test]
+    fn test_offset_map() {
+        // "a&amp;b" -> "a&b"
+        let mut map = OffsetMap::new();
+        map.add_correction(2, 6);
+
+        assert_eq!(map.correct(0), 0);
+        assert_eq!(map.correct(1), 1);
+        assert_eq!(map.correct(2), 6);
+        assert_eq!(map.correct(3), 7);
+    }
+
+    #[test]
+    fn test_offset_map_removal() {
+        // "a<br>b" -> "ab"
+        let mut map = OffsetMap::new();
+        map.add_removal(1, 5);
+
+        assert_eq!(map.correct_end(1), 1);
+        assert_eq!(map.correct(1), 5);
+        assert_eq!(map.correct_end(2), 6);
+    }
+
+    #[test]
+    fn test_offset_map_replacement() {
+        // "a-b" -> "a日本b"
+        let mut map = OffsetMap::new();
+        map.add_replacement(1, 7, 1, 2);
+
+        assert_eq!(map.correct(0), 0);
+        assert_eq!(map.correct_end(1), 1);
+        assert_eq!(map.correct(1), 1);
+        assert_eq!(map.correct(4), 1);
+        assert_eq!(map.correct_end(4), 2);
+        assert_eq!(map.correct_end(7), 2);
+        assert_eq!(map.correct(7), 2);
+        assert_eq!(map.correct_end(8), 3);
+    }
+
+    #[test]
+    fn test_offset_map_then() {
+        // "<b>x</b> &lt;y" -> "x &lt;y" -> "x <y"
+        let mut first = OffsetMap::new();
+        first.add_removal(0, 3);
+        first.add_removal(1, 8);
+
+        let mut second = OffsetMap::new();
+        second.add_correction(3, 6);
+
+        let combined = first.then(&second);
+        let original = "<b>x</b> &lt;y";
+
+        assert_eq!(&original[combined.correct(0)..combined.correct_end(1)], "x");
+        assert_eq!(&original[combined.correct(2)..combined.correct_end(4)], "&lt;y");
+        assert_eq!(combined.correct(2), 9);
+    }
+}
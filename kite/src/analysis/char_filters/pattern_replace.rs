@@ -0,0 +1,117 @@
+use regex::{Regex, Error as RegexError};
+
+use analysis::CharFilter;
+use analysis::char_filters::OffsetMap;
+
+/// Replaces all matches of a regular expression
+///
+/// The replacement can refer to capture groups in the pattern (eg, "$1").
+#[derive(Debug, Clone)]
+pub struct PatternReplaceCharFilter {
+    pattern: Regex,
+    replacement: String,
+}
+
+impl PatternReplaceCharFilter {
+    pub fn new(pattern: &str, replacement: &str) -> Result<PatternReplaceCharFilter, RegexError> {
+        Ok(PatternReplaceCharFilter {
+            pattern: try!(Regex::new(pattern)),
+            replacement: replacement.to_string(),
+        })
+    }
+}
+
+impl CharFilter for PatternReplaceCharFilter {
+    fn filter(&self, input: &str) -> (String, OffsetMap) {
+        let mut output = String::with_capacity(input.len());
+        let mut offset_map = OffsetMap::new();
+        let mut last_match_end = 0;
+
+        for captures in self.pattern.captures_iter(input) {
+            let matched = captures.get(0).unwrap();
+
+            output.push_str(&input[last_match_end..matched.start()]);
+            let output_len_before_match = output.len();
+            captures.expand(&self.replacement, &mut output);
+
+            offset_map.add_replacement(output_len_before_match, output.len(), matched.start(), matched.end());
+
+            last_match_end = matched.end();
+        }
+
+        output.push_str(&input[last_match_end..]);
+
+        (output, offset_map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use analysis::{Analyzer, ChainAnalyzer, CharFilter};
+    use super::PatternReplaceCharFilter;
+
+    fn assert_offsets_slice_input(pattern: &str, replacement: &str, input: &str) {
+        let analyzer = ChainAnalyzer::standard().char_filter(PatternReplaceCharFilter::new(pattern, replacement).unwrap());
+
+        for token in analyzer.analyze(input) {
+            let (start, end) = token.byte_offsets.unwrap();
+            assert!(start <= end, "{:?} in {:?}", token, input);
+            assert!(input.get(start..end).is_some(), "{:?} in {:?}", token, input);
+        }
+    }
+
+    #[test]
+    fn test_pattern_replace_char_filter() {
+        let filter = PatternReplaceCharFilter::new(r"(\d+)-(\d+)", "$1$2").unwrap();
+        let input = "call 555-1234 now";
+        let (output, offset_map) = filter.filter(input);
+
+        assert_eq!(output, "call 5551234 now");
+        assert_eq!(&input[offset_map.correct(5)..offset_map.correct_end(12)], "555-1234");
+        assert_eq!(&input[offset_map.correct(13)..offset_map.correct_end(16)], "now");
+    }
+
+    #[test]
+    fn test_pattern_replace_char_filter_longer_replacement() {
+        let filter = PatternReplaceCharFilter::new("x", "hello world").unwrap();
+        let input = "axb y";
+        let (output, offset_map) = filter.filter(input);
+
+        assert_eq!(output, "ahello worldb y");
+        assert_eq!(&input[offset_map.correct(0)..offset_map.correct_end(6)], "ax");
+        assert_eq!(&input[offset_map.correct(7)..offset_map.correct_end(13)], "xb");
+        assert_eq!(&input[offset_map.correct(14)..offset_map.correct_end(15)], "y");
+
+        assert_offsets_slice_input("x", "hello world", input);
+        assert_offsets_slice_input("x", "日本", "x");
+        assert_offsets_slice_input("x", "a b c d", "xx x");
+    }
+
+    #[test]
+    fn test_pattern_replace_char_filter_shorter_replacement() {
+        assert_offsets_slice_input("[a-z]+", "a", "hello world");
+        assert_offsets_slice_input("-+", " ", "a---b----c");
+        assert_offsets_slice_input("ß+", "s", "straßße weiß");
+    }
+
+    #[test]
+    fn test_pattern_replace_char_filter_multibyte_replacement() {
+        let filter = PatternReplaceCharFilter::new("-", "éé日").unwrap();
+        let input = "a-b";
+        let (output, offset_map) = filter.filter(input);
+
+        assert_eq!(output, "aéé日b");
+        assert_eq!(&input[offset_map.correct(0)..offset_map.correct_end(5)], "a-");
+        assert_eq!(&input[offset_map.correct(5)..offset_map.correct_end(9)], "-b");
+
+        assert_offsets_slice_input("-", "éé日", input);
+        assert_offsets_slice_input("é", "ab cd", "café éclair");
+        assert_offsets_slice_input("東京", "to kyo", "東京の寿司");
+        assert_offsets_slice_input("[aeiou]", "日 本", "banana split");
+    }
+
+    #[test]
+    fn test_pattern_replace_char_filter_invalid_pattern() {
+        assert!(PatternReplaceCharFilter::new("(unclosed", "").is_err());
+    }
+}
@@ -0,0 +1,474 @@
+use regex::{Regex, Error as RegexError};
+
+use analysis::{ChainAnalyzer, Language};
+use analysis::char_filters::{HtmlStripCharFilter, PatternReplaceCharFilter};
+use analysis::tokenizers::{StandardTokenizer, NGramTokenizer, EdgeNGramTokenizer, CjkBigramTokenizer};
+use analysis::tokenizers::{WhitespaceTokenizer, PatternTokenizer, PathHierarchyTokenizer, UrlEmailTokenizer};
+use analysis::filters::{LowercaseFilter, StemmerFilter, StopFilter, NfkcFilter, CaseFoldFilter, AsciiFoldingFilter};
+use analysis::filters::{ShingleFilter, SynonymFilter, SynonymRule, PhoneticFilter, PhoneticAlgorithm};
+use analysis::filters::{DelimitedPayloadFilter, PayloadEncoding};
+
+/// Selects a char filter in an analyzer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CharFilterConfig {
+    /// Removes HTML tags and decodes HTML entities
+    HtmlStrip,
+
+    /// Replaces all matches of a regular expression
+    /// The replacement can refer to capture groups in the pattern (eg, "$1")
+    PatternReplace {
+        pattern: String,
+
+        #[serde(default)]
+        replacement: String,
+    },
+}
+
+/// Selects the tokenizer of an analyzer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TokenizerConfig {
+    /// Splits text on unicode word boundaries
+    Standard,
+
+    /// Splits each word into character n-grams
+    /// "min_gram" must be at least 1 and not greater than "max_gram"
+    NGram {
+        min_gram: usize,
+        max_gram: usize,
+    },
+
+    /// Splits each word into character n-grams anchored to the start of the word
+    /// "min_gram" must be at least 1 and not greater than "max_gram"
+    EdgeNGram {
+        min_gram: usize,
+        max_gram: usize,
+    },
+
+    /// Splits Chinese, Japanese and Korean text into overlapping bigrams and
+    /// everything else on unicode word boundaries
+    CjkBigram,
+
+    /// Splits text on whitespace
+    Whitespace,
+
+    /// Splits text on matches of a regular expression
+    /// If "group" is set, the text of that capture group in each match is used as a token instead
+    Pattern {
+        pattern: String,
+
+        #[serde(default)]
+        group: Option<usize>,
+    },
+
+    /// Splits a path into the path of each of its ancestors (eg, "/a/b" becomes "/a" and "/a/b")
+    PathHierarchy {
+        #[serde(default = "default_path_delimiter")]
+        delimiter: char,
+    },
+
+    /// Splits text on unicode word boundaries, but keeps URLs and email addresses intact
+    UrlEmail,
+}
+
+fn default_path_delimiter() -> char {
+    '/'
+}
+
+/// Selects a token filter in an analyzer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TokenFilterConfig {
+    /// Converts tokens to lowercase
+    Lowercase,
+
+    /// Reduces words to their stem with the Snowball stemmer for the language
+    /// Tokens must be lowercased before this filter
+    Stemmer {
+        language: Language,
+    },
+
+    /// Removes stopwords, leaving gaps in the token positions where they were
+    /// Removes the built-in stopwords of the language (if set) and any words listed in "words"
+    Stop {
+        #[serde(default)]
+        language: Option<Language>,
+
+        #[serde(default)]
+        words: Vec<String>,
+    },
+
+    /// Applies Unicode normalization form KC
+    Nfkc,
+
+    /// Applies Unicode case folding (a more thorough version of lowercasing)
+    CaseFold,
+
+    /// Converts characters into their ASCII equivalents, removing accents
+    AsciiFolding,
+
+    /// Adds tokens made of runs of adjacent words, between "min_size" and "max_size" words long
+    /// "min_size" must be at least 2 and not greater than "max_size"
+    Shingle {
+        min_size: usize,
+        max_size: usize,
+
+        #[serde(default = "default_output_unigrams")]
+        output_unigrams: bool,
+
+        #[serde(default = "default_shingle_separator")]
+        separator: String,
+    },
+
+    /// Replaces words and sequences of words with their synonyms
+    /// Use analysis::filters::synonym::parse_synonyms to load the rules from a Solr synonym list
+    Synonym {
+        rules: Vec<SynonymRule>,
+    },
+
+    /// Converts tokens into phonetic codes
+    /// If "inject" is set, the codes are added alongside the original tokens instead of replacing them
+    Phonetic {
+        algorithm: PhoneticAlgorithm,
+
+        #[serde(default = "default_phonetic_inject")]
+        inject: bool,
+    },
+
+    /// Splits a payload off the end of each token (eg, "quick|2.0")
+    DelimitedPayload {
+        #[serde(default = "default_payload_delimiter")]
+        delimiter: char,
+
+        encoding: PayloadEncoding,
+    },
+}
+
+fn default_phonetic_inject() -> bool {
+    true
+}
+
+fn default_payload_delimiter() -> char {
+    '|'
+}
+
+fn default_output_unigrams() -> bool {
+    true
+}
+
+fn default_shingle_separator() -> String {
+    " ".to_string()
+}
+
+#[derive(Debug)]
+pub enum AnalyzerConfigError {
+    /// A regular expression in the config failed to compile
+    InvalidPattern(String, RegexError),
+
+    /// An n-gram tokenizer has a min_gram of zero or a min_gram greater than max_gram
+    InvalidNGramSize(usize, usize),
+
+    /// A shingle filter has a min_size less than 2 or a min_size greater than max_size
+    InvalidShingleSize(usize, usize),
+
+    /// A pattern tokenizer uses a capture group that isn't in its pattern (pattern, group)
+    InvalidPatternGroup(String, usize),
+}
+
+/// A description of an analyzer that can be stored in the schema
+///
+/// Call .build() to get an analyzer that can be run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalyzerConfig {
+    #[serde(default)]
+    pub char_filters: Vec<CharFilterConfig>,
+
+    pub tokenizer: TokenizerConfig,
+
+    #[serde(default)]
+    pub filters: Vec<TokenFilterConfig>,
+}
+
+impl AnalyzerConfig {
+    /// Creates a config with the specified tokenizer and no filters
+    pub fn new(tokenizer: TokenizerConfig) -> AnalyzerConfig {
+        AnalyzerConfig {
+            char_filters: Vec::new(),
+            tokenizer: tokenizer,
+            filters: Vec::new(),
+        }
+    }
+
+    /// The configuration of the standard analyzer
+    pub fn standard() -> AnalyzerConfig {
+        AnalyzerConfig::new(TokenizerConfig::Standard).filter(TokenFilterConfig::Lowercase)
+    }
+
+    /// Adds a char filter to the end of the chain of char filters
+    pub fn char_filter(mut self, char_filter: CharFilterConfig) -> AnalyzerConfig {
+        self.char_filters.push(char_filter);
+        self
+    }
+
+    /// Adds a filter to the end of the chain
+    pub fn filter(mut self, filter: TokenFilterConfig) -> AnalyzerConfig {
+        self.filters.push(filter);
+        self
+    }
+
+    /// Checks that the analyzer described by this config can be built
+    pub fn validate(&self) -> Result<(), AnalyzerConfigError> {
+        for char_filter in self.char_filters.iter() {
+            if let CharFilterConfig::PatternReplace{ref pattern, ..} = *char_filter {
+                if let Err(e) = Regex::new(pattern) {
+                    return Err(AnalyzerConfigError::InvalidPattern(pattern.clone(), e));
+                }
+            }
+        }
+
+        match self.tokenizer {
+            TokenizerConfig::NGram{min_gram, max_gram} | TokenizerConfig::EdgeNGram{min_gram, max_gram} => {
+                if NGramTokenizer::try_new(min_gram, max_gram).is_none() {
+                    return Err(AnalyzerConfigError::InvalidNGramSize(min_gram, max_gram));
+                }
+            }
+            TokenizerConfig::Pattern{ref pattern, group} => {
+                let regex = match Regex::new(pattern) {
+                    Ok(regex) => regex,
+                    Err(e) => return Err(AnalyzerConfigError::InvalidPattern(pattern.clone(), e)),
+                };
+
+                if let Some(group) = group {
+                    if group >= regex.captures_len() {
+                        return Err(AnalyzerConfigError::InvalidPatternGroup(pattern.clone(), group));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        for filter in self.filters.iter() {
+            if let TokenFilterConfig::Shingle{min_size, max_size, ..} = *filter {
+                if ShingleFilter::try_new(min_size, max_size).is_none() {
+                    return Err(AnalyzerConfigError::InvalidShingleSize(min_size, max_size));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Builds the analyzer described by this config
+    ///
+    /// Panics if the config is invalid (see .validate())
+    pub fn build(&self) -> ChainAnalyzer {
+        let mut analyzer = match self.tokenizer {
+            TokenizerConfig::Standard => ChainAnalyzer::new(StandardTokenizer),
+            TokenizerConfig::NGram{min_gram, max_gram} => {
+                ChainAnalyzer::new(NGramTokenizer::try_new(min_gram, max_gram).expect("invalid n-gram sizes in analyzer config"))
+            }
+            TokenizerConfig::EdgeNGram{min_gram, max_gram} => {
+                ChainAnalyzer::new(EdgeNGramTokenizer::try_new(min_gram, max_gram).expect("invalid n-gram sizes in analyzer config"))
+            }
+            TokenizerConfig::CjkBigram => ChainAnalyzer::new(CjkBigramTokenizer),
+            TokenizerConfig::Whitespace => ChainAnalyzer::new(WhitespaceTokenizer),
+            TokenizerConfig::Pattern{ref pattern, group} => {
+                let tokenizer = match group {
+                    Some(group) => PatternTokenizer::with_group(pattern, group),
+                    None => PatternTokenizer::new(pattern),
+                };
+
+                ChainAnalyzer::new(tokenizer.expect("invalid pattern in analyzer config"))
+            }
+            TokenizerConfig::PathHierarchy{delimiter} => ChainAnalyzer::new(PathHierarchyTokenizer::new(delimiter)),
+            TokenizerConfig::UrlEmail => ChainAnalyzer::new(UrlEmailTokenizer::new()),
+        };
+
+        for char_filter in self.char_filters.iter() {
+            analyzer = match *char_filter {
+                CharFilterConfig::HtmlStrip => analyzer.char_filter(HtmlStripCharFilter),
+                CharFilterConfig::PatternReplace{ref pattern, ref replacement} => {
+                    analyzer.char_filter(PatternReplaceCharFilter::new(pattern, replacement).expect("invalid pattern in analyzer config"))
+                }
+            };
+        }
+
+        for filter in self.filters.iter() {
+            analyzer = match *filter {
+                TokenFilterConfig::Lowercase => analyzer.filter(LowercaseFilter),
+                TokenFilterConfig::Stemmer{language} => analyzer.filter(StemmerFilter::new(language)),
+                TokenFilterConfig::Stop{language, ref words} => {
+                    let filter = match language {
+                        Some(language) => StopFilter::for_language(language),
+                        None => StopFilter::new(Vec::<String>::new()),
+                    };
+
+                    analyzer.filter(filter.with_words(words.iter().cloned()))
+                }
+                TokenFilterConfig::Nfkc => analyzer.filter(NfkcFilter),
+                TokenFilterConfig::CaseFold => analyzer.filter(CaseFoldFilter),
+                TokenFilterConfig::AsciiFolding => analyzer.filter(AsciiFoldingFilter),
+                TokenFilterConfig::Shingle{min_size, max_size, output_unigrams, ref separator} => {
+                    let filter = ShingleFilter::try_new(min_size, max_size).expect("invalid shingle sizes in analyzer config");
+                    analyzer.filter(filter.output_unigrams(output_unigrams).separator(separator.clone()))
+                }
+                TokenFilterConfig::Synonym{ref rules} => analyzer.filter(SynonymFilter::new(rules)),
+                TokenFilterConfig::Phonetic{algorithm, inject} => analyzer.filter(PhoneticFilter::new(algorithm).inject(inject)),
+                TokenFilterConfig::DelimitedPayload{delimiter, encoding} => analyzer.filter(DelimitedPayloadFilter::new(delimiter, encoding)),
+            };
+        }
+
+        analyzer
+    }
+}
+
+impl Default for AnalyzerConfig {
+    fn default() -> AnalyzerConfig {
+        AnalyzerConfig::standard()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use analysis::{Analyzer, Language};
+    use schema::{Schema, FieldType, AddFieldError, FIELD_INDEXED};
+    use super::{AnalyzerConfig, AnalyzerConfigError, CharFilterConfig, TokenizerConfig, TokenFilterConfig};
+
+    #[test]
+    fn test_build_standard() {
+        let analyzer = AnalyzerConfig::standard().build();
+
+        let terms = analyzer.analyze("Hello World").into_iter().map(|token| token.term).collect::<Vec<Term>>();
+        assert_eq!(terms, vec![Term::from_string("hello"), Term::from_string("world")]);
+    }
+
+    #[test]
+    fn test_build_without_filters() {
+        let analyzer = AnalyzerConfig::new(TokenizerConfig::Standard).build();
+
+        let terms = analyzer.analyze("Hello World").into_iter().map(|token| token.term).collect::<Vec<Term>>();
+        assert_eq!(terms, vec![Term::from_string("Hello"), Term::from_string("World")]);
+    }
+
+    #[test]
+    fn test_build_stop() {
+        let analyzer = AnalyzerConfig::standard()
+            .filter(TokenFilterConfig::Stop {
+                language: Some(Language::English),
+                words: vec!["quick".to_string()],
+            })
+            .build();
+
+        let tokens = analyzer.analyze("The quick brown fox");
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].term, Term::from_string("brown"));
+        assert_eq!(tokens[0].position, 3);
+        assert_eq!(tokens[1].term, Term::from_string("fox"));
+        assert_eq!(tokens[1].position, 4);
+    }
+
+    #[test]
+    fn test_build_folding() {
+        let analyzer = AnalyzerConfig::new(TokenizerConfig::Standard)
+            .filter(TokenFilterConfig::Nfkc)
+            .filter(TokenFilterConfig::CaseFold)
+            .filter(TokenFilterConfig::AsciiFolding)
+            .build();
+
+        // All variants of "café" must produce the same term
+        for text in vec!["Café", "cafe", "CAFÉ", "Cafe\u{301}", "\u{ff23}\u{ff21}\u{ff26}\u{ff25}"] {
+            let tokens = analyzer.analyze(text);
+            assert_eq!(tokens.len(), 1);
+            assert_eq!(tokens[0].term, Term::from_string("cafe"));
+        }
+    }
+
+    #[test]
+    fn test_build_edge_ngram() {
+        let analyzer = AnalyzerConfig::new(TokenizerConfig::EdgeNGram { min_gram: 2, max_gram: 4 })
+            .filter(TokenFilterConfig::Lowercase)
+            .build();
+
+        let terms = analyzer.analyze("New York").into_iter().map(|token| token.term).collect::<Vec<Term>>();
+        assert_eq!(terms, vec![
+            Term::from_string("ne"),
+            Term::from_string("new"),
+            Term::from_string("yo"),
+            Term::from_string("yor"),
+            Term::from_string("york"),
+        ]);
+    }
+
+    #[test]
+    fn test_build_unbounded_ngram() {
+        let config = AnalyzerConfig::new(TokenizerConfig::NGram { min_gram: 1, max_gram: usize::MAX });
+        assert!(config.validate().is_ok());
+
+        let terms = config.build().analyze("ab").into_iter().map(|token| token.term).collect::<Vec<Term>>();
+        assert_eq!(terms, vec![Term::from_string("a"), Term::from_string("ab"), Term::from_string("b")]);
+    }
+
+    #[test]
+    fn test_build_char_filters() {
+        let analyzer = AnalyzerConfig::standard()
+            .char_filter(CharFilterConfig::HtmlStrip)
+            .char_filter(CharFilterConfig::PatternReplace { pattern: r"(\d+)-(\d+)".to_string(), replacement: "$1$2".to_string() })
+            .build();
+
+        let terms = analyzer.analyze("<p>Call&nbsp;555-1234</p>").into_iter().map(|token| token.term).collect::<Vec<Term>>();
+        assert_eq!(terms, vec![Term::from_string("call"), Term::from_string("5551234")]);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(AnalyzerConfig::standard().validate().is_ok());
+
+        match AnalyzerConfig::standard().char_filter(CharFilterConfig::PatternReplace { pattern: "(".to_string(), replacement: "".to_string() }).validate() {
+            Err(AnalyzerConfigError::InvalidPattern(pattern, _)) => assert_eq!(pattern, "("),
+            result => panic!("expected InvalidPattern error, got {:?}", result),
+        }
+
+        match AnalyzerConfig::new(TokenizerConfig::NGram { min_gram: 3, max_gram: 2 }).validate() {
+            Err(AnalyzerConfigError::InvalidNGramSize(3, 2)) => {}
+            result => panic!("expected InvalidNGramSize error, got {:?}", result),
+        }
+
+        match AnalyzerConfig::new(TokenizerConfig::EdgeNGram { min_gram: 0, max_gram: 2 }).validate() {
+            Err(AnalyzerConfigError::InvalidNGramSize(0, 2)) => {}
+            result => panic!("expected InvalidNGramSize error, got {:?}", result),
+        }
+
+        match AnalyzerConfig::standard().filter(TokenFilterConfig::Shingle { min_size: 1, max_size: 2, output_unigrams: true, separator: " ".to_string() }).validate() {
+            Err(AnalyzerConfigError::InvalidShingleSize(1, 2)) => {}
+            result => panic!("expected InvalidShingleSize error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_validate_pattern_group() {
+        assert!(AnalyzerConfig::new(TokenizerConfig::Pattern { pattern: r"(\w+)@(\w+)".to_string(), group: Some(2) }).validate().is_ok());
+
+        match AnalyzerConfig::new(TokenizerConfig::Pattern { pattern: r"(\w+)@(\w+)".to_string(), group: Some(3) }).validate() {
+            Err(AnalyzerConfigError::InvalidPatternGroup(pattern, 3)) => assert_eq!(pattern, r"(\w+)@(\w+)"),
+            result => panic!("expected InvalidPatternGroup error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_add_field_invalid_ngram() {
+        let mut schema = Schema::new();
+        let analyzer = AnalyzerConfig::new(TokenizerConfig::NGram { min_gram: 0, max_gram: 3 });
+
+        match schema.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED, Some(analyzer)) {
+            Err(AddFieldError::InvalidAnalyzer(AnalyzerConfigError::InvalidNGramSize(0, 3))) => {}
+            result => panic!("expected InvalidAnalyzer error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_build_path_hierarchy() {
+        let analyzer = AnalyzerConfig::new(TokenizerConfig::PathHierarchy { delimiter: '/' }).build();
+
+        let terms = analyzer.analyze("/var/log/syslog").into_iter().map(|token| token.term).collect::<Vec<Term>>();
+        assert_eq!(terms, vec![Term::from_string("/var"), Term::from_string("/var/log"), Term::from_string("/var/log/syslog")]);
+    }
+}
@@ -0,0 +1,127 @@
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+use token::Token;
+use analysis::TokenFilter;
+use analysis::filters::map_token_text;
+
+/// Returns the ASCII replacement of characters that don't decompose into an
+/// ASCII base character and combining marks
+fn fold_special_char(c: char) -> Option<&'static str> {
+    Some(match c {
+        'Æ' | 'Ǽ' | 'Ǣ' => "AE",
+        'æ' | 'ǽ' | 'ǣ' => "ae",
+        'Œ' => "OE",
+        'œ' => "oe",
+        'Ø' | 'Ǿ' => "O",
+        'ø' | 'ǿ' => "o",
+        'Đ' | 'Ð' => "D",
+        'đ' | 'ð' => "d",
+        'Ħ' => "H",
+        'ħ' => "h",
+        'ı' => "i",
+        'Ł' => "L",
+        'ł' => "l",
+        'Ŋ' => "N",
+        'ŋ' => "n",
+        'Þ' => "TH",
+        'þ' => "th",
+        'ß' => "ss",
+        'ẞ' => "SS",
+        'Ŧ' => "T",
+        'ŧ' => "t",
+        'ĸ' => "q",
+        '‘' | '’' | '‚' | '‛' | '′' => "'",
+        '“' | '”' | '„' | '‟' | '″' | '«' | '»' => "\"",
+        '‐' | '‑' | '‒' | '–' | '—' | '―' => "-",
+        _ => return None,
+    })
+}
+
+/// Converts alphabetic, numeric and symbolic characters into their ASCII
+/// equivalents, if one exists
+///
+/// Accents are removed from accented letters (eg, "é" becomes "e") and
+/// letters such as "æ" and "ø" are replaced with "ae" and "o". Characters
+/// without an ASCII equivalent are left as they are.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsciiFoldingFilter;
+
+impl TokenFilter for AsciiFoldingFilter {
+    fn filter(&self, tokens: Vec<Token>) -> Vec<Token> {
+        map_token_text(tokens, |text| {
+            if text.is_ascii() {
+                return text.to_string();
+            }
+
+            let mut folded = String::with_capacity(text.len());
+            let mut last_folded = false;
+
+            for c in text.chars() {
+                // Drop combining marks that are attached to a character we folded
+                if last_folded && is_combining_mark(c) {
+                    continue;
+                }
+
+                last_folded = true;
+
+                if c.is_ascii() {
+                    folded.push(c);
+                    continue;
+                }
+
+                if let Some(replacement) = fold_special_char(c) {
+                    folded.push_str(replacement);
+                    continue;
+                }
+
+                // Decompose the character and strip off the combining marks
+                let mut decomposed = String::new();
+                decomposed.extend(Some(c).into_iter().nfkd());
+                let base = decomposed.chars().filter(|c| !is_combining_mark(*c)).collect::<String>();
+
+                if !base.is_empty() && base.is_ascii() {
+                    folded.push_str(&base);
+                } else {
+                    folded.push(c);
+                    last_folded = false;
+                }
+            }
+
+            folded
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use token::Token;
+    use analysis::TokenFilter;
+    use super::AsciiFoldingFilter;
+
+    fn fold(text: &str) -> Term {
+        let mut tokens = AsciiFoldingFilter.filter(vec![Token { term: Term::from_string(text), position: 1, byte_offsets: None, payload: None }]);
+        tokens.pop().unwrap().term
+    }
+
+    #[test]
+    fn test_ascii_folding_filter_strips_accents() {
+        assert_eq!(fold("café"), Term::from_string("cafe"));
+        assert_eq!(fold("Cafe\u{301}"), Term::from_string("Cafe"));
+        assert_eq!(fold("naïve"), Term::from_string("naive"));
+        assert_eq!(fold("Ångström"), Term::from_string("Angstrom"));
+    }
+
+    #[test]
+    fn test_ascii_folding_filter_special_letters() {
+        assert_eq!(fold("smørrebrød"), Term::from_string("smorrebrod"));
+        assert_eq!(fold("Æsir"), Term::from_string("AEsir"));
+        assert_eq!(fold("Łódź"), Term::from_string("Lodz"));
+    }
+
+    #[test]
+    fn test_ascii_folding_filter_leaves_other_scripts() {
+        assert_eq!(fold("こんにちは"), Term::from_string("こんにちは"));
+    }
+}
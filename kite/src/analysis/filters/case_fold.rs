@@ -0,0 +1,72 @@
+use token::Token;
+use analysis::TokenFilter;
+use analysis::filters::map_token_text;
+
+/// Folds the case of a single character, pushing the result onto "out"
+fn fold_char(c: char, out: &mut String) {
+    // Characters where full case folding differs from lowercasing
+    match c {
+        'ß' | 'ẞ' => out.push_str("ss"),
+        'ς' => out.push('σ'),
+        'ſ' => out.push('s'),
+        'ϐ' => out.push('β'),
+        'ϑ' => out.push('θ'),
+        'ϕ' => out.push('φ'),
+        'ϖ' => out.push('π'),
+        'ϰ' => out.push('κ'),
+        'ϱ' => out.push('ρ'),
+        'ϵ' => out.push('ε'),
+        'ẛ' => out.push('ṡ'),
+        '\u{1fbe}' => out.push('ι'),
+        'ﬀ' => out.push_str("ff"),
+        'ﬁ' => out.push_str("fi"),
+        'ﬂ' => out.push_str("fl"),
+        'ﬃ' => out.push_str("ffi"),
+        'ﬄ' => out.push_str("ffl"),
+        'ﬅ' | 'ﬆ' => out.push_str("st"),
+        _ => out.extend(c.to_lowercase()),
+    }
+}
+
+/// Applies Unicode case folding to the text of each token
+///
+/// This is a more thorough version of lowercasing intended for caseless
+/// matching. For example, it converts the German "ß" into "ss" and the Greek
+/// final sigma "ς" into "σ".
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseFoldFilter;
+
+impl TokenFilter for CaseFoldFilter {
+    fn filter(&self, tokens: Vec<Token>) -> Vec<Token> {
+        map_token_text(tokens, |text| {
+            let mut folded = String::with_capacity(text.len());
+
+            for c in text.chars() {
+                fold_char(c, &mut folded);
+            }
+
+            folded
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use token::Token;
+    use analysis::TokenFilter;
+    use super::CaseFoldFilter;
+
+    fn fold(text: &str) -> Term {
+        let mut tokens = CaseFoldFilter.filter(vec![Token { term: Term::from_string(text), position: 1, byte_offsets: None, payload: None }]);
+        tokens.pop().unwrap().term
+    }
+
+    #[test]
+    fn test_case_fold_filter() {
+        assert_eq!(fold("CAFÉ"), Term::from_string("café"));
+        assert_eq!(fold("Straße"), Term::from_string("strasse"));
+        assert_eq!(fold("STRASSE"), Term::from_string("strasse"));
+        assert_eq!(fold("ΣΟΦΟΣ"), fold("σοφος"));
+    }
+}
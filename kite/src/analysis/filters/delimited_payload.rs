@@ -0,0 +1,141 @@
+use std::str;
+
+use byteorder::{ByteOrder, BigEndian};
+
+use term::Term;
+use token::Token;
+use analysis::TokenFilter;
+
+/// Selects how the payloads of the delimited payload filter are encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PayloadEncoding {
+    /// A 32-bit big endian float
+    Float,
+
+    /// A 32-bit big endian signed integer
+    Integer,
+
+    /// The UTF-8 bytes of the payload text
+    Identity,
+}
+
+impl PayloadEncoding {
+    fn encode(&self, text: &str) -> Option<Vec<u8>> {
+        let mut buf = [0; 4];
+
+        match *self {
+            PayloadEncoding::Float => {
+                let value = match text.parse::<f32>() {
+                    Ok(value) => value,
+                    Err(_) => return None,
+                };
+
+                BigEndian::write_f32(&mut buf, value);
+                Some(buf.to_vec())
+            }
+            PayloadEncoding::Integer => {
+                let value = match text.parse::<i32>() {
+                    Ok(value) => value,
+                    Err(_) => return None,
+                };
+
+                BigEndian::write_i32(&mut buf, value);
+                Some(buf.to_vec())
+            }
+            PayloadEncoding::Identity => Some(text.as_bytes().to_vec()),
+        }
+    }
+}
+
+/// Splits a payload off the end of each token (eg, "quick|2.0" becomes "quick" with a payload of 2.0)
+///
+/// The text after the last delimiter is encoded and attached to the token as
+/// its payload. Tokens without a delimiter, or with a payload that can't be
+/// encoded, are left as they are. This must be used with a tokenizer that
+/// doesn't split on the delimiter, such as the whitespace tokenizer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DelimitedPayloadFilter {
+    delimiter: char,
+    encoding: PayloadEncoding,
+}
+
+impl DelimitedPayloadFilter {
+    pub fn new(delimiter: char, encoding: PayloadEncoding) -> DelimitedPayloadFilter {
+        DelimitedPayloadFilter {
+            delimiter: delimiter,
+            encoding: encoding,
+        }
+    }
+}
+
+impl TokenFilter for DelimitedPayloadFilter {
+    fn filter(&self, tokens: Vec<Token>) -> Vec<Token> {
+        tokens.into_iter()
+            .map(|mut token| {
+                let split = match str::from_utf8(token.term.as_bytes()) {
+                    Ok(text) => {
+                        text.rfind(self.delimiter).and_then(|delimiter_offset| {
+                            let payload_text = &text[delimiter_offset + self.delimiter.len_utf8()..];
+                            self.encoding.encode(payload_text).map(|payload| (text[..delimiter_offset].to_string(), payload))
+                        })
+                    }
+                    Err(_) => None,
+                };
+
+                if let Some((text, payload)) = split {
+                    // Trim the payload off the end of the offsets too
+                    token.byte_offsets = token.byte_offsets.map(|(start, end)| (start, end.min(start + text.len())));
+                    token.term = Term::from_string(&text);
+                    token.payload = Some(payload);
+                }
+
+                token
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{ByteOrder, BigEndian};
+
+    use term::Term;
+    use token::Token;
+    use term_vector::TermVector;
+    use analysis::{Analyzer, ChainAnalyzer};
+    use analysis::tokenizers::WhitespaceTokenizer;
+    use super::{DelimitedPayloadFilter, PayloadEncoding};
+
+    #[test]
+    fn test_delimited_payload_filter_float() {
+        let analyzer = ChainAnalyzer::new(WhitespaceTokenizer).filter(DelimitedPayloadFilter::new('|', PayloadEncoding::Float));
+        let tokens = analyzer.analyze("quick|2.5 fox");
+
+        assert_eq!(tokens[0].term, Term::from_string("quick"));
+        assert_eq!(tokens[0].byte_offsets, Some((0, 5)));
+        assert_eq!(BigEndian::read_f32(tokens[0].payload.as_ref().unwrap()), 2.5);
+        assert_eq!(tokens[1], Token { term: Term::from_string("fox"), position: 2, byte_offsets: Some((10, 13)), payload: None });
+
+        // Payloads are carried through the term vector
+        let term_vector: TermVector = tokens.into();
+        assert_eq!(BigEndian::read_f32(term_vector.get_payload(&Term::from_string("quick"), 1).unwrap()), 2.5);
+    }
+
+    #[test]
+    fn test_delimited_payload_filter_integer_and_identity() {
+        let tokens = ChainAnalyzer::new(WhitespaceTokenizer)
+            .filter(DelimitedPayloadFilter::new('|', PayloadEncoding::Integer))
+            .analyze("a|-3 b|x");
+
+        assert_eq!(BigEndian::read_i32(tokens[0].payload.as_ref().unwrap()), -3);
+        assert_eq!(tokens[1].term, Term::from_string("b|x"));
+        assert_eq!(tokens[1].payload, None);
+
+        let tokens = ChainAnalyzer::new(WhitespaceTokenizer)
+            .filter(DelimitedPayloadFilter::new('#', PayloadEncoding::Identity))
+            .analyze("a#NOUN");
+
+        assert_eq!(tokens[0].term, Term::from_string("a"));
+        assert_eq!(tokens[0].payload, Some(b"NOUN".to_vec()));
+    }
+}
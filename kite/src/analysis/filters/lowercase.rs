@@ -0,0 +1,47 @@
+use token::Token;
+use analysis::TokenFilter;
+use analysis::filters::map_token_text;
+
+/// Converts the text of each token to lowercase
+#[derive(Debug, Clone, PartialEq)]
+pub struct LowercaseFilter;
+
+impl TokenFilter for LowercaseFilter {
+    fn filter(&self, tokens: Vec<Token>) -> Vec<Token> {
+        map_token_text(tokens, |text| text.to_lowercase())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use token::Token;
+    use analysis::TokenFilter;
+    use super::LowercaseFilter;
+
+    #[test]
+    fn test_lowercase_filter() {
+        let tokens = LowercaseFilter.filter(vec![
+            Token { term: Term::from_string("Hello"), position: 1, byte_offsets: None, payload: None },
+            Token { term: Term::from_string("WORLD"), position: 3, byte_offsets: None, payload: None },
+            Token { term: Term::from_string("ÉCOLE"), position: 4, byte_offsets: None, payload: None },
+        ]);
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("hello"), position: 1, byte_offsets: None, payload: None },
+            Token { term: Term::from_string("world"), position: 3, byte_offsets: None, payload: None },
+            Token { term: Term::from_string("école"), position: 4, byte_offsets: None, payload: None },
+        ]);
+    }
+
+    #[test]
+    fn test_lowercase_filter_passes_through_binary_terms() {
+        let tokens = LowercaseFilter.filter(vec![
+            Token { term: Term::from_bytes(&[0xFF, b'A']), position: 1, byte_offsets: None, payload: None },
+        ]);
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_bytes(&[0xFF, b'A']), position: 1, byte_offsets: None, payload: None },
+        ]);
+    }
+}
@@ -0,0 +1,43 @@
+pub mod lowercase;
+pub mod stemmer;
+pub mod stop;
+pub mod nfkc;
+pub mod case_fold;
+pub mod ascii_folding;
+pub mod shingle;
+pub mod synonym;
+pub mod phonetic;
+pub mod delimited_payload;
+
+pub use analysis::filters::lowercase::LowercaseFilter;
+pub use analysis::filters::stemmer::StemmerFilter;
+pub use analysis::filters::stop::StopFilter;
+pub use analysis::filters::nfkc::NfkcFilter;
+pub use analysis::filters::case_fold::CaseFoldFilter;
+pub use analysis::filters::ascii_folding::AsciiFoldingFilter;
+pub use analysis::filters::shingle::ShingleFilter;
+pub use analysis::filters::synonym::{SynonymFilter, SynonymRule};
+pub use analysis::filters::phonetic::{PhoneticFilter, PhoneticAlgorithm};
+pub use analysis::filters::delimited_payload::{DelimitedPayloadFilter, PayloadEncoding};
+
+use std::str;
+
+use term::Term;
+use token::Token;
+
+/// Replaces the term of each token with the result of calling "f" on its text
+///
+/// Tokens that aren't valid UTF-8 are passed through untouched.
+fn map_token_text<F: Fn(&str) -> String>(tokens: Vec<Token>, f: F) -> Vec<Token> {
+    tokens.into_iter()
+        .map(|mut token| {
+            let new_text = match str::from_utf8(token.term.as_bytes()) {
+                Ok(text) => f(text),
+                Err(_) => return token,
+            };
+
+            token.term = Term::from_string(&new_text);
+            token
+        })
+        .collect()
+}
@@ -0,0 +1,43 @@
+use unicode_normalization::UnicodeNormalization;
+
+use token::Token;
+use analysis::TokenFilter;
+use analysis::filters::map_token_text;
+
+/// Converts the text of each token into Unicode normalization form KC
+///
+/// This makes characters that are encoded differently but represent the same
+/// text produce the same term. For example, a precomposed "é" and an "e"
+/// followed by a combining acute accent, or the "ﬁ" ligature and "fi".
+#[derive(Debug, Clone, PartialEq)]
+pub struct NfkcFilter;
+
+impl TokenFilter for NfkcFilter {
+    fn filter(&self, tokens: Vec<Token>) -> Vec<Token> {
+        map_token_text(tokens, |text| text.nfkc().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use token::Token;
+    use analysis::TokenFilter;
+    use super::NfkcFilter;
+
+    fn normalize(text: &str) -> Term {
+        let mut tokens = NfkcFilter.filter(vec![Token { term: Term::from_string(text), position: 1, byte_offsets: None, payload: None }]);
+        tokens.pop().unwrap().term
+    }
+
+    #[test]
+    fn test_nfkc_filter_composes_accents() {
+        assert_eq!(normalize("Cafe\u{301}"), Term::from_string("Caf\u{e9}"));
+    }
+
+    #[test]
+    fn test_nfkc_filter_compatibility_characters() {
+        assert_eq!(normalize("\u{fb01}le"), Term::from_string("file"));
+        assert_eq!(normalize("\u{ff23}\u{ff21}\u{ff26}\u{ff25}"), Term::from_string("CAFE"));
+    }
+}
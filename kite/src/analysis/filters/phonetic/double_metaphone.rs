@@ -0,0 +1,621 @@
+/// The maximum length of a Double Metaphone code
+const MAX_CODE_LENGTH: usize = 4;
+
+const L_R_N_M_B_H_F_V_W_SPACE: &'static [&'static str] = &["L", "R", "N", "M", "B", "H", "F", "V", "W", " "];
+const ES_EP_EB_EL_EY_IB_IL_IN_IE_EI_ER: &'static [&'static str] = &["ES", "EP", "EB", "EL", "EY", "IB", "IL", "IN", "IE", "EI", "ER"];
+const L_T_K_S_N_M_B_Z: &'static [&'static str] = &["L", "T", "K", "S", "N", "M", "B", "Z"];
+
+struct Encoder {
+    value: Vec<char>,
+    slavo_germanic: bool,
+    primary: String,
+    alternate: String,
+}
+
+impl Encoder {
+    fn new(word: &str) -> Encoder {
+        let value = word.trim().to_uppercase().chars().collect::<Vec<char>>();
+        let text = value.iter().cloned().collect::<String>();
+
+        Encoder {
+            slavo_germanic: text.contains('W') || text.contains('K') || text.contains("CZ") || text.contains("WITZ"),
+            value: value,
+            primary: String::with_capacity(MAX_CODE_LENGTH),
+            alternate: String::with_capacity(MAX_CODE_LENGTH),
+        }
+    }
+
+    fn len(&self) -> isize {
+        self.value.len() as isize
+    }
+
+    fn is_complete(&self) -> bool {
+        self.primary.len() >= MAX_CODE_LENGTH && self.alternate.len() >= MAX_CODE_LENGTH
+    }
+
+    fn char_at(&self, index: isize) -> char {
+        if index < 0 || index >= self.len() {
+            return '\0';
+        }
+
+        self.value[index as usize]
+    }
+
+    fn is_vowel(&self, index: isize) -> bool {
+        match self.char_at(index) {
+            'A' | 'E' | 'I' | 'O' | 'U' | 'Y' => true,
+            _ => false,
+        }
+    }
+
+    fn is_last(&self, index: isize) -> bool {
+        index == self.len() - 1
+    }
+
+    /// Checks if the "length" characters at "start" match any of the criteria
+    fn contains(&self, start: isize, length: isize, criteria: &[&str]) -> bool {
+        if start < 0 || start + length > self.len() {
+            return false;
+        }
+
+        let target = &self.value[start as usize..(start + length) as usize];
+        criteria.iter().any(|criterion| criterion.chars().eq(target.iter().cloned()))
+    }
+
+    fn append_primary(&mut self, code: &str) {
+        for c in code.chars() {
+            if self.primary.len() < MAX_CODE_LENGTH {
+                self.primary.push(c);
+            }
+        }
+    }
+
+    fn append_alternate(&mut self, code: &str) {
+        for c in code.chars() {
+            if self.alternate.len() < MAX_CODE_LENGTH {
+                self.alternate.push(c);
+            }
+        }
+    }
+
+    fn append(&mut self, code: &str) {
+        self.append_primary(code);
+        self.append_alternate(code);
+    }
+
+    fn append_both(&mut self, primary: &str, alternate: &str) {
+        self.append_primary(primary);
+        self.append_alternate(alternate);
+    }
+
+    /// Moves past the current character, and the next one too if it's the same letter
+    fn skip_double(&self, index: isize, letters: &[&str]) -> isize {
+        if self.contains(index + 1, 1, letters) {
+            index + 2
+        } else {
+            index + 1
+        }
+    }
+
+    fn encode(mut self) -> (String, String) {
+        let mut index = if self.contains(0, 2, &["GN", "KN", "PN", "WR", "PS"]) { 1 } else { 0 };
+
+        while !self.is_complete() && index < self.len() {
+            index = match self.char_at(index) {
+                'A' | 'E' | 'I' | 'O' | 'U' | 'Y' => {
+                    if index == 0 {
+                        self.append("A");
+                    }
+
+                    index + 1
+                }
+                'B' => {
+                    self.append("P");
+                    self.skip_double(index, &["B"])
+                }
+                'Ç' => {
+                    self.append("S");
+                    index + 1
+                }
+                'C' => self.handle_c(index),
+                'D' => self.handle_d(index),
+                'F' => {
+                    self.append("F");
+                    self.skip_double(index, &["F"])
+                }
+                'G' => self.handle_g(index),
+                'H' => self.handle_h(index),
+                'J' => self.handle_j(index),
+                'K' => {
+                    self.append("K");
+                    self.skip_double(index, &["K"])
+                }
+                'L' => self.handle_l(index),
+                'M' => {
+                    self.append("M");
+                    if self.condition_m0(index) { index + 2 } else { index + 1 }
+                }
+                'N' => {
+                    self.append("N");
+                    self.skip_double(index, &["N"])
+                }
+                'Ñ' => {
+                    self.append("N");
+                    index + 1
+                }
+                'P' => self.handle_p(index),
+                'Q' => {
+                    self.append("K");
+                    self.skip_double(index, &["Q"])
+                }
+                'R' => self.handle_r(index),
+                'S' => self.handle_s(index),
+                'T' => self.handle_t(index),
+                'V' => {
+                    self.append("F");
+                    self.skip_double(index, &["V"])
+                }
+                'W' => self.handle_w(index),
+                'X' => self.handle_x(index),
+                'Z' => self.handle_z(index),
+                _ => index + 1,
+            };
+        }
+
+        (self.primary, self.alternate)
+    }
+
+    fn handle_c(&mut self, index: isize) -> isize {
+        if self.condition_c0(index) {
+            // Germanic "ACH" (eg, "Bacher")
+            self.append("K");
+            index + 2
+        } else if index == 0 && self.contains(index, 6, &["CAESAR"]) {
+            self.append("S");
+            index + 2
+        } else if self.contains(index, 2, &["CH"]) {
+            self.handle_ch(index)
+        } else if self.contains(index, 2, &["CZ"]) && !self.contains(index - 2, 4, &["WICZ"]) {
+            self.append_both("S", "X");
+            index + 2
+        } else if self.contains(index + 1, 3, &["CIA"]) {
+            self.append("X");
+            index + 3
+        } else if self.contains(index, 2, &["CC"]) && !(index == 1 && self.char_at(0) == 'M') {
+            self.handle_cc(index)
+        } else if self.contains(index, 2, &["CK", "CG", "CQ"]) {
+            self.append("K");
+            index + 2
+        } else if self.contains(index, 2, &["CI", "CE", "CY"]) {
+            if self.contains(index, 3, &["CIO", "CIE", "CIA"]) {
+                self.append_both("S", "X");
+            } else {
+                self.append("S");
+            }
+
+            index + 2
+        } else {
+            self.append("K");
+
+            if self.contains(index + 1, 2, &[" C", " Q", " G"]) {
+                index + 3
+            } else if self.contains(index + 1, 1, &["C", "K", "Q"]) && !self.contains(index + 1, 2, &["CE", "CI"]) {
+                index + 2
+            } else {
+                index + 1
+            }
+        }
+    }
+
+    fn handle_cc(&mut self, index: isize) -> isize {
+        if self.contains(index + 2, 1, &["I", "E", "H"]) && !self.contains(index + 2, 2, &["HU"]) {
+            if (index == 1 && self.char_at(index - 1) == 'A') || self.contains(index - 1, 5, &["UCCEE", "UCCES"]) {
+                // "Accident", "Succeed"
+                self.append("KS");
+            } else {
+                // "Bacci", "Bertucci"
+                self.append("X");
+            }
+
+            index + 3
+        } else {
+            self.append("K");
+            index + 2
+        }
+    }
+
+    fn handle_ch(&mut self, index: isize) -> isize {
+        if index > 0 && self.contains(index, 4, &["CHAE"]) {
+            // "Michael"
+            self.append_both("K", "X");
+        } else if self.condition_ch0(index) || self.condition_ch1(index) {
+            // Greek roots (eg, "Chemistry") and Germanic "CH"
+            self.append("K");
+        } else if index > 0 {
+            if self.contains(0, 2, &["MC"]) {
+                self.append("K");
+            } else {
+                self.append_both("X", "K");
+            }
+        } else {
+            self.append("X");
+        }
+
+        index + 2
+    }
+
+    fn handle_d(&mut self, index: isize) -> isize {
+        if self.contains(index, 2, &["DG"]) {
+            if self.contains(index + 2, 1, &["I", "E", "Y"]) {
+                // "Edge"
+                self.append("J");
+                index + 3
+            } else {
+                // "Edgar"
+                self.append("TK");
+                index + 2
+            }
+        } else if self.contains(index, 2, &["DT", "DD"]) {
+            self.append("T");
+            index + 2
+        } else {
+            self.append("T");
+            index + 1
+        }
+    }
+
+    fn handle_g(&mut self, index: isize) -> isize {
+        if self.char_at(index + 1) == 'H' {
+            self.handle_gh(index)
+        } else if self.char_at(index + 1) == 'N' {
+            if index == 1 && self.is_vowel(0) && !self.slavo_germanic {
+                self.append_both("KN", "N");
+            } else if !self.contains(index + 2, 2, &["EY"]) && self.char_at(index + 1) != 'Y' && !self.slavo_germanic {
+                self.append_both("N", "KN");
+            } else {
+                self.append("KN");
+            }
+
+            index + 2
+        } else if self.contains(index + 1, 2, &["LI"]) && !self.slavo_germanic {
+            self.append_both("KL", "L");
+            index + 2
+        } else if index == 0 && (self.char_at(index + 1) == 'Y' || self.contains(index + 1, 2, ES_EP_EB_EL_EY_IB_IL_IN_IE_EI_ER)) {
+            self.append_both("K", "J");
+            index + 2
+        } else if (self.contains(index + 1, 2, &["ER"]) || self.char_at(index + 1) == 'Y') &&
+                  !self.contains(0, 6, &["DANGER", "RANGER", "MANGER"]) &&
+                  !self.contains(index - 1, 1, &["E", "I"]) &&
+                  !self.contains(index - 1, 3, &["RGY", "OGY"]) {
+            self.append_both("K", "J");
+            index + 2
+        } else if self.contains(index + 1, 1, &["E", "I", "Y"]) || self.contains(index - 1, 4, &["AGGI", "OGGI"]) {
+            if self.contains(0, 4, &["VAN ", "VON "]) || self.contains(0, 3, &["SCH"]) || self.contains(index + 1, 2, &["ET"]) {
+                self.append("K");
+            } else if self.contains(index + 1, 3, &["IER"]) {
+                self.append("J");
+            } else {
+                self.append_both("J", "K");
+            }
+
+            index + 2
+        } else if self.char_at(index + 1) == 'G' {
+            self.append("K");
+            index + 2
+        } else {
+            self.append("K");
+            index + 1
+        }
+    }
+
+    fn handle_gh(&mut self, index: isize) -> isize {
+        if index > 0 && !self.is_vowel(index - 1) {
+            self.append("K");
+        } else if index == 0 {
+            if self.char_at(index + 2) == 'I' {
+                self.append("J");
+            } else {
+                self.append("K");
+            }
+        } else if (index > 1 && self.contains(index - 2, 1, &["B", "H", "D"])) ||
+                  (index > 2 && self.contains(index - 3, 1, &["B", "H", "D"])) ||
+                  (index > 3 && self.contains(index - 4, 1, &["B", "H"])) {
+            // Silent (eg, "Hugh", "Bough")
+        } else if index > 2 && self.char_at(index - 1) == 'U' && self.contains(index - 3, 1, &["C", "G", "L", "R", "T"]) {
+            // "Laugh", "Tough"
+            self.append("F");
+        } else if index > 0 && self.char_at(index - 1) != 'I' {
+            self.append("K");
+        }
+
+        index + 2
+    }
+
+    fn handle_h(&mut self, index: isize) -> isize {
+        // Only coded between vowels or at the start of the word before a vowel
+        if (index == 0 || self.is_vowel(index - 1)) && self.is_vowel(index + 1) {
+            self.append("H");
+            index + 2
+        } else {
+            index + 1
+        }
+    }
+
+    fn handle_j(&mut self, index: isize) -> isize {
+        if self.contains(index, 4, &["JOSE"]) || self.contains(0, 4, &["SAN "]) {
+            // Spanish pronounciation (eg, "Jose")
+            if (index == 0 && self.char_at(index + 4) == ' ') || self.len() == 4 || self.contains(0, 4, &["SAN "]) {
+                self.append("H");
+            } else {
+                self.append_both("J", "H");
+            }
+
+            return index + 1;
+        }
+
+        if index == 0 {
+            self.append_both("J", "A");
+        } else if self.is_vowel(index - 1) && !self.slavo_germanic && (self.char_at(index + 1) == 'A' || self.char_at(index + 1) == 'O') {
+            self.append_both("J", "H");
+        } else if self.is_last(index) {
+            self.append_primary("J");
+        } else if !self.contains(index + 1, 1, L_T_K_S_N_M_B_Z) && !self.contains(index - 1, 1, &["S", "K", "L"]) {
+            self.append("J");
+        }
+
+        self.skip_double(index, &["J"])
+    }
+
+    fn handle_l(&mut self, index: isize) -> isize {
+        if self.char_at(index + 1) == 'L' {
+            if self.condition_l0(index) {
+                // Spanish (eg, "Cabrillo")
+                self.append_primary("L");
+            } else {
+                self.append("L");
+            }
+
+            index + 2
+        } else {
+            self.append("L");
+            index + 1
+        }
+    }
+
+    fn handle_p(&mut self, index: isize) -> isize {
+        if self.char_at(index + 1) == 'H' {
+            self.append("F");
+            index + 2
+        } else {
+            self.append("P");
+            self.skip_double(index, &["P", "B"])
+        }
+    }
+
+    fn handle_r(&mut self, index: isize) -> isize {
+        if self.is_last(index) && !self.slavo_germanic && self.contains(index - 2, 2, &["IE"]) && !self.contains(index - 4, 2, &["ME", "MA"]) {
+            // French (eg, "Rogier")
+            self.append_alternate("R");
+        } else {
+            self.append("R");
+        }
+
+        self.skip_double(index, &["R"])
+    }
+
+    fn handle_s(&mut self, index: isize) -> isize {
+        if self.contains(index - 1, 3, &["ISL", "YSL"]) {
+            // Silent (eg, "Island", "Carlysle")
+            index + 1
+        } else if index == 0 && self.contains(index, 5, &["SUGAR"]) {
+            self.append_both("X", "S");
+            index + 1
+        } else if self.contains(index, 2, &["SH"]) {
+            if self.contains(index + 1, 4, &["HEIM", "HOEK", "HOLM", "HOLZ"]) {
+                self.append("S");
+            } else {
+                self.append("X");
+            }
+
+            index + 2
+        } else if self.contains(index, 3, &["SIO", "SIA"]) || self.contains(index, 4, &["SIAN"]) {
+            if self.slavo_germanic {
+                self.append("S");
+            } else {
+                self.append_both("S", "X");
+            }
+
+            index + 3
+        } else if (index == 0 && self.contains(index + 1, 1, &["M", "N", "L", "W"])) || self.contains(index + 1, 1, &["Z"]) {
+            self.append_both("S", "X");
+            self.skip_double(index, &["Z"])
+        } else if self.contains(index, 2, &["SC"]) {
+            self.handle_sc(index)
+        } else {
+            if self.is_last(index) && self.contains(index - 2, 2, &["AI", "OI"]) {
+                // French (eg, "Resnais")
+                self.append_alternate("S");
+            } else {
+                self.append("S");
+            }
+
+            self.skip_double(index, &["S", "Z"])
+        }
+    }
+
+    fn handle_sc(&mut self, index: isize) -> isize {
+        if self.char_at(index + 2) == 'H' {
+            if self.contains(index + 3, 2, &["OO", "ER", "EN", "UY", "ED", "EM"]) {
+                // Dutch origin (eg, "School")
+                if self.contains(index + 3, 2, &["ER", "EN"]) {
+                    self.append_both("X", "SK");
+                } else {
+                    self.append("SK");
+                }
+            } else if index == 0 && !self.is_vowel(3) && self.char_at(3) != 'W' {
+                self.append_both("X", "S");
+            } else {
+                self.append("X");
+            }
+        } else if self.contains(index + 2, 1, &["I", "E", "Y"]) {
+            self.append("S");
+        } else {
+            self.append("SK");
+        }
+
+        index + 3
+    }
+
+    fn handle_t(&mut self, index: isize) -> isize {
+        if self.contains(index, 4, &["TION"]) || self.contains(index, 3, &["TIA", "TCH"]) {
+            self.append("X");
+            index + 3
+        } else if self.contains(index, 2, &["TH"]) || self.contains(index, 3, &["TTH"]) {
+            if self.contains(index + 2, 2, &["OM", "AM"]) || self.contains(0, 4, &["VAN ", "VON "]) || self.contains(0, 3, &["SCH"]) {
+                self.append("T");
+            } else {
+                self.append_both("0", "T");
+            }
+
+            index + 2
+        } else {
+            self.append("T");
+            self.skip_double(index, &["T", "D"])
+        }
+    }
+
+    fn handle_w(&mut self, index: isize) -> isize {
+        if self.contains(index, 2, &["WR"]) {
+            self.append("R");
+            index + 2
+        } else if index == 0 && (self.is_vowel(index + 1) || self.contains(index, 2, &["WH"])) {
+            if self.is_vowel(index + 1) {
+                self.append_both("A", "F");
+            } else {
+                self.append("A");
+            }
+
+            index + 1
+        } else if (self.is_last(index) && self.is_vowel(index - 1)) ||
+                  self.contains(index - 1, 5, &["EWSKI", "EWSKY", "OWSKI", "OWSKY"]) ||
+                  self.contains(0, 3, &["SCH"]) {
+            // Polish (eg, "Filipowicz")
+            self.append_alternate("F");
+            index + 1
+        } else if self.contains(index, 4, &["WICZ", "WITZ"]) {
+            self.append_both("TS", "FX");
+            index + 4
+        } else {
+            index + 1
+        }
+    }
+
+    fn handle_x(&mut self, index: isize) -> isize {
+        if index == 0 {
+            self.append("S");
+            return index + 1;
+        }
+
+        // Silent in French endings (eg, "Breaux")
+        if !(self.is_last(index) && (self.contains(index - 3, 3, &["IAU", "EAU"]) || self.contains(index - 2, 2, &["AU", "OU"]))) {
+            self.append("KS");
+        }
+
+        self.skip_double(index, &["C", "X"])
+    }
+
+    fn handle_z(&mut self, index: isize) -> isize {
+        if self.char_at(index + 1) == 'H' {
+            // Chinese (eg, "Zhao")
+            self.append("J");
+            return index + 2;
+        }
+
+        if self.contains(index + 1, 2, &["ZO", "ZI", "ZA"]) || (self.slavo_germanic && index > 0 && self.char_at(index - 1) != 'T') {
+            self.append_both("S", "TS");
+        } else {
+            self.append("S");
+        }
+
+        self.skip_double(index, &["Z"])
+    }
+
+    fn condition_c0(&self, index: isize) -> bool {
+        if self.contains(index, 4, &["CHIA"]) {
+            true
+        } else if index <= 1 || self.is_vowel(index - 2) || !self.contains(index - 1, 3, &["ACH"]) {
+            false
+        } else {
+            let c = self.char_at(index + 2);
+            (c != 'I' && c != 'E') || self.contains(index - 2, 6, &["BACHER", "MACHER"])
+        }
+    }
+
+    fn condition_ch0(&self, index: isize) -> bool {
+        index == 0 &&
+            (self.contains(index + 1, 5, &["HARAC", "HARIS"]) || self.contains(index + 1, 3, &["HOR", "HYM", "HIA", "HEM"])) &&
+            !self.contains(0, 5, &["CHORE"])
+    }
+
+    fn condition_ch1(&self, index: isize) -> bool {
+        self.contains(0, 4, &["VAN ", "VON "]) ||
+            self.contains(0, 3, &["SCH"]) ||
+            self.contains(index - 2, 6, &["ORCHES", "ARCHIT", "ORCHID"]) ||
+            self.contains(index + 2, 1, &["T", "S"]) ||
+            ((self.contains(index - 1, 1, &["A", "O", "U", "E"]) || index == 0) &&
+             (self.contains(index + 2, 1, L_R_N_M_B_H_F_V_W_SPACE) || index + 1 == self.len() - 1))
+    }
+
+    fn condition_l0(&self, index: isize) -> bool {
+        if index == self.len() - 3 && self.contains(index - 1, 4, &["ILLO", "ILLA", "ALLE"]) {
+            true
+        } else {
+            (self.contains(self.len() - 2, 2, &["AS", "OS"]) || self.contains(self.len() - 1, 1, &["A", "O"])) &&
+                self.contains(index - 1, 4, &["ALLE"])
+        }
+    }
+
+    fn condition_m0(&self, index: isize) -> bool {
+        self.char_at(index + 1) == 'M' ||
+            (self.contains(index - 1, 3, &["UMB"]) && (index + 1 == self.len() - 1 || self.contains(index + 2, 2, &["ER"])))
+    }
+}
+
+/// Encodes a word using Double Metaphone
+///
+/// Returns the primary and alternate encodings of the word (these are often
+/// the same). Returns None if the word produces an empty code.
+pub fn double_metaphone(word: &str) -> Option<(String, String)> {
+    let (primary, alternate) = Encoder::new(word).encode();
+
+    if primary.is_empty() && alternate.is_empty() {
+        return None;
+    }
+
+    Some((primary, alternate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::double_metaphone;
+
+    fn encode(word: &str) -> (String, String) {
+        double_metaphone(word).unwrap()
+    }
+
+    #[test]
+    fn test_double_metaphone() {
+        assert_eq!(encode("Smith"), ("SM0".to_string(), "XMT".to_string()));
+        assert_eq!(encode("Smyth"), ("SM0".to_string(), "XMT".to_string()));
+        assert_eq!(encode("Schmidt"), ("XMT".to_string(), "SMT".to_string()));
+        assert_eq!(encode("Jose"), ("HS".to_string(), "HS".to_string()));
+        assert_eq!(encode("Knight"), ("NT".to_string(), "NT".to_string()));
+        assert_eq!(encode("Michael"), ("MKL".to_string(), "MXL".to_string()));
+    }
+
+    #[test]
+    fn test_double_metaphone_no_letters() {
+        assert_eq!(double_metaphone("123"), None);
+        assert_eq!(double_metaphone(""), None);
+    }
+}
@@ -0,0 +1,232 @@
+/// The maximum length of a Metaphone code
+const MAX_CODE_LENGTH: usize = 4;
+
+struct Word {
+    chars: Vec<char>,
+}
+
+impl Word {
+    fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    fn char_at(&self, index: usize) -> Option<char> {
+        self.chars.get(index).cloned()
+    }
+
+    fn is_vowel(&self, index: usize) -> bool {
+        match self.char_at(index) {
+            Some('A') | Some('E') | Some('I') | Some('O') | Some('U') => true,
+            _ => false,
+        }
+    }
+
+    fn is_front_vowel(&self, index: usize) -> bool {
+        match self.char_at(index) {
+            Some('E') | Some('I') | Some('Y') => true,
+            _ => false,
+        }
+    }
+
+    fn is_previous_char(&self, index: usize, c: char) -> bool {
+        index > 0 && self.char_at(index - 1) == Some(c)
+    }
+
+    fn is_next_char(&self, index: usize, c: char) -> bool {
+        self.char_at(index + 1) == Some(c)
+    }
+
+    fn is_last_char(&self, index: usize) -> bool {
+        index + 1 == self.len()
+    }
+
+    fn region_matches(&self, index: usize, test: &str) -> bool {
+        let mut i = index;
+
+        for c in test.chars() {
+            if self.char_at(i) != Some(c) {
+                return false;
+            }
+
+            i += 1;
+        }
+
+        true
+    }
+}
+
+/// Encodes a word using the original Metaphone algorithm (eg, "Smith" becomes "SM0")
+///
+/// Characters other than ASCII letters are ignored. Returns None if the word
+/// has no ASCII letters.
+pub fn metaphone(word: &str) -> Option<String> {
+    let mut chars = word.chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| c.to_ascii_uppercase())
+        .collect::<Vec<char>>();
+
+    if chars.is_empty() {
+        return None;
+    }
+
+    if chars.len() == 1 {
+        return Some(chars[0].to_string());
+    }
+
+    // Transform the start of the word
+    match (chars[0], chars[1]) {
+        ('K', 'N') | ('G', 'N') | ('P', 'N') | ('A', 'E') | ('W', 'R') => {
+            chars.remove(0);
+        }
+        ('W', 'H') => {
+            chars.remove(1);
+        }
+        ('X', _) => {
+            chars[0] = 'S';
+        }
+        _ => {}
+    }
+
+    let word = Word { chars: chars };
+    let mut code = String::with_capacity(MAX_CODE_LENGTH);
+    let mut n = 0;
+
+    while code.len() < MAX_CODE_LENGTH && n < word.len() {
+        let symbol = word.chars[n];
+
+        // Skip double letters, except for "C"
+        if symbol != 'C' && word.is_previous_char(n, symbol) {
+            n += 1;
+            continue;
+        }
+
+        match symbol {
+            'A' | 'E' | 'I' | 'O' | 'U' => {
+                if n == 0 {
+                    code.push(symbol);
+                }
+            }
+            'B' => {
+                // Silent in "-MB"
+                if !(word.is_previous_char(n, 'M') && word.is_last_char(n)) {
+                    code.push('B');
+                }
+            }
+            'C' => {
+                if word.is_previous_char(n, 'S') && !word.is_last_char(n) && word.is_front_vowel(n + 1) {
+                    // Silent in "SCI", "SCE" and "SCY"
+                } else if word.region_matches(n, "CIA") {
+                    code.push('X');
+                } else if !word.is_last_char(n) && word.is_front_vowel(n + 1) {
+                    code.push('S');
+                } else if word.is_previous_char(n, 'S') && word.is_next_char(n, 'H') {
+                    code.push('K');
+                } else if word.is_next_char(n, 'H') {
+                    if n == 0 && word.len() >= 3 && word.is_vowel(2) {
+                        code.push('K');
+                    } else {
+                        code.push('X');
+                    }
+                } else {
+                    code.push('K');
+                }
+            }
+            'D' => {
+                if word.is_next_char(n, 'G') && word.is_front_vowel(n + 2) {
+                    code.push('J');
+                    n += 2;
+                } else {
+                    code.push('T');
+                }
+            }
+            'G' => {
+                let silent = (word.is_next_char(n, 'H') && (word.is_last_char(n + 1) || !word.is_vowel(n + 2))) ||
+                             (n > 0 && (word.region_matches(n, "GN") || word.region_matches(n, "GNED")));
+
+                if !silent {
+                    if word.is_front_vowel(n + 1) && !word.is_previous_char(n, 'G') {
+                        code.push('J');
+                    } else {
+                        code.push('K');
+                    }
+                }
+            }
+            'H' => {
+                let after_varson = match n.checked_sub(1).and_then(|i| word.char_at(i)) {
+                    Some('C') | Some('S') | Some('P') | Some('T') | Some('G') => true,
+                    _ => false,
+                };
+
+                if !word.is_last_char(n) && !after_varson && word.is_vowel(n + 1) {
+                    code.push('H');
+                }
+            }
+            'K' => {
+                if !word.is_previous_char(n, 'C') {
+                    code.push('K');
+                }
+            }
+            'P' => {
+                if word.is_next_char(n, 'H') {
+                    code.push('F');
+                } else {
+                    code.push('P');
+                }
+            }
+            'Q' => code.push('K'),
+            'S' => {
+                if word.region_matches(n, "SH") || word.region_matches(n, "SIO") || word.region_matches(n, "SIA") {
+                    code.push('X');
+                } else {
+                    code.push('S');
+                }
+            }
+            'T' => {
+                if word.region_matches(n, "TIA") || word.region_matches(n, "TIO") {
+                    code.push('X');
+                } else if word.region_matches(n, "TCH") {
+                    // Silent, the "CH" is coded instead
+                } else if word.region_matches(n, "TH") {
+                    code.push('0');
+                } else {
+                    code.push('T');
+                }
+            }
+            'V' => code.push('F'),
+            'W' | 'Y' => {
+                if !word.is_last_char(n) && word.is_vowel(n + 1) {
+                    code.push(symbol);
+                }
+            }
+            'X' => code.push_str("KS"),
+            'Z' => code.push('S'),
+            _ => code.push(symbol),
+        }
+
+        n += 1;
+    }
+
+    code.truncate(MAX_CODE_LENGTH);
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::metaphone;
+
+    #[test]
+    fn test_metaphone() {
+        assert_eq!(metaphone("Smith"), Some("SM0".to_string()));
+        assert_eq!(metaphone("Smyth"), Some("SM0".to_string()));
+        assert_eq!(metaphone("Knight"), Some("NT".to_string()));
+        assert_eq!(metaphone("Thompson"), Some("0MPS".to_string()));
+        assert_eq!(metaphone("Wright"), Some("RT".to_string()));
+        assert_eq!(metaphone("Xavier"), Some("SFR".to_string()));
+        assert_eq!(metaphone("Philip"), Some("FLP".to_string()));
+    }
+
+    #[test]
+    fn test_metaphone_no_letters() {
+        assert_eq!(metaphone("42"), None);
+    }
+}
@@ -0,0 +1,163 @@
+//! Phonetic encoding
+//!
+//! Phonetic algorithms convert words into codes that represent how they sound
+//! so words that sound similar (eg, "Smith" and "Smyth") produce the same code.
+
+pub mod soundex;
+pub mod metaphone;
+pub mod double_metaphone;
+
+use std::str;
+
+use term::Term;
+use token::Token;
+use analysis::TokenFilter;
+use analysis::filters::phonetic::soundex::soundex;
+use analysis::filters::phonetic::metaphone::metaphone;
+use analysis::filters::phonetic::double_metaphone::double_metaphone;
+
+/// Selects the algorithm used by the phonetic filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PhoneticAlgorithm {
+    Soundex,
+    Metaphone,
+
+    /// Emits both the primary and alternate encodings (if they differ)
+    DoubleMetaphone,
+}
+
+impl PhoneticAlgorithm {
+    /// Returns the phonetic codes of a word
+    pub fn encode(&self, word: &str) -> Vec<String> {
+        match *self {
+            PhoneticAlgorithm::Soundex => soundex(word).into_iter().collect(),
+            PhoneticAlgorithm::Metaphone => metaphone(word).into_iter().collect(),
+            PhoneticAlgorithm::DoubleMetaphone => {
+                match double_metaphone(word) {
+                    Some((primary, alternate)) => {
+                        if primary == alternate || alternate.is_empty() {
+                            vec![primary]
+                        } else if primary.is_empty() {
+                            vec![alternate]
+                        } else {
+                            vec![primary, alternate]
+                        }
+                    }
+                    None => Vec::new(),
+                }
+            }
+        }
+    }
+}
+
+/// Converts tokens into phonetic codes
+///
+/// When "inject" is set (the default), the codes are added at the same
+/// position as the original token and the original token is kept. Otherwise,
+/// the original token is replaced. Tokens that don't produce a code (such as
+/// numbers) are always kept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhoneticFilter {
+    algorithm: PhoneticAlgorithm,
+    inject: bool,
+}
+
+impl PhoneticFilter {
+    pub fn new(algorithm: PhoneticAlgorithm) -> PhoneticFilter {
+        PhoneticFilter {
+            algorithm: algorithm,
+            inject: true,
+        }
+    }
+
+    /// Sets whether the original tokens are kept alongside the codes
+    pub fn inject(mut self, inject: bool) -> PhoneticFilter {
+        self.inject = inject;
+        self
+    }
+
+    pub fn algorithm(&self) -> PhoneticAlgorithm {
+        self.algorithm
+    }
+}
+
+impl TokenFilter for PhoneticFilter {
+    fn filter(&self, tokens: Vec<Token>) -> Vec<Token> {
+        let mut output = Vec::with_capacity(tokens.len());
+
+        for token in tokens {
+            let codes = match str::from_utf8(token.term.as_bytes()) {
+                Ok(text) => self.algorithm.encode(text),
+                Err(_) => Vec::new(),
+            };
+
+            if codes.is_empty() {
+                output.push(token);
+                continue;
+            }
+
+            let position = token.position;
+            let offsets = token.byte_offsets;
+            let original_term = token.term.clone();
+            if self.inject {
+                output.push(token);
+            }
+
+            for code in codes {
+                let term = Term::from_string(&code);
+
+                if !(self.inject && term == original_term) {
+                    output.push(Token {
+                        term: term,
+                        position: position,
+                        byte_offsets: offsets,
+                        payload: None,
+                    });
+                }
+            }
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use token::Token;
+    use analysis::{Analyzer, ChainAnalyzer};
+    use super::{PhoneticFilter, PhoneticAlgorithm};
+
+    #[test]
+    fn test_phonetic_filter_inject() {
+        let analyzer = ChainAnalyzer::standard().filter(PhoneticFilter::new(PhoneticAlgorithm::Soundex));
+        let tokens = analyzer.analyze("John Smith");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("john"), position: 1, byte_offsets: Some((0, 4)), payload: None },
+            Token { term: Term::from_string("J500"), position: 1, byte_offsets: Some((0, 4)), payload: None },
+            Token { term: Term::from_string("smith"), position: 2, byte_offsets: Some((5, 10)), payload: None },
+            Token { term: Term::from_string("S530"), position: 2, byte_offsets: Some((5, 10)), payload: None },
+        ]);
+    }
+
+    #[test]
+    fn test_phonetic_filter_replace() {
+        let analyzer = ChainAnalyzer::standard().filter(PhoneticFilter::new(PhoneticAlgorithm::DoubleMetaphone).inject(false));
+
+        assert_eq!(analyzer.analyze("Smith 42"), vec![
+            Token { term: Term::from_string("SM0"), position: 1, byte_offsets: Some((0, 5)), payload: None },
+            Token { term: Term::from_string("XMT"), position: 1, byte_offsets: Some((0, 5)), payload: None },
+            Token { term: Term::from_string("42"), position: 2, byte_offsets: Some((6, 8)), payload: None },
+        ]);
+    }
+
+    #[test]
+    fn test_phonetic_filter_similar_names() {
+        for algorithm in vec![PhoneticAlgorithm::Soundex, PhoneticAlgorithm::Metaphone, PhoneticAlgorithm::DoubleMetaphone] {
+            let analyzer = ChainAnalyzer::standard().filter(PhoneticFilter::new(algorithm).inject(false));
+
+            assert_eq!(analyzer.analyze("Smith"), analyzer.analyze("Smyth"));
+        }
+    }
+}
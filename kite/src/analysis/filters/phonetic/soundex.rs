@@ -0,0 +1,80 @@
+/// Returns the Soundex digit of an uppercase ASCII letter
+///
+/// Vowels (and "Y") are given "0" and the letters "H" and "W" are given None as
+/// they are treated differently when removing adjacent duplicates.
+fn soundex_digit(c: char) -> Option<char> {
+    Some(match c {
+        'B' | 'F' | 'P' | 'V' => '1',
+        'C' | 'G' | 'J' | 'K' | 'Q' | 'S' | 'X' | 'Z' => '2',
+        'D' | 'T' => '3',
+        'L' => '4',
+        'M' | 'N' => '5',
+        'R' => '6',
+        'H' | 'W' => return None,
+        _ => '0',
+    })
+}
+
+/// Encodes a word using American Soundex (eg, "Robert" becomes "R163")
+///
+/// Characters other than ASCII letters are ignored. Returns None if the word
+/// has no ASCII letters.
+pub fn soundex(word: &str) -> Option<String> {
+    let mut letters = word.chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| c.to_ascii_uppercase());
+
+    let first_letter = match letters.next() {
+        Some(c) => c,
+        None => return None,
+    };
+
+    let mut code = String::with_capacity(4);
+    code.push(first_letter);
+
+    let mut last_digit = soundex_digit(first_letter);
+    for c in letters {
+        if code.len() == 4 {
+            break;
+        }
+
+        match soundex_digit(c) {
+            // Letters with the same digit separated by "H" or "W" are coded once
+            None => continue,
+            Some(digit) => {
+                if digit != '0' && Some(digit) != last_digit {
+                    code.push(digit);
+                }
+
+                last_digit = Some(digit);
+            }
+        }
+    }
+
+    while code.len() < 4 {
+        code.push('0');
+    }
+
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::soundex;
+
+    #[test]
+    fn test_soundex() {
+        assert_eq!(soundex("Robert"), Some("R163".to_string()));
+        assert_eq!(soundex("Rupert"), Some("R163".to_string()));
+        assert_eq!(soundex("Tymczak"), Some("T522".to_string()));
+        assert_eq!(soundex("Pfister"), Some("P236".to_string()));
+        assert_eq!(soundex("Ashcraft"), Some("A261".to_string()));
+        assert_eq!(soundex("Lee"), Some("L000".to_string()));
+    }
+
+    #[test]
+    fn test_soundex_no_letters() {
+        assert_eq!(soundex("123"), None);
+        assert_eq!(soundex(""), None);
+    }
+}
@@ -0,0 +1,165 @@
+use term::Term;
+use token::Token;
+use analysis::TokenFilter;
+
+/// Combines runs of adjacent tokens into single tokens (shingles)
+///
+/// For example, with a shingle size of 2, "new york city" produces "new york"
+/// and "york city". Each shingle is given the position of its first token.
+///
+/// Shingles are only made from tokens with consecutive positions, so no
+/// shingles are made across the gaps left by removed stopwords.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShingleFilter {
+    min_size: usize,
+    max_size: usize,
+    output_unigrams: bool,
+    separator: String,
+}
+
+impl ShingleFilter {
+    /// Creates a shingle filter that makes shingles of between "min_size" and "max_size" tokens
+    ///
+    /// The original tokens are kept and the words are separated by a single space.
+    /// Panics if "min_size" is less than 2 or greater than "max_size".
+    pub fn new(min_size: usize, max_size: usize) -> ShingleFilter {
+        ShingleFilter::try_new(min_size, max_size).expect("min_size must be at least 2 and not greater than max_size")
+    }
+
+    /// Creates a shingle filter that makes shingles of between "min_size" and "max_size" tokens
+    ///
+    /// Returns None if "min_size" is less than 2 or greater than "max_size".
+    pub fn try_new(min_size: usize, max_size: usize) -> Option<ShingleFilter> {
+        if min_size < 2 || min_size > max_size {
+            return None;
+        }
+
+        Some(ShingleFilter {
+            min_size: min_size,
+            max_size: max_size,
+            output_unigrams: true,
+            separator: " ".to_string(),
+        })
+    }
+
+    /// Sets whether the original tokens are kept
+    pub fn output_unigrams(mut self, output_unigrams: bool) -> ShingleFilter {
+        self.output_unigrams = output_unigrams;
+        self
+    }
+
+    /// Sets the string that's placed between the words of each shingle
+    pub fn separator<S: Into<String>>(mut self, separator: S) -> ShingleFilter {
+        self.separator = separator.into();
+        self
+    }
+}
+
+impl TokenFilter for ShingleFilter {
+    fn filter(&self, tokens: Vec<Token>) -> Vec<Token> {
+        let mut shingles = Vec::new();
+
+        for (i, token) in tokens.iter().enumerate() {
+            if self.output_unigrams {
+                shingles.push(token.clone());
+            }
+
+            let mut bytes = token.term.as_bytes().to_vec();
+            let mut last_position = token.position;
+            let mut size = 1;
+
+            for next_token in tokens[i + 1..].iter() {
+                if size == self.max_size || next_token.position != last_position + 1 {
+                    break;
+                }
+
+                bytes.extend_from_slice(self.separator.as_bytes());
+                bytes.extend_from_slice(next_token.term.as_bytes());
+                last_position = next_token.position;
+                size += 1;
+
+                if size >= self.min_size {
+                    // The shingle spans from the start of the first token to the end of the last
+                    let offsets = match (token.byte_offsets, next_token.byte_offsets) {
+                        (Some((start, _)), Some((_, end))) => Some((start, end)),
+                        _ => None,
+                    };
+
+                    shingles.push(Token {
+                        term: Term::from_bytes(&bytes),
+                        position: token.position,
+                        byte_offsets: offsets,
+                        payload: None,
+                    });
+                }
+            }
+        }
+
+        shingles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use token::Token;
+    use term_vector::TermVector;
+    use analysis::{Analyzer, ChainAnalyzer, TokenFilter, Language};
+    use analysis::filters::StopFilter;
+    use super::ShingleFilter;
+
+    #[test]
+    fn test_shingle_filter() {
+        let analyzer = ChainAnalyzer::standard().filter(ShingleFilter::new(2, 3));
+        let tokens = analyzer.analyze("New York City");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("new"), position: 1, byte_offsets: Some((0, 3)), payload: None },
+            Token { term: Term::from_string("new york"), position: 1, byte_offsets: Some((0, 8)), payload: None },
+            Token { term: Term::from_string("new york city"), position: 1, byte_offsets: Some((0, 13)), payload: None },
+            Token { term: Term::from_string("york"), position: 2, byte_offsets: Some((4, 8)), payload: None },
+            Token { term: Term::from_string("york city"), position: 2, byte_offsets: Some((4, 13)), payload: None },
+            Token { term: Term::from_string("city"), position: 3, byte_offsets: Some((9, 13)), payload: None },
+        ]);
+
+        let term_vector: TermVector = tokens.into();
+        assert_eq!(term_vector.get(&Term::from_string("york city")).unwrap().iter().collect::<Vec<u32>>(), vec![2]);
+    }
+
+    #[test]
+    fn test_shingle_filter_without_unigrams() {
+        let filter = ShingleFilter::new(2, 2).output_unigrams(false).separator("_");
+
+        let tokens = filter.filter(vec![
+            Token { term: Term::from_string("a"), position: 1, byte_offsets: None, payload: None },
+            Token { term: Term::from_string("b"), position: 2, byte_offsets: None, payload: None },
+            Token { term: Term::from_string("c"), position: 3, byte_offsets: None, payload: None },
+        ]);
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("a_b"), position: 1, byte_offsets: None, payload: None },
+            Token { term: Term::from_string("b_c"), position: 2, byte_offsets: None, payload: None },
+        ]);
+    }
+
+    #[test]
+    fn test_shingle_filter_position_gaps() {
+        let analyzer = ChainAnalyzer::standard()
+            .filter(StopFilter::for_language(Language::English))
+            .filter(ShingleFilter::new(2, 2).output_unigrams(false));
+
+        let tokens = analyzer.analyze("quick fox and lazy dog");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("quick fox"), position: 1, byte_offsets: Some((0, 9)), payload: None },
+            Token { term: Term::from_string("lazy dog"), position: 4, byte_offsets: Some((14, 22)), payload: None },
+        ]);
+    }
+
+    #[test]
+    fn test_try_new_invalid_sizes() {
+        assert_eq!(ShingleFilter::try_new(1, 2), None);
+        assert_eq!(ShingleFilter::try_new(3, 2), None);
+        assert!(ShingleFilter::try_new(2, 2).is_some());
+    }
+}
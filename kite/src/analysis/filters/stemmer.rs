@@ -0,0 +1,93 @@
+use rust_stemmers::{Stemmer, Algorithm};
+
+use token::Token;
+use analysis::{TokenFilter, Language};
+use analysis::filters::map_token_text;
+
+/// Reduces each token to its stem using the Snowball stemmer for a language
+///
+/// For example, the English stemmer converts "running" and "runs" into "run".
+/// The stemmer expects lowercase input so this should be placed after a
+/// lowercase filter. As stemmed terms are stored in the index, query text must
+/// be stemmed the same way for the terms to match.
+pub struct StemmerFilter {
+    language: Language,
+    stemmer: Stemmer,
+}
+
+impl StemmerFilter {
+    pub fn new(language: Language) -> StemmerFilter {
+        let algorithm = match language {
+            Language::Dutch => Algorithm::Dutch,
+            Language::English => Algorithm::English,
+            Language::French => Algorithm::French,
+            Language::German => Algorithm::German,
+            Language::Italian => Algorithm::Italian,
+            Language::Portuguese => Algorithm::Portuguese,
+            Language::Spanish => Algorithm::Spanish,
+        };
+
+        StemmerFilter {
+            language: language,
+            stemmer: Stemmer::create(algorithm),
+        }
+    }
+
+    pub fn language(&self) -> Language {
+        self.language
+    }
+}
+
+impl TokenFilter for StemmerFilter {
+    fn filter(&self, tokens: Vec<Token>) -> Vec<Token> {
+        map_token_text(tokens, |text| self.stemmer.stem(text).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use token::Token;
+    use analysis::{TokenFilter, Language};
+    use super::StemmerFilter;
+
+    fn stem(language: Language, word: &str) -> Term {
+        let filter = StemmerFilter::new(language);
+        let mut tokens = filter.filter(vec![Token { term: Term::from_string(word), position: 1, byte_offsets: None, payload: None }]);
+
+        assert_eq!(tokens.len(), 1);
+        tokens.pop().unwrap().term
+    }
+
+    #[test]
+    fn test_english_stemmer() {
+        assert_eq!(stem(Language::English, "running"), Term::from_string("run"));
+        assert_eq!(stem(Language::English, "runs"), Term::from_string("run"));
+        assert_eq!(stem(Language::English, "generously"), Term::from_string("generous"));
+        assert_eq!(stem(Language::English, "consignment"), Term::from_string("consign"));
+    }
+
+    #[test]
+    fn test_french_stemmer() {
+        assert_eq!(stem(Language::French, "continuellement"), Term::from_string("continuel"));
+    }
+
+    #[test]
+    fn test_german_stemmer() {
+        assert_eq!(stem(Language::German, "häuser"), Term::from_string("haus"));
+    }
+
+    #[test]
+    fn test_stemmer_keeps_positions() {
+        let filter = StemmerFilter::new(Language::English);
+        let tokens = filter.filter(vec![
+            Token { term: Term::from_string("cats"), position: 1, byte_offsets: None, payload: None },
+            Token { term: Term::from_string("running"), position: 5, byte_offsets: None, payload: None },
+        ]);
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("cat"), position: 1, byte_offsets: None, payload: None },
+            Token { term: Term::from_string("run"), position: 5, byte_offsets: None, payload: None },
+        ]);
+    }
+}
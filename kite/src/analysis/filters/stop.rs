@@ -0,0 +1,107 @@
+use std::str;
+use std::collections::HashSet;
+
+use token::Token;
+use analysis::{TokenFilter, Language};
+use analysis::stopwords::stopwords;
+
+/// Removes stopwords from the token stream
+///
+/// The positions of the remaining tokens are left untouched, so the gaps where
+/// the stopwords were are preserved. Matching is case sensitive, so this should
+/// be placed after a lowercase filter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StopFilter {
+    words: HashSet<String>,
+}
+
+impl StopFilter {
+    /// Creates a stop filter that removes the specified words
+    pub fn new<I: IntoIterator<Item = S>, S: Into<String>>(words: I) -> StopFilter {
+        StopFilter {
+            words: words.into_iter().map(|word| word.into()).collect(),
+        }
+    }
+
+    /// Creates a stop filter with the built-in stopword list of the language
+    pub fn for_language(language: Language) -> StopFilter {
+        StopFilter::new(stopwords(language).iter().cloned())
+    }
+
+    /// Adds more words to remove
+    pub fn with_words<I: IntoIterator<Item = S>, S: Into<String>>(mut self, words: I) -> StopFilter {
+        self.words.extend(words.into_iter().map(|word| word.into()));
+        self
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.words.contains(word)
+    }
+}
+
+impl TokenFilter for StopFilter {
+    fn filter(&self, tokens: Vec<Token>) -> Vec<Token> {
+        tokens.into_iter()
+            .filter(|token| {
+                match str::from_utf8(token.term.as_bytes()) {
+                    Ok(text) => !self.contains(text),
+                    Err(_) => true,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use token::Token;
+    use term_vector::TermVector;
+    use analysis::{Analyzer, ChainAnalyzer, TokenFilter, Language};
+    use analysis::tokenizers::StandardTokenizer;
+    use analysis::filters::LowercaseFilter;
+    use super::StopFilter;
+
+    #[test]
+    fn test_stop_filter_keeps_position_gaps() {
+        let analyzer = ChainAnalyzer::new(StandardTokenizer)
+            .filter(LowercaseFilter)
+            .filter(StopFilter::for_language(Language::English));
+
+        let tokens = analyzer.analyze("The quick fox and the lazy dog");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("quick"), position: 2, byte_offsets: Some((4, 9)), payload: None },
+            Token { term: Term::from_string("fox"), position: 3, byte_offsets: Some((10, 13)), payload: None },
+            Token { term: Term::from_string("lazy"), position: 6, byte_offsets: Some((22, 26)), payload: None },
+            Token { term: Term::from_string("dog"), position: 7, byte_offsets: Some((27, 30)), payload: None },
+        ]);
+
+        let term_vector: TermVector = tokens.into();
+        assert_eq!(term_vector.get(&Term::from_string("lazy")).unwrap().iter().collect::<Vec<u32>>(), vec![6]);
+    }
+
+    #[test]
+    fn test_stop_filter_custom_words() {
+        let filter = StopFilter::new(vec!["foo", "bar"]);
+
+        let tokens = filter.filter(vec![
+            Token { term: Term::from_string("foo"), position: 1, byte_offsets: None, payload: None },
+            Token { term: Term::from_string("baz"), position: 2, byte_offsets: None, payload: None },
+            Token { term: Term::from_string("bar"), position: 3, byte_offsets: None, payload: None },
+        ]);
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("baz"), position: 2, byte_offsets: None, payload: None },
+        ]);
+    }
+
+    #[test]
+    fn test_stop_filter_language_with_extra_words() {
+        let filter = StopFilter::for_language(Language::French).with_words(vec!["voici"]);
+
+        assert!(filter.contains("les"));
+        assert!(filter.contains("voici"));
+        assert!(!filter.contains("maison"));
+    }
+}
@@ -0,0 +1,333 @@
+use std::str;
+use std::collections::HashMap;
+
+use term::Term;
+use token::Token;
+use analysis::TokenFilter;
+
+/// A rule in a synonym list
+///
+/// Each synonym is a string of one or more words separated by whitespace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SynonymRule {
+    /// All of the synonyms are equivalent to each other
+    /// A match for any of them is expanded to all of them
+    Equivalent(Vec<String>),
+
+    /// A match for any of the synonyms in "from" is replaced with all of the synonyms in "to"
+    Explicit {
+        from: Vec<String>,
+        to: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SynonymParseError {
+    /// A rule has a "=>" with nothing on one of its sides (line number)
+    EmptySide(usize),
+
+    /// A rule has more than one "=>" (line number)
+    MultipleArrows(usize),
+}
+
+fn parse_synonym_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|synonym| synonym.split_whitespace().collect::<Vec<&str>>().join(" "))
+        .filter(|synonym| !synonym.is_empty())
+        .collect()
+}
+
+/// Parses a synonym list in the Solr format
+///
+/// Each line contains a rule. Lines with a comma separated list of synonyms
+/// (eg, "usa, united states of america") make them all equivalent. Lines with
+/// two lists separated by "=>" (eg, "colour => color") replace matches for the
+/// synonyms on the left with the synonyms on the right. Blank lines and
+/// anything after a "#" is ignored.
+pub fn parse_synonyms(text: &str) -> Result<Vec<SynonymRule>, SynonymParseError> {
+    let mut rules = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        let line = match line.find('#') {
+            Some(comment_start) => &line[..comment_start],
+            None => line,
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let sides = line.split("=>").collect::<Vec<&str>>();
+        match sides.len() {
+            1 => {
+                let synonyms = parse_synonym_list(sides[0]);
+                if !synonyms.is_empty() {
+                    rules.push(SynonymRule::Equivalent(synonyms));
+                }
+            }
+            2 => {
+                let from = parse_synonym_list(sides[0]);
+                let to = parse_synonym_list(sides[1]);
+
+                if from.is_empty() || to.is_empty() {
+                    return Err(SynonymParseError::EmptySide(line_number));
+                }
+
+                rules.push(SynonymRule::Explicit {
+                    from: from,
+                    to: to,
+                });
+            }
+            _ => return Err(SynonymParseError::MultipleArrows(line_number)),
+        }
+    }
+
+    Ok(rules)
+}
+
+fn split_words(synonym: &str) -> Vec<String> {
+    synonym.split_whitespace().map(|word| word.to_string()).collect()
+}
+
+/// Replaces words and sequences of words with their synonyms
+///
+/// Each word of a synonym is placed at the position of the first matched token
+/// plus its index in the synonym. So, if "usa" at position 5 is expanded into
+/// "united states of america", "united" is placed at position 5, "states" at 6
+/// and so on. This allows phrase queries on multi-word synonyms to match.
+///
+/// When a synonym has more words than the tokens it replaces, the tokens that
+/// follow are moved along to make room for it, so the tail of the synonym never
+/// overlaps them. In the above example, the token after "usa" moves from
+/// position 6 to 9.
+///
+/// Matching is case sensitive, so this should be placed after a lowercase filter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SynonymFilter {
+    synonyms: HashMap<Vec<String>, Vec<Vec<String>>>,
+    max_words: usize,
+}
+
+impl SynonymFilter {
+    /// Creates a synonym filter from a list of rules
+    pub fn new(rules: &[SynonymRule]) -> SynonymFilter {
+        let mut synonyms: HashMap<Vec<String>, Vec<Vec<String>>> = HashMap::new();
+
+        {
+            let mut add_mapping = |from: &str, to: &[String]| {
+                let from = split_words(from);
+                if from.is_empty() {
+                    return;
+                }
+
+                let replacements = synonyms.entry(from).or_insert_with(Vec::new);
+                for synonym in to.iter() {
+                    let words = split_words(synonym);
+                    if !words.is_empty() && !replacements.contains(&words) {
+                        replacements.push(words);
+                    }
+                }
+            };
+
+            for rule in rules.iter() {
+                match *rule {
+                    SynonymRule::Equivalent(ref words) => {
+                        for synonym in words.iter() {
+                            add_mapping(synonym, words);
+                        }
+                    }
+                    SynonymRule::Explicit{ref from, ref to} => {
+                        for synonym in from.iter() {
+                            add_mapping(synonym, to);
+                        }
+                    }
+                }
+            }
+        }
+
+        let max_words = synonyms.keys().map(|words| words.len()).max().unwrap_or(0);
+
+        SynonymFilter {
+            synonyms: synonyms,
+            max_words: max_words,
+        }
+    }
+
+    /// Creates a synonym filter from a synonym list in the Solr format (see parse_synonyms)
+    pub fn parse(text: &str) -> Result<SynonymFilter, SynonymParseError> {
+        Ok(SynonymFilter::new(&try!(parse_synonyms(text))))
+    }
+
+    /// Finds the longest synonym that matches the start of the tokens
+    /// Returns the number of tokens matched and the replacements
+    fn find_match(&self, tokens: &[Token]) -> Option<(usize, &Vec<Vec<String>>)> {
+        let mut words = Vec::new();
+        let mut longest_match = None;
+
+        for (i, token) in tokens.iter().take(self.max_words).enumerate() {
+            // The words of a synonym must be at consecutive positions
+            if i > 0 && token.position != tokens[i - 1].position + 1 {
+                break;
+            }
+
+            match str::from_utf8(token.term.as_bytes()) {
+                Ok(text) => words.push(text.to_string()),
+                Err(_) => break,
+            }
+
+            if let Some(replacements) = self.synonyms.get(&words) {
+                longest_match = Some((words.len(), replacements));
+            }
+        }
+
+        longest_match
+    }
+}
+
+impl TokenFilter for SynonymFilter {
+    fn filter(&self, tokens: Vec<Token>) -> Vec<Token> {
+        let mut output = Vec::with_capacity(tokens.len());
+        let mut i = 0;
+
+        // How far the remaining tokens have been moved along to make room for longer synonyms
+        let mut shift = 0;
+
+        while i < tokens.len() {
+            match self.find_match(&tokens[i..]) {
+                Some((length, replacements)) => {
+                    let start_position = tokens[i].position + shift;
+
+                    // Each word of the replacements spans all of the matched tokens
+                    let offsets = match (tokens[i].byte_offsets, tokens[i + length - 1].byte_offsets) {
+                        (Some((start, _)), Some((_, end))) => Some((start, end)),
+                        _ => None,
+                    };
+
+                    for replacement in replacements.iter() {
+                        for (j, word) in replacement.iter().enumerate() {
+                            output.push(Token {
+                                term: Term::from_string(word),
+                                position: start_position + j as u32,
+                                byte_offsets: offsets,
+                                payload: None,
+                            });
+                        }
+                    }
+
+                    let synonym_length = replacements.iter().map(|replacement| replacement.len()).max().unwrap_or(0);
+                    if synonym_length > length {
+                        shift += (synonym_length - length) as u32;
+                    }
+
+                    i += length;
+                }
+                None => {
+                    let mut token = tokens[i].clone();
+                    token.position += shift;
+                    output.push(token);
+                    i += 1;
+                }
+            }
+        }
+
+        // Keep the tokens in position order
+        output.sort_by_key(|token| token.position);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use token::Token;
+    use analysis::{Analyzer, ChainAnalyzer};
+    use super::{SynonymFilter, SynonymRule, SynonymParseError, parse_synonyms};
+
+    #[test]
+    fn test_parse_synonyms() {
+        let rules = parse_synonyms("
+            # Countries
+            usa, united states of america
+            colour, hue => color  # spelling
+
+            tv,television,  telly
+        ").unwrap();
+
+        assert_eq!(rules, vec![
+            SynonymRule::Equivalent(vec!["usa".to_string(), "united states of america".to_string()]),
+            SynonymRule::Explicit {
+                from: vec!["colour".to_string(), "hue".to_string()],
+                to: vec!["color".to_string()],
+            },
+            SynonymRule::Equivalent(vec!["tv".to_string(), "television".to_string(), "telly".to_string()]),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_synonyms_errors() {
+        assert_eq!(parse_synonyms("a, b\n => c"), Err(SynonymParseError::EmptySide(2)));
+        assert_eq!(parse_synonyms("a => b => c"), Err(SynonymParseError::MultipleArrows(1)));
+    }
+
+    #[test]
+    fn test_synonym_filter_single_word_to_multi_word() {
+        let analyzer = ChainAnalyzer::standard()
+            .filter(SynonymFilter::parse("usa, united states of america").unwrap());
+
+        let tokens = analyzer.analyze("visit the USA today");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("visit"), position: 1, byte_offsets: Some((0, 5)), payload: None },
+            Token { term: Term::from_string("the"), position: 2, byte_offsets: Some((6, 9)), payload: None },
+            Token { term: Term::from_string("usa"), position: 3, byte_offsets: Some((10, 13)), payload: None },
+            Token { term: Term::from_string("united"), position: 3, byte_offsets: Some((10, 13)), payload: None },
+            Token { term: Term::from_string("states"), position: 4, byte_offsets: Some((10, 13)), payload: None },
+            Token { term: Term::from_string("of"), position: 5, byte_offsets: Some((10, 13)), payload: None },
+            Token { term: Term::from_string("america"), position: 6, byte_offsets: Some((10, 13)), payload: None },
+            Token { term: Term::from_string("today"), position: 7, byte_offsets: Some((14, 19)), payload: None },
+        ]);
+    }
+
+    #[test]
+    fn test_synonym_filter_multi_word_to_single_word() {
+        let analyzer = ChainAnalyzer::standard()
+            .filter(SynonymFilter::parse("usa, united states of america").unwrap());
+
+        let tokens = analyzer.analyze("united states of america");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("usa"), position: 1, byte_offsets: Some((0, 24)), payload: None },
+            Token { term: Term::from_string("united"), position: 1, byte_offsets: Some((0, 24)), payload: None },
+            Token { term: Term::from_string("states"), position: 2, byte_offsets: Some((0, 24)), payload: None },
+            Token { term: Term::from_string("of"), position: 3, byte_offsets: Some((0, 24)), payload: None },
+            Token { term: Term::from_string("america"), position: 4, byte_offsets: Some((0, 24)), payload: None },
+        ]);
+    }
+
+    #[test]
+    fn test_synonym_filter_explicit_mapping() {
+        let analyzer = ChainAnalyzer::standard()
+            .filter(SynonymFilter::parse("colour => color").unwrap());
+
+        let tokens = analyzer.analyze("Colour, colourful");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("color"), position: 1, byte_offsets: Some((0, 6)), payload: None },
+            Token { term: Term::from_string("colourful"), position: 2, byte_offsets: Some((8, 17)), payload: None },
+        ]);
+    }
+
+    #[test]
+    fn test_synonym_filter_partial_match() {
+        let analyzer = ChainAnalyzer::standard()
+            .filter(SynonymFilter::parse("united states of america => usa").unwrap());
+
+        let tokens = analyzer.analyze("united states");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("united"), position: 1, byte_offsets: Some((0, 6)), payload: None },
+            Token { term: Term::from_string("states"), position: 2, byte_offsets: Some((7, 13)), payload: None },
+        ]);
+    }
+}
@@ -0,0 +1,188 @@
+//! Text analysis
+//!
+//! Converts text into a stream of positioned tokens which can be indexed or
+//! used to build queries. An analyzer is made up of a chain of char filters,
+//! which clean up the text (eg, removing HTML tags), a tokenizer, which splits
+//! the text into tokens, followed by a chain of token filters, which transform
+//! the tokens (eg, lowercasing or stemming them).
+
+pub mod char_filters;
+pub mod tokenizers;
+pub mod filters;
+pub mod config;
+pub mod stopwords;
+
+pub use analysis::config::{AnalyzerConfig, AnalyzerConfigError, CharFilterConfig, TokenizerConfig, TokenFilterConfig};
+
+use std::cmp;
+use std::borrow::Cow;
+
+use token::Token;
+use analysis::char_filters::OffsetMap;
+use analysis::tokenizers::StandardTokenizer;
+use analysis::filters::LowercaseFilter;
+
+/// A natural language, used to select language-specific analysis (such as stemming)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Language {
+    Dutch,
+    English,
+    French,
+    German,
+    Italian,
+    Portuguese,
+    Spanish,
+}
+
+/// Transforms text before it is tokenized
+pub trait CharFilter {
+    /// Returns the filtered text along with a map that converts offsets in the
+    /// filtered text back into offsets in the input
+    fn filter(&self, input: &str) -> (String, OffsetMap);
+}
+
+/// Splits a piece of text into tokens
+pub trait Tokenizer {
+    fn tokenize(&self, input: &str) -> Vec<Token>;
+}
+
+/// Transforms a stream of tokens
+///
+/// Filters may add, remove or alter tokens but should never renumber the
+/// positions of the tokens that pass through them.
+pub trait TokenFilter {
+    fn filter(&self, tokens: Vec<Token>) -> Vec<Token>;
+}
+
+/// Converts a piece of text into a stream of tokens
+pub trait Analyzer {
+    fn analyze(&self, input: &str) -> Vec<Token>;
+}
+
+/// An analyzer that runs a chain of char filters, a tokenizer and a chain of token filters
+pub struct ChainAnalyzer {
+    char_filters: Vec<Box<dyn CharFilter>>,
+    tokenizer: Box<dyn Tokenizer>,
+    filters: Vec<Box<dyn TokenFilter>>,
+}
+
+impl ChainAnalyzer {
+    /// Creates a new analyzer with the specified tokenizer and no filters
+    pub fn new<T: Tokenizer + 'static>(tokenizer: T) -> ChainAnalyzer {
+        ChainAnalyzer {
+            char_filters: Vec::new(),
+            tokenizer: Box::new(tokenizer),
+            filters: Vec::new(),
+        }
+    }
+
+    /// Creates the standard analyzer
+    /// This splits text on unicode word boundaries and lowercases each word
+    pub fn standard() -> ChainAnalyzer {
+        ChainAnalyzer::new(StandardTokenizer).filter(LowercaseFilter)
+    }
+
+    /// Adds a char filter to the end of the chain of char filters
+    pub fn char_filter<F: CharFilter + 'static>(mut self, char_filter: F) -> ChainAnalyzer {
+        self.char_filters.push(Box::new(char_filter));
+        self
+    }
+
+    /// Adds a filter to the end of the chain
+    pub fn filter<F: TokenFilter + 'static>(mut self, filter: F) -> ChainAnalyzer {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// Runs the char filters on the input
+    ///
+    /// Returns the filtered text along with a map that converts offsets in the
+    /// filtered text back into offsets in the input.
+    pub fn filter_chars<'a>(&self, input: &'a str) -> (Cow<'a, str>, OffsetMap) {
+        let mut text = Cow::Borrowed(input);
+        let mut offset_map = OffsetMap::new();
+
+        for char_filter in self.char_filters.iter() {
+            let (filtered_text, filter_offset_map) = char_filter.filter(&text);
+
+            text = Cow::Owned(filtered_text);
+            offset_map = offset_map.then(&filter_offset_map);
+        }
+
+        (text, offset_map)
+    }
+}
+
+impl Default for ChainAnalyzer {
+    fn default() -> ChainAnalyzer {
+        ChainAnalyzer::standard()
+    }
+}
+
+impl Analyzer for ChainAnalyzer {
+    fn analyze(&self, input: &str) -> Vec<Token> {
+        let (text, offset_map) = self.filter_chars(input);
+        let mut tokens = self.tokenizer.tokenize(&text);
+
+        // Make the offsets point into the original text
+        if !self.char_filters.is_empty() {
+            for token in tokens.iter_mut() {
+                token.byte_offsets = token.byte_offsets.map(|(start, end)| {
+                    let end = offset_map.correct_end(end);
+                    (cmp::min(offset_map.correct(start), end), end)
+                });
+            }
+        }
+
+        for filter in self.filters.iter() {
+            tokens = filter.filter(tokens);
+        }
+
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use token::Token;
+    use analysis::tokenizers::StandardTokenizer;
+    use analysis::char_filters::HtmlStripCharFilter;
+    use super::{Analyzer, ChainAnalyzer};
+
+    #[test]
+    fn test_standard_analyzer() {
+        let analyzer = ChainAnalyzer::standard();
+        let tokens = analyzer.analyze("Hello, World!");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("hello"), position: 1, byte_offsets: Some((0, 5)), payload: None },
+            Token { term: Term::from_string("world"), position: 2, byte_offsets: Some((7, 12)), payload: None },
+        ]);
+    }
+
+    #[test]
+    fn test_analyzer_without_filters() {
+        let analyzer = ChainAnalyzer::new(StandardTokenizer);
+        let tokens = analyzer.analyze("Hello, World!");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("Hello"), position: 1, byte_offsets: Some((0, 5)), payload: None },
+            Token { term: Term::from_string("World"), position: 2, byte_offsets: Some((7, 12)), payload: None },
+        ]);
+    }
+
+    #[test]
+    fn test_analyzer_offsets_point_into_original_text() {
+        let analyzer = ChainAnalyzer::standard().char_filter(HtmlStripCharFilter);
+        let input = "<p>Caf&eacute; <b>au</b> lait</p>";
+        let tokens = analyzer.analyze(input);
+
+        let spans = tokens.iter().map(|token| {
+            let (start, end) = token.byte_offsets.unwrap();
+            &input[start..end]
+        }).collect::<Vec<&str>>();
+
+        assert_eq!(spans, vec!["Caf&eacute;", "au", "lait"]);
+    }
+}
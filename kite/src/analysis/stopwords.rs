@@ -0,0 +1,107 @@
+//! Built-in stopword lists
+//!
+//! These contain the most common function words of each language. The words
+//! are lowercase so the stop filter should be placed after a lowercase filter.
+
+use analysis::Language;
+
+const DUTCH: &'static [&'static str] = &[
+    "aan", "al", "alles", "als", "altijd", "andere", "ben", "bij", "daar", "dan",
+    "dat", "de", "der", "deze", "die", "dit", "doch", "doen", "door", "dus",
+    "een", "eens", "en", "er", "ge", "geen", "geweest", "haar", "had", "heb",
+    "hebben", "heeft", "hem", "het", "hier", "hij", "hoe", "hun", "iemand", "iets",
+    "ik", "in", "is", "ja", "je", "kan", "kon", "kunnen", "maar", "me",
+    "meer", "men", "met", "mij", "mijn", "moet", "na", "naar", "niet", "niets",
+    "nog", "nu", "of", "om", "omdat", "onder", "ons", "ook", "op", "over",
+    "reeds", "te", "tegen", "toch", "toen", "tot", "u", "uit", "uw", "van",
+    "veel", "voor", "want", "waren", "was", "wat", "werd", "wezen", "wie", "wil",
+    "worden", "wordt", "zal", "ze", "zelf", "zich", "zij", "zijn", "zo", "zonder",
+    "zou",
+];
+
+const ENGLISH: &'static [&'static str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for",
+    "if", "in", "into", "is", "it", "no", "not", "of", "on", "or",
+    "such", "that", "the", "their", "then", "there", "these", "they", "this", "to",
+    "was", "will", "with",
+];
+
+const FRENCH: &'static [&'static str] = &[
+    "au", "aux", "avec", "ce", "ces", "dans", "de", "des", "du", "elle",
+    "en", "et", "eux", "il", "ils", "je", "la", "le", "les", "leur",
+    "lui", "ma", "mais", "me", "même", "mes", "moi", "mon", "ne", "nos",
+    "notre", "nous", "on", "ou", "par", "pas", "pour", "qu", "que", "qui",
+    "sa", "se", "ses", "son", "sur", "ta", "te", "tes", "toi", "ton",
+    "tu", "un", "une", "vos", "votre", "vous", "c", "d", "j", "l",
+    "m", "n", "s", "t", "y", "été", "étée", "étées", "étés", "étant",
+    "suis", "es", "est", "sommes", "êtes", "sont", "serai", "sera", "serons", "seront",
+    "étais", "était", "étions", "étiez", "étaient", "ai", "as", "avons", "avez", "ont",
+    "aurai", "aura", "aurons", "auront", "avais", "avait", "avions", "aviez", "avaient", "eu",
+];
+
+const GERMAN: &'static [&'static str] = &[
+    "aber", "alle", "allem", "allen", "aller", "alles", "als", "also", "am", "an",
+    "ander", "andere", "anderem", "anderen", "anderer", "anderes", "auch", "auf", "aus", "bei",
+    "bin", "bis", "bist", "da", "damit", "dann", "das", "dass", "daß", "dem",
+    "den", "denn", "der", "des", "dich", "die", "dir", "doch", "dort", "du",
+    "durch", "ein", "eine", "einem", "einen", "einer", "eines", "er", "es", "etwas",
+    "für", "gegen", "hab", "habe", "haben", "hat", "hatte", "hier", "hin", "ich",
+    "ihm", "ihn", "ihr", "ihre", "ihrem", "ihren", "ihrer", "im", "in", "ist",
+    "ja", "jede", "jedem", "jeden", "jeder", "kein", "keine", "mein", "meine", "mich",
+    "mir", "mit", "nach", "nicht", "noch", "nun", "nur", "ob", "oder", "ohne",
+    "sehr", "sein", "seine", "sich", "sie", "sind", "so", "um", "und", "uns",
+    "unter", "vom", "von", "vor", "war", "waren", "was", "weil", "wenn", "wer",
+    "wie", "wir", "wird", "zu", "zum", "zur", "über",
+];
+
+const ITALIAN: &'static [&'static str] = &[
+    "a", "ad", "al", "alla", "alle", "agli", "ai", "all", "anche", "avere",
+    "che", "chi", "ci", "come", "con", "contro", "cui", "da", "dal", "dalla",
+    "dalle", "dagli", "dai", "dall", "degli", "dei", "del", "dell", "della", "delle",
+    "di", "dove", "e", "è", "ed", "egli", "ella", "era", "erano", "essere",
+    "gli", "ha", "hanno", "ho", "i", "il", "in", "io", "la", "le",
+    "lei", "li", "lo", "loro", "lui", "ma", "mi", "mio", "ne", "negli",
+    "nei", "nel", "nell", "nella", "nelle", "noi", "non", "nostro", "o", "per",
+    "perché", "più", "quale", "quanto", "quella", "quelle", "quelli", "quello", "questa", "queste",
+    "questi", "questo", "se", "si", "sia", "sono", "su", "sua", "sue", "sugli",
+    "sui", "sul", "sull", "sulla", "sulle", "suo", "suoi", "ti", "tra", "tu",
+    "tuo", "un", "una", "uno", "voi", "vostro",
+];
+
+const PORTUGUESE: &'static [&'static str] = &[
+    "a", "ao", "aos", "aquela", "aquele", "aquilo", "as", "até", "com", "como",
+    "da", "das", "de", "dela", "dele", "deles", "do", "dos", "e", "é",
+    "ela", "elas", "ele", "eles", "em", "entre", "era", "essa", "esse", "esta",
+    "está", "este", "eu", "foi", "há", "isso", "isto", "já", "lhe", "mais",
+    "mas", "me", "mesmo", "meu", "minha", "muito", "na", "nas", "não", "nem",
+    "no", "nos", "nós", "num", "numa", "o", "os", "ou", "para", "pela",
+    "pelas", "pelo", "pelos", "por", "quando", "que", "quem", "se", "sem", "ser",
+    "seu", "seus", "só", "sua", "suas", "também", "te", "tem", "um", "uma",
+    "você", "vocês",
+];
+
+const SPANISH: &'static [&'static str] = &[
+    "a", "al", "algo", "algunos", "ante", "antes", "como", "con", "contra", "cual",
+    "cuando", "de", "del", "desde", "donde", "durante", "e", "el", "él", "ella",
+    "ellas", "ellos", "en", "entre", "era", "es", "esa", "esas", "ese", "eso",
+    "esos", "esta", "está", "estas", "este", "esto", "estos", "fue", "ha", "hay",
+    "la", "las", "le", "les", "lo", "los", "más", "me", "mi", "mí",
+    "mis", "mucho", "muy", "nada", "ni", "no", "nos", "nosotros", "o", "os",
+    "otra", "otros", "para", "pero", "poco", "por", "porque", "que", "qué", "quien",
+    "se", "sea", "ser", "si", "sí", "sin", "sobre", "son", "su", "sus",
+    "también", "te", "tiene", "todo", "todos", "tu", "tus", "un", "una", "uno",
+    "unos", "y", "ya", "yo",
+];
+
+/// Returns the built-in stopword list for the language
+pub fn stopwords(language: Language) -> &'static [&'static str] {
+    match language {
+        Language::Dutch => DUTCH,
+        Language::English => ENGLISH,
+        Language::French => FRENCH,
+        Language::German => GERMAN,
+        Language::Italian => ITALIAN,
+        Language::Portuguese => PORTUGUESE,
+        Language::Spanish => SPANISH,
+    }
+}
@@ -0,0 +1,157 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use term::Term;
+use token::Token;
+use analysis::Tokenizer;
+
+/// Returns true if the character is a Han ideograph, Hiragana, Katakana or Hangul
+fn is_cjk(c: char) -> bool {
+    match c as u32 {
+        // Han
+        0x3005 | 0x3007 | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x2FFFF => true,
+
+        // Hiragana and Katakana
+        0x3040..=0x309F | 0x30A0..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => true,
+
+        // Hangul
+        0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => true,
+
+        _ => false,
+    }
+}
+
+/// Splits Chinese, Japanese and Korean text into overlapping bigrams
+///
+/// Runs of Han, Hiragana, Katakana and Hangul characters are split into
+/// overlapping pairs of characters (eg, "東京都" becomes "東京" and "京都"). A
+/// run made of a single character is emitted as it is. All other text is split
+/// on unicode word boundaries, like the standard tokenizer.
+///
+/// Each bigram is given its own position so phrase queries on bigrams work.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CjkBigramTokenizer;
+
+impl CjkBigramTokenizer {
+    fn push_token(tokens: &mut Vec<Token>, text: &str, offset: usize) {
+        let position = tokens.len() as u32 + 1;
+
+        tokens.push(Token {
+            term: Term::from_string(text),
+            position: position,
+            byte_offsets: Some((offset, offset + text.len())),
+            payload: None,
+        });
+    }
+
+    fn tokenize_cjk_run(tokens: &mut Vec<Token>, run: &str, run_offset: usize) {
+        let boundaries = run.char_indices().map(|(offset, _)| offset).chain(Some(run.len())).collect::<Vec<usize>>();
+        let num_chars = boundaries.len() - 1;
+
+        if num_chars == 1 {
+            CjkBigramTokenizer::push_token(tokens, run, run_offset);
+            return;
+        }
+
+        for i in 0..num_chars - 1 {
+            CjkBigramTokenizer::push_token(tokens, &run[boundaries[i]..boundaries[i + 2]], run_offset + boundaries[i]);
+        }
+    }
+}
+
+impl Tokenizer for CjkBigramTokenizer {
+    fn tokenize(&self, input: &str) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        let mut run_start = 0;
+        let mut run_is_cjk = false;
+
+        for (offset, c) in input.char_indices().chain(Some((input.len(), ' '))) {
+            let char_is_cjk = offset < input.len() && is_cjk(c);
+
+            if char_is_cjk == run_is_cjk && offset < input.len() {
+                continue;
+            }
+
+            // The run has ended
+            let run = &input[run_start..offset];
+            if run_is_cjk {
+                CjkBigramTokenizer::tokenize_cjk_run(&mut tokens, run, run_start);
+            } else {
+                for (word_offset, word) in run.unicode_word_indices() {
+                    CjkBigramTokenizer::push_token(&mut tokens, word, run_start + word_offset);
+                }
+            }
+
+            run_start = offset;
+            run_is_cjk = char_is_cjk;
+        }
+
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use token::Token;
+    use analysis::Tokenizer;
+    use super::CjkBigramTokenizer;
+
+    fn terms(tokens: &[Token]) -> Vec<Term> {
+        tokens.iter().map(|token| token.term.clone()).collect()
+    }
+
+    #[test]
+    fn test_cjk_bigram_tokenizer_han() {
+        let tokens = CjkBigramTokenizer.tokenize("東京都");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("東京"), position: 1, byte_offsets: Some((0, 6)), payload: None },
+            Token { term: Term::from_string("京都"), position: 2, byte_offsets: Some((3, 9)), payload: None },
+        ]);
+    }
+
+    #[test]
+    fn test_cjk_bigram_tokenizer_hiragana() {
+        let tokens = CjkBigramTokenizer.tokenize("こんにちは");
+
+        assert_eq!(terms(&tokens), vec![
+            Term::from_string("こん"),
+            Term::from_string("んに"),
+            Term::from_string("にち"),
+            Term::from_string("ちは"),
+        ]);
+    }
+
+    #[test]
+    fn test_cjk_bigram_tokenizer_hangul() {
+        let tokens = CjkBigramTokenizer.tokenize("안녕하세요");
+
+        assert_eq!(terms(&tokens), vec![
+            Term::from_string("안녕"),
+            Term::from_string("녕하"),
+            Term::from_string("하세"),
+            Term::from_string("세요"),
+        ]);
+    }
+
+    #[test]
+    fn test_cjk_bigram_tokenizer_mixed() {
+        let tokens = CjkBigramTokenizer.tokenize("Rust言語で、hello world! 字");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("Rust"), position: 1, byte_offsets: Some((0, 4)), payload: None },
+            Token { term: Term::from_string("言語"), position: 2, byte_offsets: Some((4, 10)), payload: None },
+            Token { term: Term::from_string("語で"), position: 3, byte_offsets: Some((7, 13)), payload: None },
+            Token { term: Term::from_string("hello"), position: 4, byte_offsets: Some((16, 21)), payload: None },
+            Token { term: Term::from_string("world"), position: 5, byte_offsets: Some((22, 27)), payload: None },
+            Token { term: Term::from_string("字"), position: 6, byte_offsets: Some((29, 32)), payload: None },
+        ]);
+    }
+
+    #[test]
+    fn test_cjk_bigram_tokenizer_blank_string() {
+        let tokens = CjkBigramTokenizer.tokenize("");
+
+        assert_eq!(tokens, vec![]);
+    }
+}
@@ -0,0 +1,15 @@
+pub mod standard;
+pub mod ngram;
+pub mod cjk;
+pub mod whitespace;
+pub mod pattern;
+pub mod path_hierarchy;
+pub mod url_email;
+
+pub use analysis::tokenizers::standard::StandardTokenizer;
+pub use analysis::tokenizers::ngram::{NGramTokenizer, EdgeNGramTokenizer};
+pub use analysis::tokenizers::cjk::CjkBigramTokenizer;
+pub use analysis::tokenizers::whitespace::WhitespaceTokenizer;
+pub use analysis::tokenizers::pattern::PatternTokenizer;
+pub use analysis::tokenizers::path_hierarchy::PathHierarchyTokenizer;
+pub use analysis::tokenizers::url_email::UrlEmailTokenizer;
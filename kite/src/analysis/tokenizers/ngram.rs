@@ -0,0 +1,231 @@
+use std::cmp;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use term::Term;
+use token::Token;
+use analysis::Tokenizer;
+
+/// Splits each word into character n-grams of between "min_gram" and "max_gram" characters
+///
+/// Words are found using unicode word boundaries (like the standard tokenizer)
+/// and each n-gram is given the position of the word it was taken from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NGramTokenizer {
+    min_gram: usize,
+    max_gram: usize,
+}
+
+impl NGramTokenizer {
+    /// Creates a new n-gram tokenizer
+    ///
+    /// Panics if "min_gram" is zero or greater than "max_gram".
+    pub fn new(min_gram: usize, max_gram: usize) -> NGramTokenizer {
+        NGramTokenizer::try_new(min_gram, max_gram).expect("min_gram must be at least 1 and not greater than max_gram")
+    }
+
+    /// Creates a new n-gram tokenizer
+    ///
+    /// Returns None if "min_gram" is zero or greater than "max_gram".
+    pub fn try_new(min_gram: usize, max_gram: usize) -> Option<NGramTokenizer> {
+        if min_gram == 0 || min_gram > max_gram {
+            return None;
+        }
+
+        Some(NGramTokenizer {
+            min_gram: min_gram,
+            max_gram: max_gram,
+        })
+    }
+}
+
+impl Tokenizer for NGramTokenizer {
+    fn tokenize(&self, input: &str) -> Vec<Token> {
+        let mut tokens = Vec::new();
+
+        for (i, (word_offset, word)) in input.unicode_word_indices().enumerate() {
+            let boundaries = char_boundaries(word);
+            let num_chars = boundaries.len() - 1;
+
+            for start in 0..num_chars {
+                // Limit the sizes to the rest of the word ("max_gram" could be as big as usize::MAX)
+                for size in self.min_gram..(cmp::min(self.max_gram, num_chars - start) + 1) {
+                    tokens.push(Token {
+                        term: Term::from_string(&word[boundaries[start]..boundaries[start + size]]),
+                        position: i as u32 + 1,
+                        byte_offsets: Some((word_offset + boundaries[start], word_offset + boundaries[start + size])),
+                        payload: None,
+                    });
+                }
+            }
+        }
+
+        tokens
+    }
+}
+
+/// Splits each word into the n-grams anchored to the start of the word
+///
+/// For example, with a "min_gram" of 1 and a "max_gram" of 3, "quick" produces
+/// "q", "qu" and "qui". This is useful for search-as-you-type as a prefix of a
+/// word can be matched with a single term lookup. Words shorter than
+/// "min_gram" produce no tokens.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EdgeNGramTokenizer {
+    min_gram: usize,
+    max_gram: usize,
+}
+
+impl EdgeNGramTokenizer {
+    /// Creates a new edge n-gram tokenizer
+    ///
+    /// Panics if "min_gram" is zero or greater than "max_gram".
+    pub fn new(min_gram: usize, max_gram: usize) -> EdgeNGramTokenizer {
+        EdgeNGramTokenizer::try_new(min_gram, max_gram).expect("min_gram must be at least 1 and not greater than max_gram")
+    }
+
+    /// Creates a new edge n-gram tokenizer
+    ///
+    /// Returns None if "min_gram" is zero or greater than "max_gram".
+    pub fn try_new(min_gram: usize, max_gram: usize) -> Option<EdgeNGramTokenizer> {
+        if min_gram == 0 || min_gram > max_gram {
+            return None;
+        }
+
+        Some(EdgeNGramTokenizer {
+            min_gram: min_gram,
+            max_gram: max_gram,
+        })
+    }
+}
+
+impl Tokenizer for EdgeNGramTokenizer {
+    fn tokenize(&self, input: &str) -> Vec<Token> {
+        let mut tokens = Vec::new();
+
+        for (i, (word_offset, word)) in input.unicode_word_indices().enumerate() {
+            let boundaries = char_boundaries(word);
+            let num_chars = boundaries.len() - 1;
+
+            for size in self.min_gram..(cmp::min(self.max_gram, num_chars) + 1) {
+                tokens.push(Token {
+                    term: Term::from_string(&word[..boundaries[size]]),
+                    position: i as u32 + 1,
+                    byte_offsets: Some((word_offset, word_offset + boundaries[size])),
+                    payload: None,
+                });
+            }
+        }
+
+        tokens
+    }
+}
+
+/// Returns the byte offset of each character in the string, followed by the length of the string
+fn char_boundaries(s: &str) -> Vec<usize> {
+    let mut boundaries = s.char_indices().map(|(offset, _)| offset).collect::<Vec<usize>>();
+    boundaries.push(s.len());
+    boundaries
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use token::Token;
+    use analysis::Tokenizer;
+    use super::{NGramTokenizer, EdgeNGramTokenizer};
+
+    fn terms(tokens: &[Token]) -> Vec<Term> {
+        tokens.iter().map(|token| token.term.clone()).collect()
+    }
+
+    #[test]
+    fn test_ngram_tokenizer() {
+        let tokens = NGramTokenizer::new(2, 3).tokenize("fox");
+
+        assert_eq!(terms(&tokens), vec![
+            Term::from_string("fo"),
+            Term::from_string("fox"),
+            Term::from_string("ox"),
+        ]);
+    }
+
+    #[test]
+    fn test_ngram_tokenizer_positions() {
+        let tokens = NGramTokenizer::new(2, 2).tokenize("ab, cde");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("ab"), position: 1, byte_offsets: Some((0, 2)), payload: None },
+            Token { term: Term::from_string("cd"), position: 2, byte_offsets: Some((4, 6)), payload: None },
+            Token { term: Term::from_string("de"), position: 2, byte_offsets: Some((5, 7)), payload: None },
+        ]);
+    }
+
+    #[test]
+    fn test_ngram_tokenizer_multibyte() {
+        let tokens = NGramTokenizer::new(2, 2).tokenize("café");
+
+        assert_eq!(terms(&tokens), vec![
+            Term::from_string("ca"),
+            Term::from_string("af"),
+            Term::from_string("fé"),
+        ]);
+    }
+
+    #[test]
+    fn test_edge_ngram_tokenizer() {
+        let tokens = EdgeNGramTokenizer::new(1, 3).tokenize("quick fox");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("q"), position: 1, byte_offsets: Some((0, 1)), payload: None },
+            Token { term: Term::from_string("qu"), position: 1, byte_offsets: Some((0, 2)), payload: None },
+            Token { term: Term::from_string("qui"), position: 1, byte_offsets: Some((0, 3)), payload: None },
+            Token { term: Term::from_string("f"), position: 2, byte_offsets: Some((6, 7)), payload: None },
+            Token { term: Term::from_string("fo"), position: 2, byte_offsets: Some((6, 8)), payload: None },
+            Token { term: Term::from_string("fox"), position: 2, byte_offsets: Some((6, 9)), payload: None },
+        ]);
+    }
+
+    #[test]
+    fn test_edge_ngram_tokenizer_short_words() {
+        let tokens = EdgeNGramTokenizer::new(2, 10).tokenize("a fox");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("fo"), position: 2, byte_offsets: Some((2, 4)), payload: None },
+            Token { term: Term::from_string("fox"), position: 2, byte_offsets: Some((2, 5)), payload: None },
+        ]);
+    }
+
+    #[test]
+    fn test_unbounded_max_gram() {
+        let tokens = NGramTokenizer::new(2, usize::MAX).tokenize("fox");
+
+        assert_eq!(terms(&tokens), vec![
+            Term::from_string("fo"),
+            Term::from_string("fox"),
+            Term::from_string("ox"),
+        ]);
+
+        let tokens = EdgeNGramTokenizer::new(2, usize::MAX).tokenize("fox");
+
+        assert_eq!(terms(&tokens), vec![
+            Term::from_string("fo"),
+            Term::from_string("fox"),
+        ]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_ngram_tokenizer_invalid_sizes() {
+        NGramTokenizer::new(3, 2);
+    }
+
+    #[test]
+    fn test_try_new_invalid_sizes() {
+        assert_eq!(NGramTokenizer::try_new(0, 2), None);
+        assert_eq!(NGramTokenizer::try_new(3, 2), None);
+        assert_eq!(EdgeNGramTokenizer::try_new(0, 0), None);
+        assert_eq!(EdgeNGramTokenizer::try_new(2, 1), None);
+        assert!(EdgeNGramTokenizer::try_new(1, 1).is_some());
+    }
+}
@@ -0,0 +1,96 @@
+use term::Term;
+use token::Token;
+use analysis::Tokenizer;
+
+/// Splits a path into the path of each of its ancestors
+///
+/// For example, "/a/b/c" produces "/a", "/a/b" and "/a/b/c". This allows
+/// searching for everything under a particular directory with a single term.
+/// All tokens are given position 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathHierarchyTokenizer {
+    delimiter: char,
+}
+
+impl PathHierarchyTokenizer {
+    pub fn new(delimiter: char) -> PathHierarchyTokenizer {
+        PathHierarchyTokenizer {
+            delimiter: delimiter,
+        }
+    }
+}
+
+impl Default for PathHierarchyTokenizer {
+    fn default() -> PathHierarchyTokenizer {
+        PathHierarchyTokenizer::new('/')
+    }
+}
+
+impl Tokenizer for PathHierarchyTokenizer {
+    fn tokenize(&self, input: &str) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        if input.is_empty() {
+            return tokens;
+        }
+
+        let prefixes = input.match_indices(self.delimiter)
+            .map(|(offset, _)| &input[..offset])
+            .filter(|prefix| !prefix.is_empty())
+            .chain(Some(input));
+
+        for prefix in prefixes {
+            tokens.push(Token {
+                term: Term::from_string(prefix),
+                position: 1,
+                byte_offsets: Some((0, prefix.len())),
+                payload: None,
+            });
+        }
+
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use token::Token;
+    use analysis::Tokenizer;
+    use super::PathHierarchyTokenizer;
+
+    fn terms(tokens: &[Token]) -> Vec<Term> {
+        tokens.iter().map(|token| token.term.clone()).collect()
+    }
+
+    #[test]
+    fn test_path_hierarchy_tokenizer() {
+        let tokens = PathHierarchyTokenizer::default().tokenize("/a/b/c");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("/a"), position: 1, byte_offsets: Some((0, 2)), payload: None },
+            Token { term: Term::from_string("/a/b"), position: 1, byte_offsets: Some((0, 4)), payload: None },
+            Token { term: Term::from_string("/a/b/c"), position: 1, byte_offsets: Some((0, 6)), payload: None },
+        ]);
+    }
+
+    #[test]
+    fn test_path_hierarchy_tokenizer_relative_path() {
+        let tokens = PathHierarchyTokenizer::new('\\').tokenize("src\\main.rs");
+
+        assert_eq!(terms(&tokens), vec![
+            Term::from_string("src"),
+            Term::from_string("src\\main.rs"),
+        ]);
+    }
+
+    #[test]
+    fn test_path_hierarchy_tokenizer_trailing_delimiter() {
+        let tokens = PathHierarchyTokenizer::default().tokenize("/var/log/");
+
+        assert_eq!(terms(&tokens), vec![
+            Term::from_string("/var"),
+            Term::from_string("/var/log"),
+            Term::from_string("/var/log/"),
+        ]);
+    }
+}
@@ -0,0 +1,104 @@
+use regex::{Regex, Error as RegexError};
+
+use term::Term;
+use token::Token;
+use analysis::Tokenizer;
+
+/// Splits text using a regular expression
+///
+/// By default, the text is split on matches of the pattern. If a capture group
+/// is set, the text of that group in each match is used as a token instead
+/// (group 0 being the whole match). Empty tokens are discarded.
+#[derive(Debug, Clone)]
+pub struct PatternTokenizer {
+    pattern: Regex,
+    group: Option<usize>,
+}
+
+impl PatternTokenizer {
+    /// Creates a tokenizer that splits text on matches of the pattern
+    pub fn new(pattern: &str) -> Result<PatternTokenizer, RegexError> {
+        Ok(PatternTokenizer {
+            pattern: try!(Regex::new(pattern)),
+            group: None,
+        })
+    }
+
+    /// Creates a tokenizer that uses the specified capture group of each match as a token
+    ///
+    /// The group must be in the pattern (less than its captures_len()), otherwise
+    /// no tokens are produced.
+    pub fn with_group(pattern: &str, group: usize) -> Result<PatternTokenizer, RegexError> {
+        Ok(PatternTokenizer {
+            pattern: try!(Regex::new(pattern)),
+            group: Some(group),
+        })
+    }
+}
+
+impl Tokenizer for PatternTokenizer {
+    fn tokenize(&self, input: &str) -> Vec<Token> {
+        // The start and end offsets of each token
+        let spans: Vec<(usize, usize)> = match self.group {
+            Some(group) => {
+                self.pattern.captures_iter(input)
+                    .filter_map(|captures| captures.get(group).map(|group| (group.start(), group.end())))
+                    .collect()
+            }
+            None => {
+                let mut spans = Vec::new();
+                let mut last_match_end = 0;
+
+                for matched in self.pattern.find_iter(input) {
+                    spans.push((last_match_end, matched.start()));
+                    last_match_end = matched.end();
+                }
+
+                spans.push((last_match_end, input.len()));
+                spans
+            }
+        };
+
+        spans.into_iter()
+            .filter(|&(start, end)| start < end)
+            .enumerate()
+            .map(|(i, (start, end))| {
+                Token {
+                    term: Term::from_string(&input[start..end]),
+                    position: i as u32 + 1,
+                    byte_offsets: Some((start, end)),
+                    payload: None,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use token::Token;
+    use analysis::Tokenizer;
+    use super::PatternTokenizer;
+
+    #[test]
+    fn test_pattern_tokenizer_split() {
+        let tokens = PatternTokenizer::new(r"[,;]\s*").unwrap().tokenize("red, green;blue,,");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("red"), position: 1, byte_offsets: Some((0, 3)), payload: None },
+            Token { term: Term::from_string("green"), position: 2, byte_offsets: Some((5, 10)), payload: None },
+            Token { term: Term::from_string("blue"), position: 3, byte_offsets: Some((11, 15)), payload: None },
+        ]);
+    }
+
+    #[test]
+    fn test_pattern_tokenizer_group() {
+        let tokens = PatternTokenizer::with_group(r"(\w+)=\w+", 1).unwrap().tokenize("level=error service=api");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("level"), position: 1, byte_offsets: Some((0, 5)), payload: None },
+            Token { term: Term::from_string("service"), position: 2, byte_offsets: Some((12, 19)), payload: None },
+        ]);
+    }
+}
@@ -0,0 +1,70 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use term::Term;
+use token::Token;
+use analysis::Tokenizer;
+
+/// Splits text on unicode word boundaries (as defined in UAX #29)
+///
+/// Punctuation and whitespace is discarded. The first word is given position 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StandardTokenizer;
+
+impl Tokenizer for StandardTokenizer {
+    fn tokenize(&self, input: &str) -> Vec<Token> {
+        input.unicode_word_indices()
+            .enumerate()
+            .map(|(i, (offset, word))| {
+                Token {
+                    term: Term::from_string(word),
+                    position: i as u32 + 1,
+                    byte_offsets: Some((offset, offset + word.len())),
+                    payload: None,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use token::Token;
+    use analysis::Tokenizer;
+    use super::StandardTokenizer;
+
+    #[test]
+    fn test_standard_tokenizer() {
+        let tokens = StandardTokenizer.tokenize("The quick (\"brown\") fox can't jump 32.3 feet, right?");
+
+        let terms = tokens.iter().map(|token| token.term.clone()).collect::<Vec<Term>>();
+        assert_eq!(terms, vec![
+            Term::from_string("The"),
+            Term::from_string("quick"),
+            Term::from_string("brown"),
+            Term::from_string("fox"),
+            Term::from_string("can't"),
+            Term::from_string("jump"),
+            Term::from_string("32.3"),
+            Term::from_string("feet"),
+            Term::from_string("right"),
+        ]);
+    }
+
+    #[test]
+    fn test_standard_tokenizer_positions() {
+        let tokens = StandardTokenizer.tokenize("hello   --  world");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("hello"), position: 1, byte_offsets: Some((0, 5)), payload: None },
+            Token { term: Term::from_string("world"), position: 2, byte_offsets: Some((12, 17)), payload: None },
+        ]);
+    }
+
+    #[test]
+    fn test_standard_tokenizer_blank_string() {
+        let tokens = StandardTokenizer.tokenize("");
+
+        assert_eq!(tokens, vec![]);
+    }
+}
@@ -0,0 +1,106 @@
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+
+use term::Term;
+use token::Token;
+use analysis::Tokenizer;
+
+const URL_EMAIL_PATTERN: &'static str = concat!(
+    // URLs with a scheme, or starting with "www."
+    r"(?i)\b(?:(?:https?|ftp)://|www\.)[^\s<>]+",
+    "|",
+    // Email addresses
+    r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}\b",
+);
+
+/// Splits text on unicode word boundaries, but keeps URLs and email addresses intact
+#[derive(Debug, Clone)]
+pub struct UrlEmailTokenizer {
+    pattern: Regex,
+}
+
+impl UrlEmailTokenizer {
+    pub fn new() -> UrlEmailTokenizer {
+        UrlEmailTokenizer {
+            pattern: Regex::new(URL_EMAIL_PATTERN).unwrap(),
+        }
+    }
+}
+
+impl Default for UrlEmailTokenizer {
+    fn default() -> UrlEmailTokenizer {
+        UrlEmailTokenizer::new()
+    }
+}
+
+impl Tokenizer for UrlEmailTokenizer {
+    fn tokenize(&self, input: &str) -> Vec<Token> {
+        let mut words = Vec::new();
+        let mut last_match_end = 0;
+
+        for matched in self.pattern.find_iter(input) {
+            if matched.start() < last_match_end {
+                continue;
+            }
+
+            // Punctuation at the end of a URL is most likely part of the sentence
+            let url = matched.as_str().trim_end_matches(|c| ".,;:!?'\")]".contains(c));
+
+            let base = last_match_end;
+            words.extend(input[base..matched.start()].unicode_word_indices().map(|(offset, word)| (base + offset, word)));
+            words.push((matched.start(), url));
+            last_match_end = matched.start() + url.len();
+        }
+
+        let base = last_match_end;
+        words.extend(input[base..].unicode_word_indices().map(|(offset, word)| (base + offset, word)));
+
+        words.into_iter()
+            .enumerate()
+            .map(|(i, (offset, word))| {
+                Token {
+                    term: Term::from_string(word),
+                    position: i as u32 + 1,
+                    byte_offsets: Some((offset, offset + word.len())),
+                    payload: None,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use token::Token;
+    use analysis::Tokenizer;
+    use super::UrlEmailTokenizer;
+
+    fn terms(tokens: &[Token]) -> Vec<Term> {
+        tokens.iter().map(|token| token.term.clone()).collect()
+    }
+
+    #[test]
+    fn test_url_email_tokenizer() {
+        let tokens = UrlEmailTokenizer::new().tokenize("Visit https://example.com/a/b?c=d, or email info@example.co.uk.");
+
+        assert_eq!(terms(&tokens), vec![
+            Term::from_string("Visit"),
+            Term::from_string("https://example.com/a/b?c=d"),
+            Term::from_string("or"),
+            Term::from_string("email"),
+            Term::from_string("info@example.co.uk"),
+        ]);
+    }
+
+    #[test]
+    fn test_url_email_tokenizer_positions() {
+        let tokens = UrlEmailTokenizer::new().tokenize("see www.example.com now");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("see"), position: 1, byte_offsets: Some((0, 3)), payload: None },
+            Token { term: Term::from_string("www.example.com"), position: 2, byte_offsets: Some((4, 19)), payload: None },
+            Token { term: Term::from_string("now"), position: 3, byte_offsets: Some((20, 23)), payload: None },
+        ]);
+    }
+}
@@ -0,0 +1,56 @@
+use term::Term;
+use token::Token;
+use analysis::Tokenizer;
+
+/// Splits text on whitespace
+///
+/// Unlike the standard tokenizer, punctuation is kept as part of the tokens.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhitespaceTokenizer;
+
+impl Tokenizer for WhitespaceTokenizer {
+    fn tokenize(&self, input: &str) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        let mut word_start = None;
+
+        for (offset, c) in input.char_indices().chain(Some((input.len(), ' '))) {
+            match (word_start, c.is_whitespace()) {
+                (None, false) => word_start = Some(offset),
+                (Some(start), true) => {
+                    let position = tokens.len() as u32 + 1;
+
+                    tokens.push(Token {
+                        term: Term::from_string(&input[start..offset]),
+                        position: position,
+                        byte_offsets: Some((start, offset)),
+                        payload: None,
+                    });
+
+                    word_start = None;
+                }
+                _ => {}
+            }
+        }
+
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use token::Token;
+    use analysis::Tokenizer;
+    use super::WhitespaceTokenizer;
+
+    #[test]
+    fn test_whitespace_tokenizer() {
+        let tokens = WhitespaceTokenizer.tokenize("  GET /index.html\tHTTP/1.1\n");
+
+        assert_eq!(tokens, vec![
+            Token { term: Term::from_string("GET"), position: 1, byte_offsets: Some((2, 5)), payload: None },
+            Token { term: Term::from_string("/index.html"), position: 2, byte_offsets: Some((6, 17)), payload: None },
+            Token { term: Term::from_string("HTTP/1.1"), position: 3, byte_offsets: Some((18, 26)), payload: None },
+        ]);
+    }
+}
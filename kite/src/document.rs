@@ -1,10 +1,13 @@
-use chrono::{DateTime, Utc, Timelike};
-use byteorder::{WriteBytesExt, LittleEndian};
+use chrono::{DateTime, Utc};
 use fnv::FnvHashMap;
 
+use term::{Term, encode_sortable_i64, datetime_to_micros};
+use token::Token;
 use term_vector::TermVector;
-use schema::FieldId;
+use schema::{Schema, FieldId, FieldType, FIELD_INDEXED, FIELD_STORED, FIELD_TRIE};
 use segment::SegmentId;
+use analysis::{Analyzer, ChainAnalyzer};
+use trie::lower_precision_terms;
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct DocId(pub SegmentId, pub u16);
@@ -41,11 +44,7 @@ impl FieldValue {
 
                 bytes
             }
-            FieldValue::Integer(value) => {
-                let mut bytes = Vec::with_capacity(8);
-                bytes.write_i64::<LittleEndian>(value).unwrap();
-                bytes
-            }
+            FieldValue::Integer(value) => encode_sortable_i64(value),
             FieldValue::Boolean(value) => {
                 if value {
                     vec![b't']
@@ -53,21 +52,229 @@ impl FieldValue {
                     vec![b'f']
                 }
             }
-            FieldValue::DateTime(value) => {
-                let mut bytes = Vec::with_capacity(0);
-                let timestamp = value.timestamp();
-                let micros = value.nanosecond() / 1000;
-                let timestamp_with_micros = timestamp * 1000000 + micros as i64;
-                bytes.write_i64::<LittleEndian>(timestamp_with_micros).unwrap();
-                bytes
-            }
+            FieldValue::DateTime(ref value) => encode_sortable_i64(datetime_to_micros(value)),
         }
     }
 }
 
+#[derive(Debug)]
+pub enum DocumentBuildError {
+    /// A value was given for a field that isn't in the schema
+    FieldDoesntExist(FieldId),
+
+    /// A value was given that isn't compatible with the type of its field
+    FieldValueTypeMismatch(FieldId, FieldType),
+}
+
 #[derive(Debug, Clone)]
 pub struct Document {
     pub key: String,
     pub indexed_fields: FnvHashMap<FieldId, TermVector>,
     pub stored_fields: FnvHashMap<FieldId, FieldValue>,
 }
+
+impl Document {
+    /// Builds a document from raw field values
+    ///
+    /// Values of indexed Text fields are run through the analyzer configured for the
+    /// field in the schema. Values of other indexed fields are indexed as a single term.
+    ///
+    /// This builds the analyzers each time it's called, use from_field_values_with_analyzers
+    /// to reuse them between documents.
+    pub fn from_field_values(schema: &Schema, key: String, values: FnvHashMap<FieldId, FieldValue>) -> Result<Document, DocumentBuildError> {
+        let analyzers = values.keys()
+            .filter_map(|field_id| schema.get(field_id).map(|field_info| (field_id, field_info)))
+            .filter(|&(_, field_info)| field_info.field_type == FieldType::Text && field_info.field_flags.contains(FIELD_INDEXED))
+            .map(|(field_id, field_info)| (*field_id, field_info.build_analyzer()))
+            .collect();
+
+        Document::from_field_values_with_analyzers(schema, &analyzers, key, values)
+    }
+
+    /// Builds a document from raw field values, analyzing Text fields with the given analyzers
+    ///
+    /// The analyzers should be built by Schema::build_analyzers. If a Text field doesn't
+    /// have one, its analyzer is built from the schema.
+    pub fn from_field_values_with_analyzers(schema: &Schema, analyzers: &FnvHashMap<FieldId, ChainAnalyzer>, key: String, values: FnvHashMap<FieldId, FieldValue>) -> Result<Document, DocumentBuildError> {
+        let mut indexed_fields = FnvHashMap::default();
+        let mut stored_fields = FnvHashMap::default();
+
+        for (field_id, value) in values {
+            let field_info = match schema.get(&field_id) {
+                Some(field_info) => field_info,
+                None => return Err(DocumentBuildError::FieldDoesntExist(field_id)),
+            };
+
+            let type_matches = match (&field_info.field_type, &value) {
+                (&FieldType::Text, &FieldValue::String(_)) => true,
+                (&FieldType::PlainString, &FieldValue::String(_)) => true,
+                (&FieldType::I64, &FieldValue::Integer(_)) => true,
+                (&FieldType::Boolean, &FieldValue::Boolean(_)) => true,
+                (&FieldType::DateTime, &FieldValue::DateTime(_)) => true,
+                _ => false,
+            };
+
+            if !type_matches {
+                return Err(DocumentBuildError::FieldValueTypeMismatch(field_id, field_info.field_type.clone()));
+            }
+
+            if field_info.field_flags.contains(FIELD_INDEXED) {
+                let mut tokens = match value {
+                    FieldValue::String(ref string) => {
+                        if field_info.field_type == FieldType::Text {
+                            match analyzers.get(&field_id) {
+                                Some(analyzer) => analyzer.analyze(string),
+                                None => field_info.build_analyzer().analyze(string),
+                            }
+                        } else {
+                            vec![Token { term: Term::from_string(string), position: 1, byte_offsets: None, payload: None }]
+                        }
+                    }
+                    FieldValue::Integer(value) => {
+                        vec![Token { term: Term::from_integer(value), position: 1, byte_offsets: None, payload: None }]
+                    }
+                    FieldValue::Boolean(value) => {
+                        vec![Token { term: Term::from_boolean(value), position: 1, byte_offsets: None, payload: None }]
+                    }
+                    FieldValue::DateTime(ref value) => {
+                        vec![Token { term: Term::from_datetime(value), position: 1, byte_offsets: None, payload: None }]
+                    }
+                };
+
+                if field_info.field_flags.contains(FIELD_TRIE) {
+                    // Lower precision terms are given position 0 so they aren't counted in
+                    // the length of the field
+                    let precision_tokens = tokens.iter()
+                        .flat_map(|token| lower_precision_terms(&token.term))
+                        .map(|term| Token { term: term, position: 0, byte_offsets: None, payload: None })
+                        .collect::<Vec<_>>();
+
+                    tokens.extend(precision_tokens);
+                }
+
+                indexed_fields.insert(field_id, tokens.into());
+            }
+
+            if field_info.field_flags.contains(FIELD_STORED) {
+                stored_fields.insert(field_id, value);
+            }
+        }
+
+        Ok(Document {
+            key: key,
+            indexed_fields: indexed_fields,
+            stored_fields: stored_fields,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fnv::FnvHashMap;
+
+    use term::Term;
+    use schema::{Schema, FieldType, FIELD_INDEXED, FIELD_STORED, FIELD_TRIE};
+    use analysis::{AnalyzerConfig, TokenizerConfig};
+    use trie::lower_precision_terms;
+    use super::{Document, FieldValue, DocumentBuildError};
+
+    #[test]
+    fn test_from_field_values() {
+        let mut schema = Schema::new();
+        let title_field = schema.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED, None).unwrap();
+        let pk_field = schema.add_field("pk".to_string(), FieldType::I64, FIELD_INDEXED | FIELD_STORED, None).unwrap();
+
+        let mut values = FnvHashMap::default();
+        values.insert(title_field, FieldValue::String("Hello World hello".to_string()));
+        values.insert(pk_field, FieldValue::Integer(123));
+
+        let doc = Document::from_field_values(&schema, "test".to_string(), values).unwrap();
+
+        assert_eq!(doc.key, "test");
+
+        let title = doc.indexed_fields.get(&title_field).unwrap();
+        assert_eq!(title.len(), 2);
+        assert_eq!(title.get(&Term::from_string("hello")).unwrap().iter().collect::<Vec<u32>>(), vec![1, 3]);
+        assert_eq!(title.get(&Term::from_string("world")).unwrap().iter().collect::<Vec<u32>>(), vec![2]);
+
+        let pk = doc.indexed_fields.get(&pk_field).unwrap();
+        assert!(pk.contains_key(&Term::from_integer(123)));
+
+        assert_eq!(doc.stored_fields.len(), 1);
+        assert!(doc.stored_fields.contains_key(&pk_field));
+    }
+
+    #[test]
+    fn test_from_field_values_uses_field_analyzer() {
+        let mut schema = Schema::new();
+        let title_field = schema.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED, Some(AnalyzerConfig::new(TokenizerConfig::Standard))).unwrap();
+
+        let mut values = FnvHashMap::default();
+        values.insert(title_field, FieldValue::String("Hello World".to_string()));
+
+        let doc = Document::from_field_values(&schema, "test".to_string(), values).unwrap();
+
+        let title = doc.indexed_fields.get(&title_field).unwrap();
+        assert!(title.contains_key(&Term::from_string("Hello")));
+        assert!(!title.contains_key(&Term::from_string("hello")));
+    }
+
+    #[test]
+    fn test_from_field_values_with_analyzers() {
+        let mut schema = Schema::new();
+        let title_field = schema.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED, Some(AnalyzerConfig::new(TokenizerConfig::Standard))).unwrap();
+        let body_field = schema.add_field("body".to_string(), FieldType::Text, FIELD_INDEXED, None).unwrap();
+        let pk_field = schema.add_field("pk".to_string(), FieldType::I64, FIELD_INDEXED, None).unwrap();
+
+        let analyzers = schema.build_analyzers();
+        assert_eq!(analyzers.len(), 2);
+        assert!(!analyzers.contains_key(&pk_field));
+
+        for &(title, body) in [("Hello World", "Hello again"), ("Goodbye World", "See you")].iter() {
+            let mut values = FnvHashMap::default();
+            values.insert(title_field, FieldValue::String(title.to_string()));
+            values.insert(body_field, FieldValue::String(body.to_string()));
+            values.insert(pk_field, FieldValue::Integer(1));
+
+            let doc = Document::from_field_values_with_analyzers(&schema, &analyzers, "test".to_string(), values.clone()).unwrap();
+            let expected = Document::from_field_values(&schema, "test".to_string(), values).unwrap();
+
+            assert_eq!(doc.indexed_fields, expected.indexed_fields);
+        }
+    }
+
+    #[test]
+    fn test_from_field_values_trie() {
+        let mut schema = Schema::new();
+        let price_field = schema.add_field("price".to_string(), FieldType::I64, FIELD_INDEXED | FIELD_TRIE, None).unwrap();
+
+        let mut values = FnvHashMap::default();
+        values.insert(price_field, FieldValue::Integer(123));
+
+        let doc = Document::from_field_values(&schema, "test".to_string(), values).unwrap();
+
+        let price = doc.indexed_fields.get(&price_field).unwrap();
+        assert_eq!(price.len(), 8);
+        assert!(price.contains_key(&Term::from_integer(123)));
+
+        assert_eq!(price.get(&Term::from_integer(123)).unwrap().iter().collect::<Vec<u32>>(), vec![1]);
+
+        for term in lower_precision_terms(&Term::from_integer(123)) {
+            assert_eq!(price.get(&term).unwrap().iter().collect::<Vec<u32>>(), vec![0]);
+        }
+    }
+
+    #[test]
+    fn test_from_field_values_type_mismatch() {
+        let mut schema = Schema::new();
+        let pk_field = schema.add_field("pk".to_string(), FieldType::I64, FIELD_STORED, None).unwrap();
+
+        let mut values = FnvHashMap::default();
+        values.insert(pk_field, FieldValue::String("123".to_string()));
+
+        match Document::from_field_values(&schema, "test".to_string(), values) {
+            Err(DocumentBuildError::FieldValueTypeMismatch(field_id, FieldType::I64)) => assert_eq!(field_id, pk_field),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+}
@@ -2,11 +2,20 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate chrono;
+extern crate chrono_tz;
 extern crate roaring;
 extern crate byteorder;
 #[macro_use]
 extern crate bitflags;
 extern crate fnv;
+extern crate unicode_segmentation;
+extern crate rust_stemmers;
+extern crate unicode_normalization;
+extern crate regex;
+extern crate levenshtein_automata;
+extern crate regex_automata;
+#[cfg(test)]
+extern crate serde_json;
 
 pub mod term;
 pub mod token;
@@ -17,6 +26,8 @@ pub mod segment;
 pub mod similarity;
 pub mod query;
 pub mod collectors;
+pub mod analysis;
+pub mod trie;
 
 pub use term::{Term, TermId};
 pub use token::Token;
@@ -24,3 +35,4 @@ pub use document::{Document, DocId};
 pub use query::multi_term_selector::MultiTermSelector;
 pub use query::term_scorer::TermScorer;
 pub use query::Query;
+pub use analysis::{Analyzer, Tokenizer, TokenFilter};
@@ -0,0 +1,410 @@
+//! Automata for selecting terms
+//!
+//! Multi term selectors compile into automata that run over the bytes of a term.
+//! Because an automaton knows when no term starting with the bytes it has seen so
+//! far can match, a sorted term dictionary can use it to skip over whole ranges of
+//! terms rather than testing each one.
+
+use std::sync::{Arc, Mutex};
+
+use levenshtein_automata::{LevenshteinAutomatonBuilder, DFA, Distance, SINK_STATE};
+use regex::escape;
+use regex_automata::Anchored;
+use regex_automata::dfa::{dense, StartKind, Automaton as DFAAutomaton};
+use regex_automata::util::primitives::StateID;
+use regex_automata::util::start;
+
+/// The highest number of edits supported by fuzzy selectors
+/// The size of the automaton grows quickly with the number of edits
+pub const MAX_FUZZY_EDITS: u8 = 2;
+
+/// The most memory (in bytes) that the DFA of a regular expression can use
+/// Some short expressions (eg, ".*a.{20}") compile into huge DFAs which take a long
+/// time to build, so these are rejected
+pub const MAX_REGEX_DFA_SIZE: usize = 4 << 20;
+
+/// The number of recently compiled regular expressions to keep
+/// Queries are validated when they're parsed and compiled again when they're run, so
+/// this saves compiling them twice
+const REGEX_CACHE_SIZE: usize = 16;
+
+/// Recently compiled regular expressions, the most recently used last
+static REGEX_CACHE: Mutex<Vec<(String, RegexAutomaton)>> = Mutex::new(Vec::new());
+
+/// A deterministic automaton that runs over the bytes of a term
+pub trait Automaton {
+    /// Returns the state to start in
+    fn start(&self) -> usize;
+
+    /// Moves to the next state after reading the byte
+    /// Returns None if no term starting with the bytes read so far can match
+    fn accept(&self, state: usize, byte: u8) -> Option<usize>;
+
+    /// Returns true if a term that ends in this state matches
+    fn is_match(&self, state: usize) -> bool;
+
+    /// Runs the automaton over all the bytes of a term
+    fn matches(&self, term: &[u8]) -> bool {
+        let mut state = self.start();
+
+        for byte in term.iter() {
+            state = match self.accept(state, *byte) {
+                Some(state) => state,
+                None => return false,
+            };
+        }
+
+        self.is_match(state)
+    }
+}
+
+/// Matches terms that start with a prefix
+///
+/// The state is the number of bytes of the prefix matched so far.
+pub struct PrefixAutomaton {
+    prefix: Vec<u8>,
+}
+
+impl PrefixAutomaton {
+    pub fn new(prefix: &str) -> PrefixAutomaton {
+        PrefixAutomaton {
+            prefix: prefix.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl Automaton for PrefixAutomaton {
+    fn start(&self) -> usize {
+        0
+    }
+
+    fn accept(&self, state: usize, byte: u8) -> Option<usize> {
+        if state == self.prefix.len() {
+            // Matched the whole prefix, anything can follow it
+            Some(state)
+        } else if self.prefix[state] == byte {
+            Some(state + 1)
+        } else {
+            None
+        }
+    }
+
+    fn is_match(&self, state: usize) -> bool {
+        state == self.prefix.len()
+    }
+}
+
+/// Matches terms within a number of edits of another term
+///
+/// The first "prefix_length" characters of the term must match exactly. The rest
+/// is matched by a Levenshtein automaton. States below the length of the exact
+/// prefix (in bytes) count the bytes of it matched so far, states above it are
+/// the states of the Levenshtein automaton offset by the length of the prefix.
+pub struct FuzzyAutomaton {
+    prefix: Vec<u8>,
+    dfa: DFA,
+}
+
+impl FuzzyAutomaton {
+    /// Creates a new fuzzy automaton
+    ///
+    /// Panics if "max_edits" is greater than MAX_FUZZY_EDITS.
+    pub fn new(term: &str, max_edits: u8, prefix_length: usize, transpositions: bool) -> FuzzyAutomaton {
+        assert!(max_edits <= MAX_FUZZY_EDITS, "max_edits must not be greater than MAX_FUZZY_EDITS");
+
+        let prefix_bytes = term.char_indices().nth(prefix_length).map_or(term.len(), |(index, _)| index);
+        let (prefix, rest) = term.split_at(prefix_bytes);
+
+        let builder = LevenshteinAutomatonBuilder::new(max_edits, transpositions);
+
+        FuzzyAutomaton {
+            prefix: prefix.as_bytes().to_vec(),
+            dfa: builder.build_dfa(rest),
+        }
+    }
+
+    fn dfa_state(&self, dfa_state: u32) -> Option<usize> {
+        if dfa_state == SINK_STATE {
+            None
+        } else {
+            Some(self.prefix.len() + dfa_state as usize)
+        }
+    }
+}
+
+impl Automaton for FuzzyAutomaton {
+    fn start(&self) -> usize {
+        if self.prefix.is_empty() {
+            self.prefix.len() + self.dfa.initial_state() as usize
+        } else {
+            0
+        }
+    }
+
+    fn accept(&self, state: usize, byte: u8) -> Option<usize> {
+        if state >= self.prefix.len() {
+            let dfa_state = (state - self.prefix.len()) as u32;
+            self.dfa_state(self.dfa.transition(dfa_state, byte))
+        } else if self.prefix[state] != byte {
+            None
+        } else if state + 1 == self.prefix.len() {
+            self.dfa_state(self.dfa.initial_state())
+        } else {
+            Some(state + 1)
+        }
+    }
+
+    fn is_match(&self, state: usize) -> bool {
+        if state < self.prefix.len() {
+            return false;
+        }
+
+        match self.dfa.distance((state - self.prefix.len()) as u32) {
+            Distance::Exact(_) => true,
+            Distance::AtLeast(_) => false,
+        }
+    }
+}
+
+/// Matches terms that match a regular expression
+///
+/// The expression must match the whole term. The states are the states of a
+/// DFA compiled from the expression.
+#[derive(Clone)]
+pub struct RegexAutomaton {
+    dfa: Arc<dense::DFA<Vec<u32>>>,
+    start: StateID,
+}
+
+impl RegexAutomaton {
+    /// Compiles a regular expression into an automaton
+    ///
+    /// Fails with a description of the error if the expression is invalid or its DFA
+    /// would use more than MAX_REGEX_DFA_SIZE bytes. Recently compiled expressions
+    /// are reused.
+    pub fn new(pattern: &str) -> Result<RegexAutomaton, String> {
+        {
+            let mut cache = REGEX_CACHE.lock().unwrap();
+
+            if let Some(index) = cache.iter().position(|&(ref cached_pattern, _)| cached_pattern == pattern) {
+                let entry = cache.remove(index);
+                let automaton = entry.1.clone();
+                cache.push(entry);
+                return Ok(automaton);
+            }
+        }
+
+        let dfa = try!(dense::Builder::new()
+            .configure(dense::Config::new()
+                .start_kind(StartKind::Anchored)
+                .dfa_size_limit(Some(MAX_REGEX_DFA_SIZE))
+                .determinize_size_limit(Some(MAX_REGEX_DFA_SIZE)))
+            .build(&format!("(?:{})$", pattern))
+            .map_err(|e| e.to_string()));
+
+        // Anchored start states are always built so this can't fail
+        let start = dfa.start_state(&start::Config::new().anchored(Anchored::Yes)).unwrap();
+
+        let automaton = RegexAutomaton {
+            dfa: Arc::new(dfa),
+            start: start,
+        };
+
+        // Another thread may have compiled the same expression in the meantime
+        let mut cache = REGEX_CACHE.lock().unwrap();
+        if !cache.iter().any(|&(ref cached_pattern, _)| cached_pattern == pattern) {
+            if cache.len() >= REGEX_CACHE_SIZE {
+                cache.remove(0);
+            }
+
+            cache.push((pattern.to_string(), automaton.clone()));
+        }
+
+        Ok(automaton)
+    }
+
+    /// Creates an automaton that matches a wildcard pattern
+    /// "?" matches any single character and "*" matches any number of characters.
+    /// They can be escaped with a "\"
+    pub fn wildcard(pattern: &str) -> Result<RegexAutomaton, String> {
+        RegexAutomaton::new(&wildcard_to_regex(pattern))
+    }
+}
+
+impl Automaton for RegexAutomaton {
+    fn start(&self) -> usize {
+        self.start.as_usize()
+    }
+
+    fn accept(&self, state: usize, byte: u8) -> Option<usize> {
+        let next_state = self.dfa.next_state(StateID::new_unchecked(state), byte);
+
+        if self.dfa.is_dead_state(next_state) {
+            None
+        } else {
+            Some(next_state.as_usize())
+        }
+    }
+
+    fn is_match(&self, state: usize) -> bool {
+        let eoi_state = self.dfa.next_eoi_state(StateID::new_unchecked(state));
+        self.dfa.is_match_state(eoi_state)
+    }
+}
+
+/// Converts a wildcard pattern into an equivalent regular expression
+fn wildcard_to_regex(pattern: &str) -> String {
+    let mut regex = String::from("(?s)");
+    let mut chars = pattern.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '?' => regex.push('.'),
+            '*' => regex.push_str(".*"),
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    regex.push_str(&escape(&escaped.to_string()));
+                }
+            }
+            c => regex.push_str(&escape(&c.to_string())),
+        }
+    }
+
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{Automaton, PrefixAutomaton, FuzzyAutomaton, RegexAutomaton};
+
+    #[test]
+    fn test_prefix() {
+        let automaton = PrefixAutomaton::new("qu");
+
+        assert!(automaton.matches(b"qu"));
+        assert!(automaton.matches(b"quick"));
+        assert!(!automaton.matches(b"q"));
+        assert!(!automaton.matches(b"brown"));
+        assert_eq!(automaton.accept(automaton.start(), b'b'), None);
+    }
+
+    #[test]
+    fn test_fuzzy() {
+        let automaton = FuzzyAutomaton::new("quick", 1, 0, false);
+
+        assert!(automaton.matches(b"quick"));
+        assert!(automaton.matches(b"quack"));
+        assert!(automaton.matches(b"quik"));
+        assert!(automaton.matches(b"quicks"));
+        assert!(!automaton.matches(b"qiuck"));
+        assert!(!automaton.matches(b"quacks"));
+        assert!(!automaton.matches(b"brown"));
+    }
+
+    #[test]
+    fn test_fuzzy_transpositions() {
+        let automaton = FuzzyAutomaton::new("quick", 1, 0, true);
+
+        assert!(automaton.matches(b"qiuck"));
+        assert!(!automaton.matches(b"iqcuk"));
+    }
+
+    #[test]
+    fn test_fuzzy_prefix_length() {
+        let automaton = FuzzyAutomaton::new("quick", 1, 2, false);
+
+        assert!(automaton.matches(b"quack"));
+        assert!(!automaton.matches(b"quacks"));
+        assert!(!automaton.matches(b"xuick"));
+        assert!(!automaton.matches(b"qu"));
+        assert_eq!(automaton.accept(automaton.start(), b'x'), None);
+    }
+
+    #[test]
+    fn test_fuzzy_prefix_length_counts_characters() {
+        let automaton = FuzzyAutomaton::new("こんにちは", 1, 2, false);
+
+        assert!(automaton.matches("こんにちわ".as_bytes()));
+        assert!(!automaton.matches("こにちわ".as_bytes()));
+    }
+
+    #[test]
+    fn test_fuzzy_no_edits() {
+        let automaton = FuzzyAutomaton::new("quick", 0, 0, false);
+
+        assert!(automaton.matches(b"quick"));
+        assert!(!automaton.matches(b"quack"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_fuzzy_too_many_edits() {
+        FuzzyAutomaton::new("quick", 3, 0, false);
+    }
+
+    #[test]
+    fn test_regex() {
+        let automaton = RegexAutomaton::new("err(or)?_[0-9]+").unwrap();
+
+        assert!(automaton.matches(b"err_1"));
+        assert!(automaton.matches(b"error_123"));
+        assert!(!automaton.matches(b"err_"));
+        assert!(!automaton.matches(b"error_123x"));
+        assert!(!automaton.matches(b"an_error_123"));
+        assert_eq!(automaton.accept(automaton.start(), b'x'), None);
+    }
+
+    #[test]
+    fn test_regex_unicode() {
+        let automaton = RegexAutomaton::new("こん.ちは").unwrap();
+
+        assert!(automaton.matches("こんにちは".as_bytes()));
+        assert!(!automaton.matches("こんちは".as_bytes()));
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        assert!(RegexAutomaton::new("err(or").is_err());
+    }
+
+    #[test]
+    fn test_regex_too_big() {
+        // The DFA of this has millions of states
+        assert!(RegexAutomaton::new(".*a.{18}").is_err());
+        assert!(RegexAutomaton::new(".*a.{4}").is_ok());
+    }
+
+    #[test]
+    fn test_regex_is_reused() {
+        let first = RegexAutomaton::new("reused_[0-9]+").unwrap();
+        let second = RegexAutomaton::new("reused_[0-9]+").unwrap();
+
+        assert!(Arc::ptr_eq(&first.dfa, &second.dfa));
+    }
+
+    #[test]
+    fn test_wildcard() {
+        let automaton = RegexAutomaton::wildcard("req-*-eu").unwrap();
+
+        assert!(automaton.matches(b"req-123-eu"));
+        assert!(automaton.matches(b"req--eu"));
+        assert!(!automaton.matches(b"req-123-us"));
+
+        let automaton = RegexAutomaton::wildcard("qu?ck").unwrap();
+
+        assert!(automaton.matches(b"quick"));
+        assert!(automaton.matches("quäck".as_bytes()));
+        assert!(!automaton.matches(b"quck"));
+    }
+
+    #[test]
+    fn test_wildcard_escaping() {
+        let automaton = RegexAutomaton::wildcard("what\\?.c++").unwrap();
+
+        assert!(automaton.matches(b"what?.c++"));
+        assert!(!automaton.matches(b"whats.c++"));
+        assert!(!automaton.matches(b"what?xc++"));
+    }
+}
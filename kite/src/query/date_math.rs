@@ -0,0 +1,534 @@
+//! Date math expressions
+//!
+//! Date math resolves expressions like "now-7d/d" (midnight, seven days ago) into
+//! datetimes, so ranges over DateTime fields can be relative to the current time.
+//! An expression starts with an anchor, which is either "now" or a date followed
+//! by "||", and then any number of operations:
+//!
+//! - "+1d" or "-1d" adds or subtracts a number of units (the number defaults to 1)
+//! - "/d" rounds down to the start of the unit
+//!
+//! The units are "y" (years), "M" (months), "w" (weeks), "d" (days), "h" or "H"
+//! (hours), "m" (minutes) and "s" (seconds). For example, "2016-07-23||+1M/M" is the
+//! start of August 2016. A date on its own is also an expression.
+//!
+//! Dates can be RFC3339 datetimes, or dates ("2016-07-23") and datetimes without an
+//! offset ("2016-07-23T16:15:00"), which are in the time zone the expression is
+//! resolved in. Rounding happens in this time zone too, so rounding to the day gives
+//! midnight in that time zone rather than midnight UTC.
+//!
+//! The time zone can be a fixed offset or a named time zone (eg, "Europe/London").
+//! In named time zones, days, weeks, months and years follow the local calendar
+//! across daylight saving time changes, so a day may be 23 or 25 hours long. Local
+//! times that happen twice are taken to be the first one and local times skipped
+//! over are moved forward by the length of the gap.
+//!
+//! Expressions can be resolved rounding up instead, which rounds to the last
+//! microsecond of the unit. This is for the bounds of ranges that include the whole
+//! unit they're rounded to, so "lte now/d" includes all of today and "gt now/d"
+//! starts tomorrow.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, FixedOffset, Utc, Duration, Days, Months, Datelike, Timelike, TimeZone, Offset, LocalResult};
+
+pub use chrono_tz::Tz;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DateMathError {
+    /// The date of the anchor couldn't be parsed (date)
+    InvalidDate(String),
+
+    /// A character was found where it isn't allowed (byte position, character)
+    UnexpectedCharacter(usize, char),
+
+    /// The expression ended part way through an operation
+    UnexpectedEnd,
+
+    /// The result is outside the range of supported datetimes
+    OutOfRange,
+}
+
+/// A unit of time that can be added or rounded to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateUnit {
+    Year,
+    Month,
+    Week,
+    Day,
+    Hour,
+    Minute,
+    Second,
+}
+
+impl DateUnit {
+    fn from_char(c: char) -> Option<DateUnit> {
+        match c {
+            'y' => Some(DateUnit::Year),
+            'M' => Some(DateUnit::Month),
+            'w' => Some(DateUnit::Week),
+            'd' => Some(DateUnit::Day),
+            'h' | 'H' => Some(DateUnit::Hour),
+            'm' => Some(DateUnit::Minute),
+            's' => Some(DateUnit::Second),
+            _ => None,
+        }
+    }
+}
+
+/// A time zone that date math can be resolved in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateMathTimeZone {
+    /// A fixed offset from UTC (eg, "+01:00")
+    Offset(FixedOffset),
+
+    /// A time zone from the IANA time zone database (eg, "Europe/London")
+    Named(Tz),
+}
+
+impl DateMathTimeZone {
+    /// Returns the UTC time zone
+    pub fn utc() -> DateMathTimeZone {
+        DateMathTimeZone::Offset(FixedOffset::east_opt(0).unwrap())
+    }
+}
+
+impl From<FixedOffset> for DateMathTimeZone {
+    fn from(offset: FixedOffset) -> DateMathTimeZone {
+        DateMathTimeZone::Offset(offset)
+    }
+}
+
+impl From<Tz> for DateMathTimeZone {
+    fn from(timezone: Tz) -> DateMathTimeZone {
+        DateMathTimeZone::Named(timezone)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Anchor {
+    Now,
+    DateTime(DateTime<FixedOffset>),
+
+    /// A date or datetime without an offset, which is in the time zone the expression is resolved in
+    Local(NaiveDateTime),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DateMathOperation {
+    /// Adds a number of units (subtractions are negative)
+    Add(i64, DateUnit),
+
+    /// Rounds to the unit
+    Round(DateUnit),
+}
+
+/// A parsed date math expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateMath {
+    anchor: Anchor,
+    operations: Vec<DateMathOperation>,
+}
+
+impl DateMath {
+    pub fn parse(expression: &str) -> Result<DateMath, DateMathError> {
+        let (anchor, operations_start) = if expression.starts_with("now") {
+            (Anchor::Now, 3)
+        } else {
+            match expression.find("||") {
+                Some(index) => (try!(parse_date(&expression[..index])), index + 2),
+                None => (try!(parse_date(expression)), expression.len()),
+            }
+        };
+
+        let mut operations = Vec::new();
+        let mut chars = expression[operations_start..].char_indices()
+            .map(|(position, c)| (operations_start + position, c))
+            .peekable();
+
+        while let Some((position, c)) = chars.next() {
+            match c {
+                '+' | '-' => {
+                    let mut number = String::new();
+                    while let Some(&(_, c)) = chars.peek() {
+                        if !c.is_ascii_digit() {
+                            break;
+                        }
+
+                        number.push(c);
+                        chars.next();
+                    }
+
+                    let number = if number.is_empty() {
+                        1
+                    } else {
+                        match number.parse::<i64>() {
+                            Ok(number) => number,
+                            Err(_) => return Err(DateMathError::OutOfRange),
+                        }
+                    };
+
+                    let unit = try!(parse_unit(chars.next()));
+                    operations.push(DateMathOperation::Add(if c == '-' { -number } else { number }, unit));
+                }
+                '/' => {
+                    let unit = try!(parse_unit(chars.next()));
+                    operations.push(DateMathOperation::Round(unit));
+                }
+                c => return Err(DateMathError::UnexpectedCharacter(position, c)),
+            }
+        }
+
+        Ok(DateMath {
+            anchor: anchor,
+            operations: operations,
+        })
+    }
+
+    /// Returns the operations that are applied to the anchor, in order
+    pub fn operations(&self) -> &Vec<DateMathOperation> {
+        &self.operations
+    }
+
+    /// Resolves the expression into a datetime
+    ///
+    /// "now" is the time that the "now" anchor refers to. Dates without an offset
+    /// are in "timezone" and rounding happens in it. If "round_up" is set, rounding
+    /// goes to the last microsecond of the unit rather than the first.
+    pub fn resolve(&self, now: &DateTime<Utc>, timezone: &DateMathTimeZone, round_up: bool) -> Result<DateTime<Utc>, DateMathError> {
+        match *timezone {
+            DateMathTimeZone::Offset(ref offset) => self.resolve_in(now, offset, round_up),
+            DateMathTimeZone::Named(ref timezone) => self.resolve_in(now, timezone, round_up),
+        }
+    }
+
+    /// Resolves the expression into a datetime, in any chrono time zone (see .resolve())
+    pub fn resolve_in<Z: TimeZone>(&self, now: &DateTime<Utc>, timezone: &Z, round_up: bool) -> Result<DateTime<Utc>, DateMathError> {
+        let mut datetime = match self.anchor {
+            Anchor::Now => now.with_timezone(timezone),
+            Anchor::DateTime(ref datetime) => datetime.with_timezone(timezone),
+            Anchor::Local(ref datetime) => try!(from_local(timezone, datetime, None)),
+        };
+
+        for operation in self.operations.iter() {
+            datetime = match *operation {
+                DateMathOperation::Add(number, unit) => try!(add(&datetime, number, unit)),
+                DateMathOperation::Round(unit) => {
+                    let start = try!(round_down(&datetime, unit));
+
+                    if round_up {
+                        let end = try!(add(&start, 1, unit));
+
+                        match end.checked_sub_signed(Duration::microseconds(1)) {
+                            Some(end) => end,
+                            None => return Err(DateMathError::OutOfRange),
+                        }
+                    } else {
+                        start
+                    }
+                }
+            };
+        }
+
+        Ok(datetime.with_timezone(&Utc))
+    }
+}
+
+fn parse_date(date: &str) -> Result<Anchor, DateMathError> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(date) {
+        return Ok(Anchor::DateTime(datetime));
+    }
+
+    for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"].iter() {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(date, format) {
+            return Ok(Anchor::Local(datetime));
+        }
+    }
+
+    match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        Ok(date) => Ok(Anchor::Local(date.and_hms_opt(0, 0, 0).unwrap())),
+        Err(_) => Err(DateMathError::InvalidDate(date.to_string())),
+    }
+}
+
+fn parse_unit(c: Option<(usize, char)>) -> Result<DateUnit, DateMathError> {
+    match c {
+        Some((position, c)) => {
+            match DateUnit::from_char(c) {
+                Some(unit) => Ok(unit),
+                None => Err(DateMathError::UnexpectedCharacter(position, c)),
+            }
+        }
+        None => Err(DateMathError::UnexpectedEnd),
+    }
+}
+
+/// Converts a local datetime in the time zone into a datetime
+///
+/// Local times that happen twice (when the clocks go back) are taken to be the one
+/// with the "preferred" offset if there is one, otherwise the first. Local times
+/// that are skipped over (when the clocks go forward) are moved forward by the
+/// length of the gap.
+fn from_local<Z: TimeZone>(timezone: &Z, datetime: &NaiveDateTime, preferred: Option<FixedOffset>) -> Result<DateTime<Z>, DateMathError> {
+    let result = match timezone.from_local_datetime(datetime) {
+        LocalResult::Single(result) => Some(result),
+        LocalResult::Ambiguous(earliest, latest) => {
+            if preferred == Some(latest.offset().fix()) {
+                Some(latest)
+            } else {
+                Some(earliest)
+            }
+        }
+        LocalResult::None => {
+            // Use the offset from before the gap
+            datetime.checked_sub_days(Days::new(1))
+                .map(|before| timezone.offset_from_utc_datetime(&before).fix())
+                .and_then(|offset| datetime.checked_sub_signed(Duration::seconds(offset.local_minus_utc() as i64)))
+                .map(|utc| timezone.from_utc_datetime(&utc))
+        }
+    };
+
+    match result {
+        Some(result) => Ok(result),
+        None => Err(DateMathError::OutOfRange),
+    }
+}
+
+fn add<Z: TimeZone>(datetime: &DateTime<Z>, number: i64, unit: DateUnit) -> Result<DateTime<Z>, DateMathError> {
+    // Calendar units are added to the local datetime, so adding a day keeps the
+    // same local time even if the clocks change
+    let local = datetime.naive_local();
+    let local_result = match unit {
+        DateUnit::Year | DateUnit::Month => {
+            let months = if unit == DateUnit::Year { number.checked_mul(12) } else { Some(number) };
+
+            match months {
+                Some(months) if months >= 0 && months <= u32::MAX as i64 => {
+                    local.checked_add_months(Months::new(months as u32))
+                }
+                Some(months) if months < 0 && -months <= u32::MAX as i64 => {
+                    local.checked_sub_months(Months::new(-months as u32))
+                }
+                _ => None,
+            }
+        }
+        DateUnit::Week | DateUnit::Day => {
+            let duration = if unit == DateUnit::Week { Duration::try_weeks(number) } else { Duration::try_days(number) };
+            duration.and_then(|duration| local.checked_add_signed(duration))
+        }
+        _ => {
+            let duration = match unit {
+                DateUnit::Hour => Duration::try_hours(number),
+                DateUnit::Minute => Duration::try_minutes(number),
+                _ => Duration::try_seconds(number),
+            };
+
+            return match duration.and_then(|duration| datetime.clone().checked_add_signed(duration)) {
+                Some(result) => Ok(result),
+                None => Err(DateMathError::OutOfRange),
+            };
+        }
+    };
+
+    match local_result {
+        Some(local_result) => from_local(&datetime.timezone(), &local_result, Some(datetime.offset().fix())),
+        None => Err(DateMathError::OutOfRange),
+    }
+}
+
+fn round_down<Z: TimeZone>(datetime: &DateTime<Z>, unit: DateUnit) -> Result<DateTime<Z>, DateMathError> {
+    let date = datetime.date_naive();
+
+    let rounded = match unit {
+        DateUnit::Year => NaiveDate::from_ymd_opt(date.year(), 1, 1).and_then(|date| date.and_hms_opt(0, 0, 0)),
+        DateUnit::Month => NaiveDate::from_ymd_opt(date.year(), date.month(), 1).and_then(|date| date.and_hms_opt(0, 0, 0)),
+        DateUnit::Week => {
+            let days_since_monday = date.weekday().num_days_from_monday() as u64;
+            date.checked_sub_days(Days::new(days_since_monday)).and_then(|date| date.and_hms_opt(0, 0, 0))
+        }
+        DateUnit::Day => date.and_hms_opt(0, 0, 0),
+        DateUnit::Hour => date.and_hms_opt(datetime.hour(), 0, 0),
+        DateUnit::Minute => date.and_hms_opt(datetime.hour(), datetime.minute(), 0),
+        DateUnit::Second => date.and_hms_opt(datetime.hour(), datetime.minute(), datetime.second()),
+    };
+
+    match rounded {
+        Some(rounded) => from_local(&datetime.timezone(), &rounded, Some(datetime.offset().fix())),
+        None => Err(DateMathError::OutOfRange),
+    }
+}
+
+/// Parses a time zone, which is either an offset ("Z", "UTC", "+01:00", "+0100" or
+/// "+01") or the name of a time zone in the IANA time zone database ("Europe/London")
+pub fn parse_timezone(timezone: &str) -> Option<DateMathTimeZone> {
+    match parse_offset(timezone) {
+        Some(offset) => Some(DateMathTimeZone::Offset(offset)),
+        None => timezone.parse::<Tz>().ok().map(DateMathTimeZone::Named),
+    }
+}
+
+fn parse_offset(timezone: &str) -> Option<FixedOffset> {
+    if timezone == "Z" || timezone == "UTC" {
+        return FixedOffset::east_opt(0);
+    }
+
+    if !timezone.is_ascii() {
+        return None;
+    }
+
+    let sign = match timezone.chars().next() {
+        Some('+') => 1,
+        Some('-') => -1,
+        _ => return None,
+    };
+
+    let digits = &timezone[1..];
+    let (hours, minutes) = match digits.len() {
+        2 => (digits, "00"),
+        4 => (&digits[..2], &digits[2..]),
+        5 if digits.as_bytes()[2] == b':' => (&digits[..2], &digits[3..]),
+        _ => return None,
+    };
+
+    if !hours.chars().chain(minutes.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let hours = hours.parse::<i32>().unwrap();
+    let minutes = minutes.parse::<i32>().unwrap();
+    if minutes >= 60 {
+        return None;
+    }
+
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc, FixedOffset};
+
+    use super::{DateMath, DateMathError, DateMathOperation, DateMathTimeZone, DateUnit, Tz, parse_timezone};
+
+    fn datetime(datetime: &str) -> DateTime<Utc> {
+        datetime.parse().unwrap()
+    }
+
+    fn resolve_at(expression: &str, now: &str, timezone: &DateMathTimeZone, round_up: bool) -> DateTime<Utc> {
+        DateMath::parse(expression).unwrap().resolve(&datetime(now), timezone, round_up).unwrap()
+    }
+
+    fn resolve(expression: &str, timezone: &DateMathTimeZone, round_up: bool) -> DateTime<Utc> {
+        resolve_at(expression, "2016-07-23T16:15:42.123456Z", timezone, round_up)
+    }
+
+    #[test]
+    fn test_parse() {
+        let date_math = DateMath::parse("now-7d/d").unwrap();
+        assert_eq!(date_math.operations(), &vec![DateMathOperation::Add(-7, DateUnit::Day), DateMathOperation::Round(DateUnit::Day)]);
+
+        let date_math = DateMath::parse("2016-07-23||+M+12h/H").unwrap();
+        assert_eq!(date_math.operations(), &vec![DateMathOperation::Add(1, DateUnit::Month), DateMathOperation::Add(12, DateUnit::Hour), DateMathOperation::Round(DateUnit::Hour)]);
+    }
+
+    #[test]
+    fn test_now() {
+        let utc = DateMathTimeZone::utc();
+
+        assert_eq!(resolve("now", &utc, false), datetime("2016-07-23T16:15:42.123456Z"));
+        assert_eq!(resolve("now-7d", &utc, false), datetime("2016-07-16T16:15:42.123456Z"));
+        assert_eq!(resolve("now-7d/d", &utc, false), datetime("2016-07-16T00:00:00Z"));
+        assert_eq!(resolve("now/d", &utc, true), datetime("2016-07-23T23:59:59.999999Z"));
+        assert_eq!(resolve("now+1h/h", &utc, false), datetime("2016-07-23T17:00:00Z"));
+        assert_eq!(resolve("now/m", &utc, false), datetime("2016-07-23T16:15:00Z"));
+        assert_eq!(resolve("now/s", &utc, false), datetime("2016-07-23T16:15:42Z"));
+        assert_eq!(resolve("now/w", &utc, false), datetime("2016-07-18T00:00:00Z"));
+        assert_eq!(resolve("now/M", &utc, true), datetime("2016-07-31T23:59:59.999999Z"));
+        assert_eq!(resolve("now-1y/y", &utc, false), datetime("2015-01-01T00:00:00Z"));
+    }
+
+    #[test]
+    fn test_anchor_date() {
+        let utc = DateMathTimeZone::utc();
+
+        assert_eq!(resolve("2024-01-01||+1M", &utc, false), datetime("2024-02-01T00:00:00Z"));
+        assert_eq!(resolve("2024-01-31||+1M", &utc, false), datetime("2024-02-29T00:00:00Z"));
+        assert_eq!(resolve("2024-01-01T12:30:00||-1d/d", &utc, false), datetime("2023-12-31T00:00:00Z"));
+        assert_eq!(resolve("2024-01-01T12:30:00+02:00||/h", &utc, false), datetime("2024-01-01T10:00:00Z"));
+        assert_eq!(resolve("2024-01-01", &utc, false), datetime("2024-01-01T00:00:00Z"));
+    }
+
+    #[test]
+    fn test_fixed_offset() {
+        let minus_five = DateMathTimeZone::Offset(FixedOffset::west_opt(5 * 3600).unwrap());
+
+        // Rounding happens in the time zone
+        assert_eq!(resolve("now/d", &minus_five, false), datetime("2016-07-23T05:00:00Z"));
+        assert_eq!(resolve("now/d", &minus_five, true), datetime("2016-07-24T04:59:59.999999Z"));
+
+        // Dates without an offset are in the time zone
+        assert_eq!(resolve("2024-01-01", &minus_five, false), datetime("2024-01-01T05:00:00Z"));
+        assert_eq!(resolve("2024-01-01T00:00:00Z||/d", &minus_five, false), datetime("2023-12-31T05:00:00Z"));
+    }
+
+    #[test]
+    fn test_named_timezone() {
+        let new_york = DateMathTimeZone::Named(Tz::America__New_York);
+
+        // New York is UTC-4 in summer and UTC-5 in winter
+        assert_eq!(resolve("now/d", &new_york, false), datetime("2016-07-23T04:00:00Z"));
+        assert_eq!(resolve("now/d", &new_york, true), datetime("2016-07-24T03:59:59.999999Z"));
+        assert_eq!(resolve("2024-01-01", &new_york, false), datetime("2024-01-01T05:00:00Z"));
+    }
+
+    #[test]
+    fn test_daylight_saving_time() {
+        let london = DateMathTimeZone::Named(Tz::Europe__London);
+
+        // The clocks went forward an hour at 01:00 on 2016-03-27, so that day is 23 hours long
+        assert_eq!(resolve("2016-03-27||/d", &london, false), datetime("2016-03-27T00:00:00Z"));
+        assert_eq!(resolve("2016-03-27||/d", &london, true), datetime("2016-03-27T22:59:59.999999Z"));
+
+        // Adding a day keeps the same local time
+        assert_eq!(resolve("2016-03-26T12:00:00||+1d", &london, false), datetime("2016-03-27T11:00:00Z"));
+        assert_eq!(resolve("2016-03-26T12:00:00||+24h", &london, false), datetime("2016-03-27T12:00:00Z"));
+
+        // Local times skipped over are moved forward
+        assert_eq!(resolve("2016-03-27T01:30:00", &london, false), datetime("2016-03-27T01:30:00Z"));
+
+        // The clocks went back an hour at 02:00 on 2016-10-30, so 01:00 to 02:00 happened twice
+        assert_eq!(resolve("2016-10-30T01:30:00", &london, false), datetime("2016-10-30T00:30:00Z"));
+        assert_eq!(resolve_at("now/h", "2016-10-30T00:45:00Z", &london, false), datetime("2016-10-30T00:00:00Z"));
+        assert_eq!(resolve_at("now/h", "2016-10-30T01:45:00Z", &london, false), datetime("2016-10-30T01:00:00Z"));
+        assert_eq!(resolve("2016-10-30||/d", &london, true), datetime("2016-10-30T23:59:59.999999Z"));
+
+        // Midnight was skipped over in Sao Paulo on 2016-10-16, so that day started at 01:00
+        let sao_paulo = DateMathTimeZone::Named(Tz::America__Sao_Paulo);
+        assert_eq!(resolve("2016-10-16T12:00:00||/d", &sao_paulo, false), datetime("2016-10-16T03:00:00Z"));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(DateMath::parse("yesterday"), Err(DateMathError::InvalidDate("yesterday".to_string())));
+        assert_eq!(DateMath::parse("2024-13-01||+1d"), Err(DateMathError::InvalidDate("2024-13-01".to_string())));
+        assert_eq!(DateMath::parse("now-7x"), Err(DateMathError::UnexpectedCharacter(5, 'x')));
+        assert_eq!(DateMath::parse("now*2"), Err(DateMathError::UnexpectedCharacter(3, '*')));
+        assert_eq!(DateMath::parse("now/"), Err(DateMathError::UnexpectedEnd));
+
+        let date_math = DateMath::parse("now+999999999999y").unwrap();
+        assert_eq!(date_math.resolve(&Utc::now(), &DateMathTimeZone::utc(), false), Err(DateMathError::OutOfRange));
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        let offset = |seconds: i32| Some(DateMathTimeZone::Offset(FixedOffset::east_opt(seconds).unwrap()));
+
+        assert_eq!(parse_timezone("Z"), offset(0));
+        assert_eq!(parse_timezone("UTC"), offset(0));
+        assert_eq!(parse_timezone("+01:00"), offset(3600));
+        assert_eq!(parse_timezone("-0530"), offset(-(5 * 3600 + 30 * 60)));
+        assert_eq!(parse_timezone("+09"), offset(9 * 3600));
+        assert_eq!(parse_timezone("Europe/London"), Some(DateMathTimeZone::Named(Tz::Europe__London)));
+        assert_eq!(parse_timezone("01:00"), None);
+        assert_eq!(parse_timezone("+01:70"), None);
+        assert_eq!(parse_timezone("+1:00"), None);
+        assert_eq!(parse_timezone("Europe/Nowhere"), None);
+    }
+}
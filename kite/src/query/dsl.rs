@@ -0,0 +1,1087 @@
+//! A serializable representation of queries
+//!
+//! Queries refer to fields by id and contain terms as raw bytes, so they can't be
+//! sent to another process as they are. The types in this module mirror Query,
+//! SpanQuery and Intervals but refer to fields by name and contain typed term
+//! values, which are converted to and from terms by the type of the field in the
+//! Schema.
+//!
+//! Enums are serialized with the name of the variant as the key. For example:
+//!
+//! ```json
+//! {
+//!     "Filter": {
+//!         "query": {"Term": {"field": "title", "term": "hello"}},
+//!         "filter": {"Term": {"field": "published", "term": true}}
+//!     }
+//! }
+//! ```
+//!
+//! Term values are strings for Text and PlainString fields, integers for I64 fields,
+//! booleans for Boolean fields and RFC3339 strings for DateTime fields. The "scorer"
+//! of a query can be left out to use the default scorer and the "slop" of a phrase
+//! defaults to 0.
+//!
+//! The bounds of a range are given with "gt" (or "gte" to include the bound) and "lt"
+//! (or "lte"). Either end can be left out to leave the range open at that end:
+//!
+//! ```json
+//! {"Range": {"field": "price", "gte": 10, "lt": 50}}
+//! ```
+//!
+//! The bounds of ranges over DateTime fields can be date math expressions (see the
+//! date_math module). These are resolved in the "time_zone" of the range (an offset
+//! such as "+01:00" or a named time zone such as "Europe/London", defaulting to UTC):
+//!
+//! ```json
+//! {"Range": {"field": "published", "gte": "now-30d/d", "time_zone": "Europe/London"}}
+//! ```
+
+use std::ops::Bound;
+
+use chrono::{DateTime, Utc};
+
+use term::Term;
+use schema::{Schema, FieldId, FieldType};
+use query::Query;
+use query::date_math::{DateMath, DateMathTimeZone, parse_timezone};
+use query::multi_term_selector::{MultiTermSelector, MultiTermSelectorError};
+use query::term_scorer::TermScorer;
+use query::span::SpanQuery;
+use query::intervals::Intervals;
+
+/// The value of a term, typed by the field that it's in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TermValue {
+    Boolean(bool),
+    Integer(i64),
+    String(String),
+}
+
+#[derive(Debug)]
+pub enum QueryDslError {
+    /// A field name that isn't in the schema
+    UnknownField(String),
+
+    /// A field id that isn't in the schema
+    FieldDoesntExist(FieldId),
+
+    /// The term value can't be converted into a term for a field of this type
+    InvalidValue(TermValue, FieldType),
+
+    /// The term can't be read as a value of this field type
+    InvalidTerm(Term, FieldType),
+
+    /// A range was given both an inclusive and an exclusive bound at the same end (field name)
+    ConflictingBounds(String),
+
+    /// The time zone of a range isn't a valid offset (eg, "+01:00") or time zone name (eg, "Europe/London")
+    InvalidTimeZone(String),
+
+    /// The positions of a phrase don't match up with its terms or aren't in order (field name)
+    InvalidPhrasePositions(String),
+
+    /// A multi term selector can't be compiled (eg, an invalid regex or too many fuzzy edits)
+    InvalidTermSelector(MultiTermSelectorError),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum QueryDsl {
+    All {
+        #[serde(default = "default_score")]
+        score: f32,
+    },
+
+    None,
+
+    Term {
+        field: String,
+        term: TermValue,
+
+        #[serde(default)]
+        scorer: TermScorer,
+    },
+
+    MultiTerm {
+        field: String,
+        term_selector: MultiTermSelector,
+
+        #[serde(default)]
+        scorer: TermScorer,
+    },
+
+    Range {
+        field: String,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        gt: Option<TermValue>,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        gte: Option<TermValue>,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        lt: Option<TermValue>,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        lte: Option<TermValue>,
+
+        /// The time zone of dates without an offset, which date math also rounds in
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        time_zone: Option<String>,
+
+        #[serde(default = "default_score")]
+        score: f32,
+    },
+
+    Phrase {
+        field: String,
+        terms: Vec<TermValue>,
+
+        /// The position of each term relative to the start of the phrase, if there are
+        /// gaps between them (eg, left by removed stopwords) or terms share a position
+        /// (eg, synonyms)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        positions: Option<Vec<u32>>,
+
+        #[serde(default)]
+        slop: u32,
+
+        #[serde(default)]
+        scorer: TermScorer,
+    },
+
+    Span {
+        field: String,
+        span: SpanQueryDsl,
+
+        #[serde(default)]
+        scorer: TermScorer,
+    },
+
+    Intervals {
+        field: String,
+        intervals: IntervalsDsl,
+
+        #[serde(default)]
+        scorer: TermScorer,
+    },
+
+    Conjunction {
+        queries: Vec<QueryDsl>,
+    },
+
+    Disjunction {
+        queries: Vec<QueryDsl>,
+    },
+
+    DisjunctionMax {
+        queries: Vec<QueryDsl>,
+    },
+
+    Filter {
+        query: Box<QueryDsl>,
+        filter: Box<QueryDsl>,
+    },
+
+    Exclude {
+        query: Box<QueryDsl>,
+        exclude: Box<QueryDsl>,
+    },
+}
+
+fn default_score() -> f32 {
+    1.0f32
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SpanQueryDsl {
+    Term {
+        term: TermValue,
+    },
+
+    Near {
+        clauses: Vec<SpanQueryDsl>,
+        slop: u32,
+        in_order: bool,
+    },
+
+    Or {
+        clauses: Vec<SpanQueryDsl>,
+    },
+
+    Not {
+        include: Box<SpanQueryDsl>,
+        exclude: Box<SpanQueryDsl>,
+    },
+
+    First {
+        span: Box<SpanQueryDsl>,
+        end: u32,
+    },
+
+    Containing {
+        big: Box<SpanQueryDsl>,
+        little: Box<SpanQueryDsl>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum IntervalsDsl {
+    Term {
+        term: TermValue,
+    },
+
+    Or {
+        sources: Vec<IntervalsDsl>,
+    },
+
+    Ordered {
+        sources: Vec<IntervalsDsl>,
+    },
+
+    Unordered {
+        sources: Vec<IntervalsDsl>,
+    },
+
+    MaxGaps {
+        source: Box<IntervalsDsl>,
+        max_gaps: u32,
+    },
+
+    Containing {
+        big: Box<IntervalsDsl>,
+        small: Box<IntervalsDsl>,
+    },
+
+    ContainedBy {
+        small: Box<IntervalsDsl>,
+        big: Box<IntervalsDsl>,
+    },
+
+    NotContaining {
+        minuend: Box<IntervalsDsl>,
+        subtrahend: Box<IntervalsDsl>,
+    },
+
+    NotContainedBy {
+        minuend: Box<IntervalsDsl>,
+        subtrahend: Box<IntervalsDsl>,
+    },
+
+    Overlapping {
+        source: Box<IntervalsDsl>,
+        reference: Box<IntervalsDsl>,
+    },
+
+    NotOverlapping {
+        minuend: Box<IntervalsDsl>,
+        subtrahend: Box<IntervalsDsl>,
+    },
+}
+
+impl TermValue {
+    /// Converts the value into a term for a field of the specified type
+    pub fn to_term(&self, field_type: &FieldType) -> Result<Term, QueryDslError> {
+        match (field_type, self) {
+            (&FieldType::Text, &TermValue::String(ref value)) => Ok(Term::from_string(value)),
+            (&FieldType::PlainString, &TermValue::String(ref value)) => Ok(Term::from_string(value)),
+            (&FieldType::I64, &TermValue::Integer(value)) => Ok(Term::from_integer(value)),
+            (&FieldType::Boolean, &TermValue::Boolean(value)) => Ok(Term::from_boolean(value)),
+            (&FieldType::DateTime, &TermValue::String(ref value)) => {
+                match DateTime::parse_from_rfc3339(value) {
+                    Ok(value) => Ok(Term::from_datetime(&value.with_timezone(&Utc))),
+                    Err(_) => Err(QueryDslError::InvalidValue(self.clone(), field_type.clone())),
+                }
+            }
+            _ => Err(QueryDslError::InvalidValue(self.clone(), field_type.clone())),
+        }
+    }
+
+    /// Reads the value of a term from a field of the specified type
+    pub fn from_term(term: &Term, field_type: &FieldType) -> Result<TermValue, QueryDslError> {
+        let invalid_term = || QueryDslError::InvalidTerm(term.clone(), field_type.clone());
+        let bytes = term.as_bytes();
+
+        match *field_type {
+            FieldType::Text | FieldType::PlainString => {
+                match String::from_utf8(bytes.to_vec()) {
+                    Ok(value) => Ok(TermValue::String(value)),
+                    Err(_) => Err(invalid_term()),
+                }
+            }
+            FieldType::I64 => {
+                match term.to_integer() {
+                    Some(value) => Ok(TermValue::Integer(value)),
+                    None => Err(invalid_term()),
+                }
+            }
+            FieldType::Boolean => {
+                match bytes {
+                    b"t" => Ok(TermValue::Boolean(true)),
+                    b"f" => Ok(TermValue::Boolean(false)),
+                    _ => Err(invalid_term()),
+                }
+            }
+            FieldType::DateTime => {
+                match term.to_datetime() {
+                    Some(value) => Ok(TermValue::String(value.to_rfc3339())),
+                    None => Err(invalid_term()),
+                }
+            }
+        }
+    }
+}
+
+fn field_type_by_name(schema: &Schema, name: &str) -> Result<(FieldId, FieldType), QueryDslError> {
+    let field = match schema.get_field_by_name(name) {
+        Some(field) => field,
+        None => return Err(QueryDslError::UnknownField(name.to_string())),
+    };
+
+    match schema.get(&field) {
+        Some(field_info) => Ok((field, field_info.field_type.clone())),
+        None => Err(QueryDslError::FieldDoesntExist(field)),
+    }
+}
+
+fn field_name_and_type(schema: &Schema, field: FieldId) -> Result<(String, FieldType), QueryDslError> {
+    match schema.get(&field) {
+        Some(field_info) => Ok((field_info.name().to_string(), field_info.field_type.clone())),
+        None => Err(QueryDslError::FieldDoesntExist(field)),
+    }
+}
+
+fn to_terms(values: &[TermValue], field_type: &FieldType) -> Result<Vec<Term>, QueryDslError> {
+    values.iter().map(|value| value.to_term(field_type)).collect()
+}
+
+/// Converts a value at one end of a range into a term
+/// Values of DateTime fields can be date math expressions, which are resolved rounding up if "round_up" is set
+fn range_value_to_term(value: &TermValue, field_type: &FieldType, round_up: bool, now: &DateTime<Utc>, timezone: &DateMathTimeZone) -> Result<Term, QueryDslError> {
+    match (field_type, value) {
+        (&FieldType::DateTime, &TermValue::String(ref expression)) => {
+            match DateMath::parse(expression).and_then(|date_math| date_math.resolve(now, timezone, round_up)) {
+                Ok(value) => Ok(Term::from_datetime(&value)),
+                Err(_) => Err(QueryDslError::InvalidValue(value.clone(), field_type.clone())),
+            }
+        }
+        _ => value.to_term(field_type),
+    }
+}
+
+/// Converts one end of a range into a bound, from its exclusive and inclusive values
+///
+/// Included upper bounds and excluded lower bounds are rounded up, so the range covers
+/// the whole unit that date math rounds to.
+fn to_bound<F>(field: &str, exclusive: &Option<TermValue>, inclusive: &Option<TermValue>, is_upper: bool, to_term: &F) -> Result<Bound<Term>, QueryDslError>
+    where F: Fn(&TermValue, bool) -> Result<Term, QueryDslError>
+{
+    match (exclusive, inclusive) {
+        (&Some(_), &Some(_)) => Err(QueryDslError::ConflictingBounds(field.to_string())),
+        (&Some(ref value), &None) => Ok(Bound::Excluded(try!(to_term(value, !is_upper)))),
+        (&None, &Some(ref value)) => Ok(Bound::Included(try!(to_term(value, is_upper)))),
+        (&None, &None) => Ok(Bound::Unbounded),
+    }
+}
+
+/// Converts a bound into the exclusive and inclusive values of one end of a range
+fn from_bound(bound: &Bound<Term>, field_type: &FieldType) -> Result<(Option<TermValue>, Option<TermValue>), QueryDslError> {
+    match *bound {
+        Bound::Excluded(ref term) => Ok((Some(try!(TermValue::from_term(term, field_type))), None)),
+        Bound::Included(ref term) => Ok((None, Some(try!(TermValue::from_term(term, field_type))))),
+        Bound::Unbounded => Ok((None, None)),
+    }
+}
+
+impl QueryDsl {
+    /// Converts a query into its serializable representation
+    pub fn from_query(query: &Query, schema: &Schema) -> Result<QueryDsl, QueryDslError> {
+        Ok(match *query {
+            Query::All{score} => QueryDsl::All {
+                score: score,
+            },
+            Query::None => QueryDsl::None,
+            Query::Term{field, ref term, ref scorer} => {
+                let (field_name, field_type) = try!(field_name_and_type(schema, field));
+
+                QueryDsl::Term {
+                    field: field_name,
+                    term: try!(TermValue::from_term(term, &field_type)),
+                    scorer: scorer.clone(),
+                }
+            }
+            Query::MultiTerm{field, ref term_selector, ref scorer} => {
+                let (field_name, _) = try!(field_name_and_type(schema, field));
+
+                QueryDsl::MultiTerm {
+                    field: field_name,
+                    term_selector: term_selector.clone(),
+                    scorer: scorer.clone(),
+                }
+            }
+            Query::Range{field, ref lower, ref upper, score} => {
+                let (field_name, field_type) = try!(field_name_and_type(schema, field));
+                let (gt, gte) = try!(from_bound(lower, &field_type));
+                let (lt, lte) = try!(from_bound(upper, &field_type));
+
+                QueryDsl::Range {
+                    field: field_name,
+                    gt: gt,
+                    gte: gte,
+                    lt: lt,
+                    lte: lte,
+                    time_zone: None,
+                    score: score,
+                }
+            }
+            Query::Phrase{field, ref terms, slop, ref scorer} => {
+                let (field_name, field_type) = try!(field_name_and_type(schema, field));
+
+                // Positions are only needed if the terms aren't next to each other
+                let has_gaps = terms.iter().enumerate().any(|(i, &(position, _))| position != i as u32);
+                let positions = if has_gaps {
+                    Some(terms.iter().map(|&(position, _)| position).collect())
+                } else {
+                    None
+                };
+
+                QueryDsl::Phrase {
+                    field: field_name,
+                    terms: try!(terms.iter().map(|&(_, ref term)| TermValue::from_term(term, &field_type)).collect()),
+                    positions: positions,
+                    slop: slop,
+                    scorer: scorer.clone(),
+                }
+            }
+            Query::Span{field, ref span, ref scorer} => {
+                let (field_name, field_type) = try!(field_name_and_type(schema, field));
+
+                QueryDsl::Span {
+                    field: field_name,
+                    span: try!(SpanQueryDsl::from_span_query(span, &field_type)),
+                    scorer: scorer.clone(),
+                }
+            }
+            Query::Intervals{field, ref intervals, ref scorer} => {
+                let (field_name, field_type) = try!(field_name_and_type(schema, field));
+
+                QueryDsl::Intervals {
+                    field: field_name,
+                    intervals: try!(IntervalsDsl::from_intervals(intervals, &field_type)),
+                    scorer: scorer.clone(),
+                }
+            }
+            Query::Conjunction{ref queries} => QueryDsl::Conjunction {
+                queries: try!(queries.iter().map(|query| QueryDsl::from_query(query, schema)).collect()),
+            },
+            Query::Disjunction{ref queries} => QueryDsl::Disjunction {
+                queries: try!(queries.iter().map(|query| QueryDsl::from_query(query, schema)).collect()),
+            },
+            Query::DisjunctionMax{ref queries} => QueryDsl::DisjunctionMax {
+                queries: try!(queries.iter().map(|query| QueryDsl::from_query(query, schema)).collect()),
+            },
+            Query::Filter{ref query, ref filter} => QueryDsl::Filter {
+                query: Box::new(try!(QueryDsl::from_query(query, schema))),
+                filter: Box::new(try!(QueryDsl::from_query(filter, schema))),
+            },
+            Query::Exclude{ref query, ref exclude} => QueryDsl::Exclude {
+                query: Box::new(try!(QueryDsl::from_query(query, schema))),
+                exclude: Box::new(try!(QueryDsl::from_query(exclude, schema))),
+            },
+        })
+    }
+
+    /// Converts this representation back into a query, looking up fields in the schema
+    pub fn to_query(&self, schema: &Schema) -> Result<Query, QueryDslError> {
+        self.to_query_at(schema, &Utc::now())
+    }
+
+    /// Converts this representation back into a query, with "now" in date math
+    /// referring to the specified time
+    pub fn to_query_at(&self, schema: &Schema, now: &DateTime<Utc>) -> Result<Query, QueryDslError> {
+        Ok(match *self {
+            QueryDsl::All{score} => Query::All {
+                score: score,
+            },
+            QueryDsl::None => Query::None,
+            QueryDsl::Term{ref field, ref term, ref scorer} => {
+                let (field, field_type) = try!(field_type_by_name(schema, field));
+
+                Query::Term {
+                    field: field,
+                    term: try!(term.to_term(&field_type)),
+                    scorer: scorer.clone(),
+                }
+            }
+            QueryDsl::MultiTerm{ref field, ref term_selector, ref scorer} => {
+                let (field, _) = try!(field_type_by_name(schema, field));
+
+                if let Err(e) = term_selector.validate() {
+                    return Err(QueryDslError::InvalidTermSelector(e));
+                }
+
+                Query::MultiTerm {
+                    field: field,
+                    term_selector: term_selector.clone(),
+                    scorer: scorer.clone(),
+                }
+            }
+            QueryDsl::Range{ref field, ref gt, ref gte, ref lt, ref lte, ref time_zone, score} => {
+                let (field_id, field_type) = try!(field_type_by_name(schema, field));
+
+                let timezone = match *time_zone {
+                    Some(ref time_zone) => {
+                        match parse_timezone(time_zone) {
+                            Some(timezone) => timezone,
+                            None => return Err(QueryDslError::InvalidTimeZone(time_zone.clone())),
+                        }
+                    }
+                    None => DateMathTimeZone::utc(),
+                };
+
+                let to_term = |value: &TermValue, round_up: bool| range_value_to_term(value, &field_type, round_up, now, &timezone);
+
+                Query::Range {
+                    field: field_id,
+                    lower: try!(to_bound(field, gt, gte, false, &to_term)),
+                    upper: try!(to_bound(field, lt, lte, true, &to_term)),
+                    score: score,
+                }
+            }
+            QueryDsl::Phrase{ref field, ref terms, ref positions, slop, ref scorer} => {
+                let (field_id, field_type) = try!(field_type_by_name(schema, field));
+                let terms = try!(to_terms(terms, &field_type));
+
+                let positions = match *positions {
+                    Some(ref positions) => {
+                        let is_ordered = positions.windows(2).all(|pair| pair[0] <= pair[1]);
+                        if positions.len() != terms.len() || !is_ordered {
+                            return Err(QueryDslError::InvalidPhrasePositions(field.clone()));
+                        }
+
+                        positions.clone()
+                    }
+                    None => (0..terms.len() as u32).collect(),
+                };
+
+                Query::Phrase {
+                    field: field_id,
+                    terms: positions.into_iter().zip(terms).collect(),
+                    slop: slop,
+                    scorer: scorer.clone(),
+                }
+            }
+            QueryDsl::Span{ref field, ref span, ref scorer} => {
+                let (field, field_type) = try!(field_type_by_name(schema, field));
+
+                Query::Span {
+                    field: field,
+                    span: try!(span.to_span_query(&field_type)),
+                    scorer: scorer.clone(),
+                }
+            }
+            QueryDsl::Intervals{ref field, ref intervals, ref scorer} => {
+                let (field, field_type) = try!(field_type_by_name(schema, field));
+
+                Query::Intervals {
+                    field: field,
+                    intervals: try!(intervals.to_intervals(&field_type)),
+                    scorer: scorer.clone(),
+                }
+            }
+            QueryDsl::Conjunction{ref queries} => Query::Conjunction {
+                queries: try!(queries.iter().map(|query| query.to_query_at(schema, now)).collect()),
+            },
+            QueryDsl::Disjunction{ref queries} => Query::Disjunction {
+                queries: try!(queries.iter().map(|query| query.to_query_at(schema, now)).collect()),
+            },
+            QueryDsl::DisjunctionMax{ref queries} => Query::DisjunctionMax {
+                queries: try!(queries.iter().map(|query| query.to_query_at(schema, now)).collect()),
+            },
+            QueryDsl::Filter{ref query, ref filter} => Query::Filter {
+                query: Box::new(try!(query.to_query_at(schema, now))),
+                filter: Box::new(try!(filter.to_query_at(schema, now))),
+            },
+            QueryDsl::Exclude{ref query, ref exclude} => Query::Exclude {
+                query: Box::new(try!(query.to_query_at(schema, now))),
+                exclude: Box::new(try!(exclude.to_query_at(schema, now))),
+            },
+        })
+    }
+}
+
+impl SpanQueryDsl {
+    /// Converts a span query into its serializable representation
+    pub fn from_span_query(span: &SpanQuery, field_type: &FieldType) -> Result<SpanQueryDsl, QueryDslError> {
+        let from = |span: &SpanQuery| SpanQueryDsl::from_span_query(span, field_type).map(Box::new);
+
+        Ok(match *span {
+            SpanQuery::Term{ref term} => SpanQueryDsl::Term {
+                term: try!(TermValue::from_term(term, field_type)),
+            },
+            SpanQuery::Near{ref clauses, slop, in_order} => SpanQueryDsl::Near {
+                clauses: try!(clauses.iter().map(|clause| SpanQueryDsl::from_span_query(clause, field_type)).collect()),
+                slop: slop,
+                in_order: in_order,
+            },
+            SpanQuery::Or{ref clauses} => SpanQueryDsl::Or {
+                clauses: try!(clauses.iter().map(|clause| SpanQueryDsl::from_span_query(clause, field_type)).collect()),
+            },
+            SpanQuery::Not{ref include, ref exclude} => SpanQueryDsl::Not {
+                include: try!(from(include)),
+                exclude: try!(from(exclude)),
+            },
+            SpanQuery::First{ref span, end} => SpanQueryDsl::First {
+                span: try!(from(span)),
+                end: end,
+            },
+            SpanQuery::Containing{ref big, ref little} => SpanQueryDsl::Containing {
+                big: try!(from(big)),
+                little: try!(from(little)),
+            },
+        })
+    }
+
+    /// Converts this representation back into a span query
+    pub fn to_span_query(&self, field_type: &FieldType) -> Result<SpanQuery, QueryDslError> {
+        let to = |span: &SpanQueryDsl| span.to_span_query(field_type).map(Box::new);
+
+        Ok(match *self {
+            SpanQueryDsl::Term{ref term} => SpanQuery::Term {
+                term: try!(term.to_term(field_type)),
+            },
+            SpanQueryDsl::Near{ref clauses, slop, in_order} => SpanQuery::Near {
+                clauses: try!(clauses.iter().map(|clause| clause.to_span_query(field_type)).collect()),
+                slop: slop,
+                in_order: in_order,
+            },
+            SpanQueryDsl::Or{ref clauses} => SpanQuery::Or {
+                clauses: try!(clauses.iter().map(|clause| clause.to_span_query(field_type)).collect()),
+            },
+            SpanQueryDsl::Not{ref include, ref exclude} => SpanQuery::Not {
+                include: try!(to(include)),
+                exclude: try!(to(exclude)),
+            },
+            SpanQueryDsl::First{ref span, end} => SpanQuery::First {
+                span: try!(to(span)),
+                end: end,
+            },
+            SpanQueryDsl::Containing{ref big, ref little} => SpanQuery::Containing {
+                big: try!(to(big)),
+                little: try!(to(little)),
+            },
+        })
+    }
+}
+
+impl IntervalsDsl {
+    /// Converts an intervals source into its serializable representation
+    pub fn from_intervals(intervals: &Intervals, field_type: &FieldType) -> Result<IntervalsDsl, QueryDslError> {
+        let from = |source: &Intervals| IntervalsDsl::from_intervals(source, field_type).map(Box::new);
+        let from_all = |sources: &Vec<Intervals>| -> Result<Vec<IntervalsDsl>, QueryDslError> {
+            sources.iter().map(|source| IntervalsDsl::from_intervals(source, field_type)).collect()
+        };
+
+        Ok(match *intervals {
+            Intervals::Term{ref term} => IntervalsDsl::Term {
+                term: try!(TermValue::from_term(term, field_type)),
+            },
+            Intervals::Or{ref sources} => IntervalsDsl::Or {
+                sources: try!(from_all(sources)),
+            },
+            Intervals::Ordered{ref sources} => IntervalsDsl::Ordered {
+                sources: try!(from_all(sources)),
+            },
+            Intervals::Unordered{ref sources} => IntervalsDsl::Unordered {
+                sources: try!(from_all(sources)),
+            },
+            Intervals::MaxGaps{ref source, max_gaps} => IntervalsDsl::MaxGaps {
+                source: try!(from(source)),
+                max_gaps: max_gaps,
+            },
+            Intervals::Containing{ref big, ref small} => IntervalsDsl::Containing {
+                big: try!(from(big)),
+                small: try!(from(small)),
+            },
+            Intervals::ContainedBy{ref small, ref big} => IntervalsDsl::ContainedBy {
+                small: try!(from(small)),
+                big: try!(from(big)),
+            },
+            Intervals::NotContaining{ref minuend, ref subtrahend} => IntervalsDsl::NotContaining {
+                minuend: try!(from(minuend)),
+                subtrahend: try!(from(subtrahend)),
+            },
+            Intervals::NotContainedBy{ref minuend, ref subtrahend} => IntervalsDsl::NotContainedBy {
+                minuend: try!(from(minuend)),
+                subtrahend: try!(from(subtrahend)),
+            },
+            Intervals::Overlapping{ref source, ref reference} => IntervalsDsl::Overlapping {
+                source: try!(from(source)),
+                reference: try!(from(reference)),
+            },
+            Intervals::NotOverlapping{ref minuend, ref subtrahend} => IntervalsDsl::NotOverlapping {
+                minuend: try!(from(minuend)),
+                subtrahend: try!(from(subtrahend)),
+            },
+        })
+    }
+
+    /// Converts this representation back into an intervals source
+    pub fn to_intervals(&self, field_type: &FieldType) -> Result<Intervals, QueryDslError> {
+        let to = |source: &IntervalsDsl| source.to_intervals(field_type).map(Box::new);
+        let to_all = |sources: &Vec<IntervalsDsl>| -> Result<Vec<Intervals>, QueryDslError> {
+            sources.iter().map(|source| source.to_intervals(field_type)).collect()
+        };
+
+        Ok(match *self {
+            IntervalsDsl::Term{ref term} => Intervals::Term {
+                term: try!(term.to_term(field_type)),
+            },
+            IntervalsDsl::Or{ref sources} => Intervals::Or {
+                sources: try!(to_all(sources)),
+            },
+            IntervalsDsl::Ordered{ref sources} => Intervals::Ordered {
+                sources: try!(to_all(sources)),
+            },
+            IntervalsDsl::Unordered{ref sources} => Intervals::Unordered {
+                sources: try!(to_all(sources)),
+            },
+            IntervalsDsl::MaxGaps{ref source, max_gaps} => Intervals::MaxGaps {
+                source: try!(to(source)),
+                max_gaps: max_gaps,
+            },
+            IntervalsDsl::Containing{ref big, ref small} => Intervals::Containing {
+                big: try!(to(big)),
+                small: try!(to(small)),
+            },
+            IntervalsDsl::ContainedBy{ref small, ref big} => Intervals::ContainedBy {
+                small: try!(to(small)),
+                big: try!(to(big)),
+            },
+            IntervalsDsl::NotContaining{ref minuend, ref subtrahend} => Intervals::NotContaining {
+                minuend: try!(to(minuend)),
+                subtrahend: try!(to(subtrahend)),
+            },
+            IntervalsDsl::NotContainedBy{ref minuend, ref subtrahend} => Intervals::NotContainedBy {
+                minuend: try!(to(minuend)),
+                subtrahend: try!(to(subtrahend)),
+            },
+            IntervalsDsl::Overlapping{ref source, ref reference} => Intervals::Overlapping {
+                source: try!(to(source)),
+                reference: try!(to(reference)),
+            },
+            IntervalsDsl::NotOverlapping{ref minuend, ref subtrahend} => Intervals::NotOverlapping {
+                minuend: try!(to(minuend)),
+                subtrahend: try!(to(subtrahend)),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use std::ops::Bound;
+
+    use chrono::{DateTime, Utc};
+
+    use term::Term;
+    use schema::{Schema, FieldType, FIELD_INDEXED, FIELD_POSITIONS};
+    use similarity::SimilarityModel;
+    use query::Query;
+    use query::multi_term_selector::{MultiTermSelector, MultiTermSelectorError};
+    use query::term_scorer::TermScorer;
+    use query::span::SpanQuery;
+    use query::intervals::Intervals;
+    use super::{QueryDsl, QueryDslError, TermValue};
+
+    fn make_schema() -> Schema {
+        let mut schema = Schema::new();
+        schema.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED | FIELD_POSITIONS, None).unwrap();
+        schema.add_field("slug".to_string(), FieldType::PlainString, FIELD_INDEXED, None).unwrap();
+        schema.add_field("pk".to_string(), FieldType::I64, FIELD_INDEXED, None).unwrap();
+        schema.add_field("published".to_string(), FieldType::Boolean, FIELD_INDEXED, None).unwrap();
+        schema.add_field("created".to_string(), FieldType::DateTime, FIELD_INDEXED, None).unwrap();
+        schema
+    }
+
+    fn round_trip(query: &Query, schema: &Schema) -> Query {
+        let json = serde_json::to_string(&QueryDsl::from_query(query, schema).unwrap()).unwrap();
+        let dsl: QueryDsl = serde_json::from_str(&json).unwrap();
+        dsl.to_query(schema).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let schema = make_schema();
+        let title = schema.get_field_by_name("title").unwrap();
+        let slug = schema.get_field_by_name("slug").unwrap();
+        let pk = schema.get_field_by_name("pk").unwrap();
+        let published = schema.get_field_by_name("published").unwrap();
+        let created = schema.get_field_by_name("created").unwrap();
+        let date = "2016-07-23T16:15:00.123456+01:00".parse::<DateTime<Utc>>().unwrap();
+
+        let queries = vec![
+            Query::all(),
+            Query::all().boost(2.0),
+            Query::None,
+            Query::term(title, Term::from_string("hello")),
+            Query::term(title, Term::from_string("hello")).boost(2.5),
+            Query::term(slug, Term::from_string("hello-world")),
+            Query::term(pk, Term::from_integer(-123)),
+            Query::term(published, Term::from_boolean(true)),
+            Query::term(created, Term::from_datetime(&date)),
+            Query::Term {
+                field: title,
+                term: Term::from_string("hello"),
+                scorer: TermScorer {
+                    similarity_model: SimilarityModel::TfIdf,
+                    boost: 1.0,
+                },
+            },
+            Query::MultiTerm {
+                field: title,
+                term_selector: MultiTermSelector::Prefix("hel".to_string()),
+                scorer: TermScorer::default(),
+            },
+            Query::range(pk, Bound::Included(Term::from_integer(-10)), Bound::Excluded(Term::from_integer(10))),
+            Query::range(created, Bound::Excluded(Term::from_datetime(&date)), Bound::Unbounded).boost(2.0),
+            Query::range(slug, Bound::Unbounded, Bound::Included(Term::from_string("m"))),
+            Query::phrase(title, vec![Term::from_string("hello"), Term::from_string("world")]),
+            Query::Phrase {
+                field: title,
+                terms: vec![(0, Term::from_string("united")), (2, Term::from_string("america"))],
+                slop: 0,
+                scorer: TermScorer::default(),
+            },
+            Query::span(title, SpanQuery::not(
+                SpanQuery::near(vec![
+                    SpanQuery::term(Term::from_string("quick")),
+                    SpanQuery::or(vec![SpanQuery::term(Term::from_string("fox"))]),
+                ], 2, true),
+                SpanQuery::first(SpanQuery::term(Term::from_string("brown")), 3),
+            )),
+            Query::intervals(title, Intervals::max_gaps(
+                Intervals::not_containing(
+                    Intervals::unordered(vec![Intervals::term(Term::from_string("quick")), Intervals::phrase(vec![Term::from_string("brown"), Term::from_string("fox")])]),
+                    Intervals::term(Term::from_string("lazy")),
+                ),
+                3,
+            )),
+            Query::Conjunction {
+                queries: vec![
+                    Query::term(title, Term::from_string("hello")),
+                    Query::Disjunction {
+                        queries: vec![
+                            Query::term(pk, Term::from_integer(1)),
+                            Query::DisjunctionMax {
+                                queries: vec![Query::term(pk, Term::from_integer(2))],
+                            },
+                        ],
+                    },
+                ],
+            },
+            Query::term(title, Term::from_string("hello"))
+                .filter(Query::term(published, Term::from_boolean(true)))
+                .exclude(Query::term(slug, Term::from_string("draft"))),
+        ];
+
+        for query in queries {
+            assert_eq!(round_trip(&query, &schema), query);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_json() {
+        let schema = make_schema();
+        let title = schema.get_field_by_name("title").unwrap();
+        let pk = schema.get_field_by_name("pk").unwrap();
+
+        let dsl: QueryDsl = serde_json::from_str(r#"{
+            "Filter": {
+                "query": {"Phrase": {"field": "title", "terms": ["hello", "world"], "slop": 1}},
+                "filter": {"Term": {"field": "pk", "term": 123, "scorer": {"similarity_model": "TfIdf", "boost": 2.0}}}
+            }
+        }"#).unwrap();
+
+        assert_eq!(dsl.to_query(&schema).unwrap(), Query::Phrase {
+            field: title,
+            terms: vec![(0, Term::from_string("hello")), (1, Term::from_string("world"))],
+            slop: 1,
+            scorer: TermScorer::default(),
+        }.filter(Query::Term {
+            field: pk,
+            term: Term::from_integer(123),
+            scorer: TermScorer {
+                similarity_model: SimilarityModel::TfIdf,
+                boost: 2.0,
+            },
+        }));
+    }
+
+    #[test]
+    fn test_serialize_json() {
+        let schema = make_schema();
+        let created = schema.get_field_by_name("created").unwrap();
+        let date = "2016-07-23T16:15:00+01:00".parse::<DateTime<Utc>>().unwrap();
+        let query = Query::Term {
+            field: created,
+            term: Term::from_datetime(&date),
+            scorer: TermScorer {
+                similarity_model: SimilarityModel::TfIdf,
+                boost: 2.0,
+            },
+        };
+        let dsl = QueryDsl::from_query(&query, &schema).unwrap();
+
+        assert_eq!(serde_json::to_value(&dsl).unwrap(), json_value(r#"{
+            "Term": {
+                "field": "created",
+                "term": "2016-07-23T15:15:00+00:00",
+                "scorer": {"similarity_model": "TfIdf", "boost": 2.0}
+            }
+        }"#));
+    }
+
+    #[test]
+    fn test_range_json() {
+        let schema = make_schema();
+        let pk = schema.get_field_by_name("pk").unwrap();
+        let query = Query::range(pk, Bound::Included(Term::from_integer(10)), Bound::Unbounded);
+
+        assert_eq!(serde_json::to_value(&QueryDsl::from_query(&query, &schema).unwrap()).unwrap(), json_value(r#"{
+            "Range": {"field": "pk", "gte": 10, "score": 1.0}
+        }"#));
+
+        let dsl: QueryDsl = serde_json::from_str(r#"{"Range": {"field": "pk", "gte": 10}}"#).unwrap();
+        assert_eq!(dsl.to_query(&schema).unwrap(), query);
+
+        let dsl: QueryDsl = serde_json::from_str(r#"{"Range": {"field": "pk", "gt": 10, "gte": 10}}"#).unwrap();
+        match dsl.to_query(&schema) {
+            Err(QueryDslError::ConflictingBounds(ref field)) if field == "pk" => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_range_date_math() {
+        let schema = make_schema();
+        let created = schema.get_field_by_name("created").unwrap();
+        let now = "2017-03-15T10:30:00Z".parse::<DateTime<Utc>>().unwrap();
+        let datetime = |value: &str| Term::from_datetime(&value.parse::<DateTime<Utc>>().unwrap());
+
+        let dsl: QueryDsl = serde_json::from_str(r#"{"Range": {"field": "created", "gte": "now-7d/d", "lte": "now/d", "time_zone": "+01:00"}}"#).unwrap();
+        assert_eq!(dsl.to_query_at(&schema, &now).unwrap(), Query::range(
+            created,
+            Bound::Included(datetime("2017-03-07T23:00:00Z")),
+            Bound::Included(datetime("2017-03-15T22:59:59.999999Z"))
+        ));
+
+        let dsl: QueryDsl = serde_json::from_str(r#"{"Range": {"field": "created", "gt": "2017-03-01||/M", "lt": "now/d"}}"#).unwrap();
+        assert_eq!(dsl.to_query_at(&schema, &now).unwrap(), Query::range(
+            created,
+            Bound::Excluded(datetime("2017-03-31T23:59:59.999999Z")),
+            Bound::Excluded(datetime("2017-03-15T00:00:00Z"))
+        ));
+
+        let dsl: QueryDsl = serde_json::from_str(r#"{"Range": {"field": "created", "gte": "now/d", "time_zone": "America/New_York"}}"#).unwrap();
+        assert_eq!(dsl.to_query_at(&schema, &now).unwrap(), Query::range(
+            created,
+            Bound::Included(datetime("2017-03-15T04:00:00Z")),
+            Bound::Unbounded
+        ));
+
+        let dsl: QueryDsl = serde_json::from_str(r#"{"Range": {"field": "created", "gte": "now", "time_zone": "Mars"}}"#).unwrap();
+        match dsl.to_query_at(&schema, &now) {
+            Err(QueryDslError::InvalidTimeZone(ref time_zone)) if time_zone == "Mars" => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        let dsl: QueryDsl = serde_json::from_str(r#"{"Range": {"field": "created", "gte": "now+1x"}}"#).unwrap();
+        match dsl.to_query_at(&schema, &now) {
+            Err(QueryDslError::InvalidValue(..)) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_phrase_positions() {
+        let schema = make_schema();
+
+        let dsl: QueryDsl = serde_json::from_str(r#"{"Phrase": {"field": "title", "terms": ["united", "america"], "positions": [0, 2]}}"#).unwrap();
+        match dsl.to_query(&schema).unwrap() {
+            Query::Phrase{ref terms, ..} => assert_eq!(*terms, vec![(0, Term::from_string("united")), (2, Term::from_string("america"))]),
+            query => panic!("unexpected query: {:?}", query),
+        }
+
+        // Terms can share a position
+        let dsl: QueryDsl = serde_json::from_str(r#"{"Phrase": {"field": "title", "terms": ["usa", "united", "states"], "positions": [0, 0, 1]}}"#).unwrap();
+        match dsl.to_query(&schema).unwrap() {
+            Query::Phrase{ref terms, ..} => assert_eq!(*terms, vec![(0, Term::from_string("usa")), (0, Term::from_string("united")), (1, Term::from_string("states"))]),
+            query => panic!("unexpected query: {:?}", query),
+        }
+
+        for json in &[r#"{"Phrase": {"field": "title", "terms": ["united", "america"], "positions": [0]}}"#, r#"{"Phrase": {"field": "title", "terms": ["united", "america"], "positions": [2, 0]}}"#] {
+            let dsl: QueryDsl = serde_json::from_str(json).unwrap();
+
+            match dsl.to_query(&schema) {
+                Err(QueryDslError::InvalidPhrasePositions(ref field)) if field == "title" => {}
+                result => panic!("unexpected result: {:?}", result),
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_term_selector() {
+        let schema = make_schema();
+        let dsl: QueryDsl = serde_json::from_str(r#"{"MultiTerm": {"field": "title", "term_selector": {"Fuzzy": {"term": "quick", "max_edits": 3}}}}"#).unwrap();
+
+        match dsl.to_query(&schema) {
+            Err(QueryDslError::InvalidTermSelector(MultiTermSelectorError::TooManyEdits(3))) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    fn json_value(json: &str) -> serde_json::Value {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        let schema = make_schema();
+        let dsl: QueryDsl = serde_json::from_str(r#"{"Term": {"field": "foo", "term": "hello"}}"#).unwrap();
+
+        match dsl.to_query(&schema) {
+            Err(QueryDslError::UnknownField(ref name)) if name == "foo" => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_invalid_value() {
+        let schema = make_schema();
+
+        for json in &[r#"{"Term": {"field": "pk", "term": "hello"}}"#, r#"{"Term": {"field": "created", "term": "yesterday"}}"#] {
+            let dsl: QueryDsl = serde_json::from_str(json).unwrap();
+
+            match dsl.to_query(&schema) {
+                Err(QueryDslError::InvalidValue(..)) => {}
+                result => panic!("unexpected result: {:?}", result),
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_term() {
+        let schema = make_schema();
+        let pk = schema.get_field_by_name("pk").unwrap();
+
+        match QueryDsl::from_query(&Query::term(pk, Term::from_string("hello")), &schema) {
+            Err(QueryDslError::InvalidTerm(_, FieldType::I64)) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        assert_eq!(TermValue::from_term(&Term::from_integer(5), &FieldType::I64).unwrap(), TermValue::Integer(5));
+    }
+}
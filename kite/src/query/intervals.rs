@@ -0,0 +1,567 @@
+//! Interval queries
+//!
+//! An intervals source finds the ranges of positions in a field that match it.
+//! Sources can be combined to match terms that are near each other, in order,
+//! within other intervals and so on.
+//!
+//! Combinations only return minimal intervals: an interval is never returned
+//! if it contains another interval that also matches. This means a document is
+//! scored by how tightly its terms are grouped, rather than how many ways they
+//! can be matched.
+
+use roaring::RoaringBitmap;
+
+use term::Term;
+
+/// A range of positions matched by an intervals source. Both ends are inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Interval {
+    pub start: u32,
+    pub end: u32,
+
+    /// The number of positions in the interval that aren't covered by the
+    /// sub-intervals that it is made up of
+    pub gaps: u32,
+}
+
+impl Interval {
+    pub fn new(start: u32, end: u32, gaps: u32) -> Interval {
+        Interval {
+            start: start,
+            end: end,
+            gaps: gaps,
+        }
+    }
+
+    /// Returns true if the other interval is within this one
+    pub fn contains(&self, other: &Interval) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    /// Returns true if any position is in both intervals
+    pub fn overlaps(&self, other: &Interval) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+}
+
+/// A source of intervals
+#[derive(Debug, Clone, PartialEq)]
+pub enum Intervals {
+    /// Each position of the term
+    Term {
+        term: Term,
+    },
+
+    /// Intervals of any of the sources
+    Or {
+        sources: Vec<Intervals>,
+    },
+
+    /// Minimal intervals that contain an interval from each source, in order and without overlapping
+    Ordered {
+        sources: Vec<Intervals>,
+    },
+
+    /// Minimal intervals that contain an interval from each source, in any order
+    Unordered {
+        sources: Vec<Intervals>,
+    },
+
+    /// Intervals of the source that have no more than "max_gaps" gaps
+    MaxGaps {
+        source: Box<Intervals>,
+        max_gaps: u32,
+    },
+
+    /// Intervals of "big" that contain an interval of "small"
+    Containing {
+        big: Box<Intervals>,
+        small: Box<Intervals>,
+    },
+
+    /// Intervals of "small" that are contained by an interval of "big"
+    ContainedBy {
+        small: Box<Intervals>,
+        big: Box<Intervals>,
+    },
+
+    /// Intervals of "minuend" that don't contain an interval of "subtrahend"
+    NotContaining {
+        minuend: Box<Intervals>,
+        subtrahend: Box<Intervals>,
+    },
+
+    /// Intervals of "minuend" that aren't contained by an interval of "subtrahend"
+    NotContainedBy {
+        minuend: Box<Intervals>,
+        subtrahend: Box<Intervals>,
+    },
+
+    /// Intervals of "source" that overlap an interval of "reference"
+    Overlapping {
+        source: Box<Intervals>,
+        reference: Box<Intervals>,
+    },
+
+    /// Intervals of "minuend" that don't overlap an interval of "subtrahend"
+    NotOverlapping {
+        minuend: Box<Intervals>,
+        subtrahend: Box<Intervals>,
+    },
+}
+
+impl Intervals {
+    pub fn term(term: Term) -> Intervals {
+        Intervals::Term {
+            term: term,
+        }
+    }
+
+    /// Matches the terms next to each other, in order
+    pub fn phrase(terms: Vec<Term>) -> Intervals {
+        Intervals::max_gaps(Intervals::ordered(terms.into_iter().map(Intervals::term).collect()), 0)
+    }
+
+    pub fn or(sources: Vec<Intervals>) -> Intervals {
+        Intervals::Or {
+            sources: sources,
+        }
+    }
+
+    pub fn ordered(sources: Vec<Intervals>) -> Intervals {
+        Intervals::Ordered {
+            sources: sources,
+        }
+    }
+
+    pub fn unordered(sources: Vec<Intervals>) -> Intervals {
+        Intervals::Unordered {
+            sources: sources,
+        }
+    }
+
+    pub fn max_gaps(source: Intervals, max_gaps: u32) -> Intervals {
+        Intervals::MaxGaps {
+            source: Box::new(source),
+            max_gaps: max_gaps,
+        }
+    }
+
+    pub fn containing(big: Intervals, small: Intervals) -> Intervals {
+        Intervals::Containing {
+            big: Box::new(big),
+            small: Box::new(small),
+        }
+    }
+
+    pub fn contained_by(small: Intervals, big: Intervals) -> Intervals {
+        Intervals::ContainedBy {
+            small: Box::new(small),
+            big: Box::new(big),
+        }
+    }
+
+    pub fn not_containing(minuend: Intervals, subtrahend: Intervals) -> Intervals {
+        Intervals::NotContaining {
+            minuend: Box::new(minuend),
+            subtrahend: Box::new(subtrahend),
+        }
+    }
+
+    pub fn not_contained_by(minuend: Intervals, subtrahend: Intervals) -> Intervals {
+        Intervals::NotContainedBy {
+            minuend: Box::new(minuend),
+            subtrahend: Box::new(subtrahend),
+        }
+    }
+
+    pub fn overlapping(source: Intervals, reference: Intervals) -> Intervals {
+        Intervals::Overlapping {
+            source: Box::new(source),
+            reference: Box::new(reference),
+        }
+    }
+
+    pub fn not_overlapping(minuend: Intervals, subtrahend: Intervals) -> Intervals {
+        Intervals::NotOverlapping {
+            minuend: Box::new(minuend),
+            subtrahend: Box::new(subtrahend),
+        }
+    }
+
+    /// Returns the terms that can contribute to a match
+    ///
+    /// Terms that are only used to exclude intervals (subtrahends) aren't included.
+    pub fn terms(&self) -> Vec<&Term> {
+        let mut terms = Vec::new();
+        self.collect_terms(&mut terms, false);
+        terms
+    }
+
+    /// Returns all the terms that need to be loaded to find the intervals
+    pub fn all_terms(&self) -> Vec<&Term> {
+        let mut terms = Vec::new();
+        self.collect_terms(&mut terms, true);
+        terms
+    }
+
+    fn collect_terms<'a>(&'a self, terms: &mut Vec<&'a Term>, include_subtrahends: bool) {
+        match *self {
+            Intervals::Term{ref term} => {
+                if !terms.contains(&term) {
+                    terms.push(term);
+                }
+            }
+            Intervals::Or{ref sources} | Intervals::Ordered{ref sources} | Intervals::Unordered{ref sources} => {
+                for source in sources.iter() {
+                    source.collect_terms(terms, include_subtrahends);
+                }
+            }
+            Intervals::MaxGaps{ref source, ..} => source.collect_terms(terms, include_subtrahends),
+            Intervals::Containing{ref big, ref small} | Intervals::ContainedBy{ref small, ref big} => {
+                big.collect_terms(terms, include_subtrahends);
+                small.collect_terms(terms, include_subtrahends);
+            }
+            Intervals::Overlapping{ref source, ref reference} => {
+                source.collect_terms(terms, include_subtrahends);
+                reference.collect_terms(terms, include_subtrahends);
+            }
+            Intervals::NotContaining{ref minuend, ref subtrahend} |
+            Intervals::NotContainedBy{ref minuend, ref subtrahend} |
+            Intervals::NotOverlapping{ref minuend, ref subtrahend} => {
+                minuend.collect_terms(terms, include_subtrahends);
+
+                if include_subtrahends {
+                    subtrahend.collect_terms(terms, include_subtrahends);
+                }
+            }
+        }
+    }
+
+    /// Finds the intervals that match in a single field of a document
+    ///
+    /// "load_positions" is called to get the positions of each term in the field,
+    /// and should return None if the term isn't in the field. The returned intervals
+    /// are sorted by their start position.
+    pub fn find_intervals<F>(&self, load_positions: &mut F) -> Result<Vec<Interval>, String>
+        where F: FnMut(&Term) -> Result<Option<RoaringBitmap>, String>
+    {
+        let intervals = match *self {
+            Intervals::Term{ref term} => {
+                match try!(load_positions(term)) {
+                    Some(positions) => positions.iter().map(|position| Interval::new(position, position, 0)).collect(),
+                    None => Vec::new(),
+                }
+            }
+            Intervals::Or{ref sources} => {
+                let mut intervals = Vec::new();
+                for source in sources.iter() {
+                    intervals.extend(try!(source.find_intervals(load_positions)));
+                }
+
+                intervals
+            }
+            Intervals::Ordered{ref sources} => {
+                let mut source_intervals = Vec::with_capacity(sources.len());
+                for source in sources.iter() {
+                    source_intervals.push(try!(source.find_intervals(load_positions)));
+                }
+
+                ordered(&source_intervals)
+            }
+            Intervals::Unordered{ref sources} => {
+                let mut source_intervals = Vec::with_capacity(sources.len());
+                for source in sources.iter() {
+                    source_intervals.push(try!(source.find_intervals(load_positions)));
+                }
+
+                unordered(&source_intervals)
+            }
+            Intervals::MaxGaps{ref source, max_gaps} => {
+                try!(source.find_intervals(load_positions)).into_iter()
+                    .filter(|interval| interval.gaps <= max_gaps)
+                    .collect()
+            }
+            Intervals::Containing{ref big, ref small} => {
+                let small = try!(small.find_intervals(load_positions));
+                try!(big.find_intervals(load_positions)).into_iter()
+                    .filter(|interval| small.iter().any(|other| interval.contains(other)))
+                    .collect()
+            }
+            Intervals::ContainedBy{ref small, ref big} => {
+                let big = try!(big.find_intervals(load_positions));
+                try!(small.find_intervals(load_positions)).into_iter()
+                    .filter(|interval| big.iter().any(|other| other.contains(interval)))
+                    .collect()
+            }
+            Intervals::NotContaining{ref minuend, ref subtrahend} => {
+                let subtrahend = try!(subtrahend.find_intervals(load_positions));
+                try!(minuend.find_intervals(load_positions)).into_iter()
+                    .filter(|interval| !subtrahend.iter().any(|other| interval.contains(other)))
+                    .collect()
+            }
+            Intervals::NotContainedBy{ref minuend, ref subtrahend} => {
+                let subtrahend = try!(subtrahend.find_intervals(load_positions));
+                try!(minuend.find_intervals(load_positions)).into_iter()
+                    .filter(|interval| !subtrahend.iter().any(|other| other.contains(interval)))
+                    .collect()
+            }
+            Intervals::Overlapping{ref source, ref reference} => {
+                let reference = try!(reference.find_intervals(load_positions));
+                try!(source.find_intervals(load_positions)).into_iter()
+                    .filter(|interval| reference.iter().any(|other| interval.overlaps(other)))
+                    .collect()
+            }
+            Intervals::NotOverlapping{ref minuend, ref subtrahend} => {
+                let subtrahend = try!(subtrahend.find_intervals(load_positions));
+                try!(minuend.find_intervals(load_positions)).into_iter()
+                    .filter(|interval| !subtrahend.iter().any(|other| interval.overlaps(other)))
+                    .collect()
+            }
+        };
+
+        Ok(sort_intervals(intervals))
+    }
+}
+
+/// Sorts intervals, removing any duplicates
+fn sort_intervals(mut intervals: Vec<Interval>) -> Vec<Interval> {
+    intervals.sort();
+    intervals.dedup_by(|a, b| a.start == b.start && a.end == b.end);
+    intervals
+}
+
+/// Removes intervals that contain another interval
+fn minimize(intervals: Vec<Interval>) -> Vec<Interval> {
+    let intervals = sort_intervals(intervals);
+
+    intervals.iter()
+        .filter(|interval| !intervals.iter().any(|other| other != *interval && interval.contains(other)))
+        .cloned()
+        .collect()
+}
+
+fn ordered(sources: &[Vec<Interval>]) -> Vec<Interval> {
+    if sources.is_empty() {
+        return Vec::new();
+    }
+
+    let mut intervals = Vec::new();
+
+    'first: for first in sources[0].iter() {
+        // Find the earliest ending interval of each source that starts after the previous one
+        let mut chosen = vec![*first];
+        for source in sources[1..].iter() {
+            let previous_end = chosen.last().unwrap().end;
+            let next = source.iter()
+                .filter(|interval| interval.start > previous_end)
+                .min_by_key(|interval| (interval.end, interval.start));
+
+            match next {
+                Some(next) => chosen.push(*next),
+                None => continue 'first,
+            }
+        }
+
+        // Then move each interval (apart from the last) as close as possible to the one after it
+        for i in (0..chosen.len() - 1).rev() {
+            let next_start = chosen[i + 1].start;
+            let closest = sources[i].iter()
+                .filter(|interval| interval.end < next_start)
+                .max_by_key(|interval| (interval.start, -(interval.end as i64)));
+
+            if let Some(closest) = closest {
+                chosen[i] = *closest;
+            }
+        }
+
+        let gaps = chosen.windows(2).map(|pair| pair[1].start - pair[0].end - 1).sum();
+        intervals.push(Interval::new(chosen[0].start, chosen.last().unwrap().end, gaps));
+    }
+
+    minimize(intervals)
+}
+
+fn unordered(sources: &[Vec<Interval>]) -> Vec<Interval> {
+    let mut intervals = Vec::new();
+
+    for (first_source, first_intervals) in sources.iter().enumerate() {
+        'first: for first in first_intervals.iter() {
+            // Find the earliest ending interval of each other source that doesn't start before this one
+            let mut chosen = vec![*first];
+            for (source, source_intervals) in sources.iter().enumerate() {
+                if source == first_source {
+                    continue;
+                }
+
+                let next = source_intervals.iter()
+                    .filter(|interval| interval.start >= first.start)
+                    .min_by_key(|interval| (interval.end, interval.start));
+
+                match next {
+                    Some(next) => chosen.push(*next),
+                    None => continue 'first,
+                }
+            }
+
+            // Count the positions that are covered by the chosen intervals
+            chosen.sort();
+            let mut covered = 0;
+            let mut covered_to = 0;
+            for interval in chosen.iter() {
+                let from = if interval.start > covered_to { interval.start } else { covered_to + 1 };
+                if interval.end >= from {
+                    covered += interval.end - from + 1;
+                    covered_to = interval.end;
+                }
+            }
+
+            let end = chosen.iter().map(|interval| interval.end).max().unwrap();
+            intervals.push(Interval::new(first.start, end, end - first.start + 1 - covered));
+        }
+    }
+
+    minimize(intervals)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use roaring::RoaringBitmap;
+
+    use term::Term;
+    use super::{Intervals, Interval};
+
+    fn find(intervals: &Intervals, text: &str) -> Vec<(u32, u32)> {
+        let mut positions: HashMap<Term, RoaringBitmap> = HashMap::new();
+        for (i, word) in text.split(' ').enumerate() {
+            positions.entry(Term::from_string(word)).or_insert_with(RoaringBitmap::new).insert(i as u32 + 1);
+        }
+
+        intervals.find_intervals(&mut |term| Ok(positions.get(term).cloned())).unwrap()
+            .into_iter()
+            .map(|interval| (interval.start, interval.end))
+            .collect()
+    }
+
+    fn term(term: &str) -> Intervals {
+        Intervals::term(Term::from_string(term))
+    }
+
+    #[test]
+    fn test_term() {
+        assert_eq!(find(&term("a"), "a b a"), vec![(1, 1), (3, 3)]);
+        assert_eq!(find(&term("c"), "a b a"), vec![]);
+    }
+
+    #[test]
+    fn test_ordered() {
+        let query = Intervals::ordered(vec![term("a"), term("b")]);
+
+        assert_eq!(find(&query, "a b"), vec![(1, 2)]);
+        assert_eq!(find(&query, "b a"), vec![]);
+        assert_eq!(find(&query, "a x b"), vec![(1, 3)]);
+    }
+
+    #[test]
+    fn test_ordered_is_minimal() {
+        let query = Intervals::ordered(vec![term("a"), term("b")]);
+
+        // Only the tightest match around each "b" is returned
+        assert_eq!(find(&query, "a a x b a b"), vec![(2, 4), (5, 6)]);
+    }
+
+    #[test]
+    fn test_ordered_repeated_term() {
+        let query = Intervals::ordered(vec![term("a"), term("a")]);
+
+        assert_eq!(find(&query, "a"), vec![]);
+        assert_eq!(find(&query, "a x a a"), vec![(1, 3), (3, 4)]);
+    }
+
+    #[test]
+    fn test_unordered() {
+        let query = Intervals::unordered(vec![term("a"), term("b")]);
+
+        assert_eq!(find(&query, "b x a"), vec![(1, 3)]);
+        assert_eq!(find(&query, "a b a"), vec![(1, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn test_max_gaps() {
+        let query = Intervals::max_gaps(Intervals::ordered(vec![term("a"), term("b"), term("c")]), 1);
+
+        assert_eq!(find(&query, "a x b c"), vec![(1, 4)]);
+        assert_eq!(find(&query, "a x b x c"), vec![]);
+
+        let query = Intervals::max_gaps(Intervals::unordered(vec![term("a"), term("b")]), 0);
+
+        assert_eq!(find(&query, "b a"), vec![(1, 2)]);
+        assert_eq!(find(&query, "b x a"), vec![]);
+    }
+
+    #[test]
+    fn test_phrase() {
+        let query = Intervals::phrase(vec![Term::from_string("a"), Term::from_string("b")]);
+
+        assert_eq!(find(&query, "a b x a x b"), vec![(1, 2)]);
+    }
+
+    #[test]
+    fn test_containing() {
+        let query = Intervals::containing(Intervals::ordered(vec![term("a"), term("b")]), term("x"));
+
+        assert_eq!(find(&query, "a x b a y b"), vec![(1, 3)]);
+
+        let query = Intervals::not_containing(Intervals::ordered(vec![term("a"), term("b")]), term("x"));
+
+        assert_eq!(find(&query, "a x b a y b"), vec![(4, 6)]);
+    }
+
+    #[test]
+    fn test_contained_by() {
+        let query = Intervals::contained_by(term("x"), Intervals::ordered(vec![term("a"), term("b")]));
+
+        assert_eq!(find(&query, "x a x b x"), vec![(3, 3)]);
+
+        let query = Intervals::not_contained_by(term("x"), Intervals::ordered(vec![term("a"), term("b")]));
+
+        assert_eq!(find(&query, "x a x b x"), vec![(1, 1), (5, 5)]);
+    }
+
+    #[test]
+    fn test_overlapping() {
+        let query = Intervals::overlapping(Intervals::ordered(vec![term("a"), term("b")]), Intervals::ordered(vec![term("b"), term("c")]));
+
+        assert_eq!(find(&query, "a b c a b"), vec![(1, 2)]);
+
+        let query = Intervals::not_overlapping(Intervals::ordered(vec![term("a"), term("b")]), Intervals::ordered(vec![term("b"), term("c")]));
+
+        assert_eq!(find(&query, "a b c a b"), vec![(4, 5)]);
+    }
+
+    #[test]
+    fn test_gaps() {
+        let query = Intervals::ordered(vec![term("a"), term("b"), term("c")]);
+        let mut positions = |term: &Term| -> Result<Option<RoaringBitmap>, String> {
+            let position = match term.as_bytes() {
+                b"a" => 1,
+                b"b" => 3,
+                _ => 6,
+            };
+
+            Ok(Some(vec![position].into_iter().collect()))
+        };
+
+        assert_eq!(query.find_intervals(&mut positions).unwrap(), vec![Interval::new(1, 6, 3)]);
+    }
+
+    #[test]
+    fn test_terms() {
+        let query = Intervals::not_overlapping(Intervals::ordered(vec![term("a"), term("b"), term("a")]), term("c"));
+
+        assert_eq!(query.terms(), vec![&Term::from_string("a"), &Term::from_string("b")]);
+        assert_eq!(query.all_terms(), vec![&Term::from_string("a"), &Term::from_string("b"), &Term::from_string("c")]);
+    }
+}
@@ -1,12 +1,23 @@
+pub mod automaton;
 pub mod multi_term_selector;
 pub mod term_scorer;
+pub mod span;
+pub mod intervals;
+pub mod parser;
+pub mod dsl;
+pub mod date_math;
+
+use std::ops::Bound;
 
 use term::Term;
 use schema::FieldId;
+use analysis::Analyzer;
 use query::multi_term_selector::MultiTermSelector;
 use query::term_scorer::TermScorer;
+use query::span::SpanQuery;
+use query::intervals::Intervals;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Query {
     /// Matches all documents, assigning the specified score to each one
     All {
@@ -42,6 +53,82 @@ pub enum Query {
         scorer: TermScorer,
     },
 
+    /// Matches documents that contain a term within a range in the specified field
+    ///
+    /// Terms are compared by their bytes. This puts strings in lexicographic order, and
+    /// integers and datetimes in numeric order (see Term::from_integer). All matching
+    /// documents are given the same score.
+    Range {
+        /// The field being searched
+        field: FieldId,
+
+        /// The lowest term in the range
+        lower: Bound<Term>,
+
+        /// The highest term in the range
+        upper: Bound<Term>,
+
+        /// The score to assign to each document
+        score: f32,
+    },
+
+    /// Matches documents that contain the specified terms in order in the specified field
+    ///
+    /// Only fields that are indexed with positions (FIELD_POSITIONS) can be searched
+    /// with a phrase query. Documents are scored by the number of times the phrase
+    /// occurs in them.
+    Phrase {
+        /// The field being searched
+        field: FieldId,
+
+        /// The terms to search for, in the order they must appear in
+        ///
+        /// Each term is paired with its position relative to the start of the phrase. This
+        /// is usually its index, but gaps can be left for words that aren't indexed (eg,
+        /// stopwords) and the terms then only match with the same gaps between them.
+        /// Terms can also share a position (eg, synonyms) and then match at the same
+        /// position in the document.
+        terms: Vec<(u32, Term)>,
+
+        /// The number of extra positions allowed between the terms
+        /// A slop of 0 only matches the terms when they are next to each other
+        slop: u32,
+
+        /// The method of scoring each match
+        scorer: TermScorer,
+    },
+
+    /// Matches documents that contain spans matched by a span query in the specified field
+    ///
+    /// Only fields that are indexed with positions (FIELD_POSITIONS) can be searched
+    /// with a span query. Documents are scored by the number of spans that match.
+    Span {
+        /// The field being searched
+        field: FieldId,
+
+        /// The span query to search with
+        span: SpanQuery,
+
+        /// The method of scoring each match
+        scorer: TermScorer,
+    },
+
+    /// Matches documents that contain intervals from an intervals source in the specified field
+    ///
+    /// Only fields that are indexed with positions (FIELD_POSITIONS) can be searched
+    /// with an intervals query. Each matching interval contributes to the score of the
+    /// document by how few gaps it has, so tightly grouped matches score highest.
+    Intervals {
+        /// The field being searched
+        field: FieldId,
+
+        /// The intervals source to search with
+        intervals: Intervals,
+
+        /// The method of scoring each match
+        scorer: TermScorer,
+    },
+
     /// Joins two queries with an AND operator
     /// This intersects the results of the queries. The scores are combined by average
     Conjunction {
@@ -92,6 +179,68 @@ impl Query {
         }
     }
 
+    /// Creates a new Range query
+    pub fn range(field: FieldId, lower: Bound<Term>, upper: Bound<Term>) -> Query {
+        Query::Range {
+            field: field,
+            lower: lower,
+            upper: upper,
+            score: 1.0f32,
+        }
+    }
+
+    /// Creates a new Phrase query that only matches the terms when they are next to each other
+    pub fn phrase(field: FieldId, terms: Vec<Term>) -> Query {
+        Query::Phrase {
+            field: field,
+            terms: terms.into_iter().enumerate().map(|(offset, term)| (offset as u32, term)).collect(),
+            slop: 0,
+            scorer: TermScorer::default(),
+        }
+    }
+
+    /// Creates a new Span query
+    pub fn span(field: FieldId, span: SpanQuery) -> Query {
+        Query::Span {
+            field: field,
+            span: span,
+            scorer: TermScorer::default(),
+        }
+    }
+
+    /// Creates a new Intervals query
+    pub fn intervals(field: FieldId, intervals: Intervals) -> Query {
+        Query::Intervals {
+            field: field,
+            intervals: intervals,
+            scorer: TermScorer::default(),
+        }
+    }
+
+    /// Creates a query that matches documents containing any of the terms in the text
+    ///
+    /// The text is converted into terms by the analyzer. To match the terms in the
+    /// index, this should be the analyzer of the field (see FieldInfo::build_analyzer).
+    pub fn match_text<A: Analyzer>(field: FieldId, analyzer: &A, text: &str) -> Query {
+        let mut queries = Vec::new();
+
+        for token in analyzer.analyze(text) {
+            let query = Query::term(field, token.term);
+
+            if !queries.contains(&query) {
+                queries.push(query);
+            }
+        }
+
+        match queries.len() {
+            0 => Query::None,
+            1 => queries.pop().unwrap(),
+            _ => Query::Disjunction {
+                queries: queries,
+            }
+        }
+    }
+
     /// Filters the query by another query
     /// Only documents that match the other query will remain in the results but the other query will not affect the score
     pub fn filter(self, filter: Query) -> Query {
@@ -133,6 +282,18 @@ impl Query {
             Query::MultiTerm{ref mut scorer, ..} => {
                 scorer.boost *= add_boost;
             }
+            Query::Range{ref mut score, ..} => {
+                *score *= add_boost;
+            }
+            Query::Phrase{ref mut scorer, ..} => {
+                scorer.boost *= add_boost;
+            }
+            Query::Span{ref mut scorer, ..} => {
+                scorer.boost *= add_boost;
+            }
+            Query::Intervals{ref mut scorer, ..} => {
+                scorer.boost *= add_boost;
+            }
             Query::Conjunction{ref mut queries} => {
                 for query in queries {
                     query.add_boost(add_boost);
@@ -157,3 +318,64 @@ impl Query {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use schema::FieldId;
+    use analysis::{AnalyzerConfig, TokenFilterConfig, Language};
+    use analysis::filters::synonym::parse_synonyms;
+    use super::Query;
+
+    #[test]
+    fn test_match_text() {
+        let analyzer = AnalyzerConfig::standard().build();
+        let query = Query::match_text(FieldId(1), &analyzer, "Hello world, hello!");
+
+        assert_eq!(query, Query::Disjunction {
+            queries: vec![
+                Query::term(FieldId(1), Term::from_string("hello")),
+                Query::term(FieldId(1), Term::from_string("world")),
+            ]
+        });
+    }
+
+    #[test]
+    fn test_match_text_single_term() {
+        let analyzer = AnalyzerConfig::standard().build();
+        let query = Query::match_text(FieldId(1), &analyzer, "Hello");
+
+        assert_eq!(query, Query::term(FieldId(1), Term::from_string("hello")));
+    }
+
+    #[test]
+    fn test_match_text_blank() {
+        let analyzer = AnalyzerConfig::standard().build();
+        let query = Query::match_text(FieldId(1), &analyzer, "");
+
+        assert_eq!(query, Query::None);
+    }
+
+    #[test]
+    fn test_match_text_stemmed() {
+        // Query terms must be stemmed the same way as the indexed terms
+        let analyzer = AnalyzerConfig::standard().filter(TokenFilterConfig::Stemmer { language: Language::English }).build();
+
+        assert_eq!(Query::match_text(FieldId(1), &analyzer, "Running"), Query::term(FieldId(1), Term::from_string("run")));
+        assert_eq!(Query::match_text(FieldId(1), &analyzer, "runs"), Query::term(FieldId(1), Term::from_string("run")));
+    }
+
+    #[test]
+    fn test_match_text_synonyms() {
+        let analyzer = AnalyzerConfig::standard()
+            .filter(TokenFilterConfig::Synonym { rules: parse_synonyms("tv, television").unwrap() })
+            .build();
+
+        assert_eq!(Query::match_text(FieldId(1), &analyzer, "TV"), Query::Disjunction {
+            queries: vec![
+                Query::term(FieldId(1), Term::from_string("tv")),
+                Query::term(FieldId(1), Term::from_string("television")),
+            ]
+        });
+    }
+}
@@ -1,16 +1,141 @@
 use term::Term;
+use query::automaton::{Automaton, PrefixAutomaton, FuzzyAutomaton, RegexAutomaton, MAX_FUZZY_EDITS};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
+pub enum MultiTermSelectorError {
+    /// A regular expression or wildcard pattern failed to compile, or its automaton
+    /// would be too big (see MAX_REGEX_DFA_SIZE) (pattern, error message)
+    InvalidPattern(String, String),
+
+    /// A fuzzy selector allows more edits than MAX_FUZZY_EDITS
+    TooManyEdits(u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum MultiTermSelector {
     Prefix(String),
+
+    /// Selects terms that are within "max_edits" edits (insertions, deletions and
+    /// substitutions of a character) of "term"
+    ///
+    /// The first "prefix_length" characters must match exactly, which reduces the
+    /// number of terms that need to be checked. If "transpositions" is set, swapping
+    /// two adjacent characters counts as a single edit. "max_edits" must not be greater
+    /// than MAX_FUZZY_EDITS (2).
+    Fuzzy {
+        term: String,
+        max_edits: u8,
+
+        #[serde(default)]
+        prefix_length: usize,
+
+        #[serde(default)]
+        transpositions: bool,
+    },
+
+    /// Selects terms that match a regular expression
+    /// The expression must match the whole term (it's implicitly anchored at both ends)
+    Regex(String),
+
+    /// Selects terms that match a wildcard pattern
+    /// "?" matches any single character and "*" matches any number of characters.
+    /// They can be escaped with a "\"
+    Wildcard(String),
 }
 
 impl MultiTermSelector {
+    /// Creates a new fuzzy selector that allows transpositions and has no exact prefix
+    pub fn fuzzy(term: &str, max_edits: u8) -> MultiTermSelector {
+        MultiTermSelector::Fuzzy {
+            term: term.to_string(),
+            max_edits: max_edits,
+            prefix_length: 0,
+            transpositions: true,
+        }
+    }
+
+    /// Builds an automaton that matches the selected terms
+    pub fn automaton(&self) -> Result<Box<dyn Automaton>, MultiTermSelectorError> {
+        Ok(match *self {
+            MultiTermSelector::Prefix(ref prefix) => {
+                Box::new(PrefixAutomaton::new(prefix))
+            }
+            MultiTermSelector::Fuzzy{ref term, max_edits, prefix_length, transpositions} => {
+                if max_edits > MAX_FUZZY_EDITS {
+                    return Err(MultiTermSelectorError::TooManyEdits(max_edits));
+                }
+
+                Box::new(FuzzyAutomaton::new(term, max_edits, prefix_length, transpositions))
+            }
+            MultiTermSelector::Regex(ref pattern) => {
+                match RegexAutomaton::new(pattern) {
+                    Ok(automaton) => Box::new(automaton),
+                    Err(e) => return Err(MultiTermSelectorError::InvalidPattern(pattern.clone(), e)),
+                }
+            }
+            MultiTermSelector::Wildcard(ref pattern) => {
+                match RegexAutomaton::wildcard(pattern) {
+                    Ok(automaton) => Box::new(automaton),
+                    Err(e) => return Err(MultiTermSelectorError::InvalidPattern(pattern.clone(), e)),
+                }
+            }
+        })
+    }
+
+    /// Checks that the selector can be compiled into an automaton
+    pub fn validate(&self) -> Result<(), MultiTermSelectorError> {
+        self.automaton().map(|_| ())
+    }
+
+    /// Returns true if the term is selected
+    /// Invalid selectors don't select any terms
     pub fn matches(&self, term: &Term) -> bool {
         match *self {
             MultiTermSelector::Prefix(ref prefix) => {
                 return term.as_bytes().starts_with(prefix.as_bytes());
             }
+            _ => self.automaton().map(|automaton| automaton.matches(term.as_bytes())).unwrap_or(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use super::{MultiTermSelector, MultiTermSelectorError};
+
+    #[test]
+    fn test_matches() {
+        assert!(MultiTermSelector::Prefix("qu".to_string()).matches(&Term::from_string("quick")));
+        assert!(MultiTermSelector::fuzzy("quikc", 1).matches(&Term::from_string("quick")));
+        assert!(MultiTermSelector::Regex("q.*k".to_string()).matches(&Term::from_string("quick")));
+        assert!(MultiTermSelector::Wildcard("q*k".to_string()).matches(&Term::from_string("quick")));
+        assert!(!MultiTermSelector::Wildcard("q*k".to_string()).matches(&Term::from_string("quicken")));
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let selector = MultiTermSelector::Regex("qu(ick".to_string());
+
+        match selector.validate() {
+            Err(MultiTermSelectorError::InvalidPattern(ref pattern, _)) if pattern == "qu(ick" => {}
+            result => panic!("unexpected result: {:?}", result),
         }
+
+        assert!(!selector.matches(&Term::from_string("qu(ick")));
+    }
+
+    #[test]
+    fn test_too_many_edits() {
+        assert!(MultiTermSelector::fuzzy("quick", 2).validate().is_ok());
+
+        let selector = MultiTermSelector::fuzzy("quick", 3);
+
+        match selector.validate() {
+            Err(MultiTermSelectorError::TooManyEdits(3)) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        assert!(!selector.matches(&Term::from_string("quick")));
     }
 }
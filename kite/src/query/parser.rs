@@ -0,0 +1,1129 @@
+//! Query string parser
+//!
+//! Converts Lucene-style query strings into Query trees. For example:
+//!
+//! ```text
+//! title:foo AND (bar OR baz) -qux "exact phrase"~2 pre* fuzzy~1 req-*-eu /err(or)?_[0-9]+/ pk:42 ^2.0
+//! price:[10 TO 50} created:>=2016-07-23T00:00:00Z
+//! ```
+//!
+//! Words and phrases are converted into terms with the analyzer of the field they
+//! search. A word ending with a "*" searches for terms starting with the rest of the
+//! word and a word followed by a "~" searches for terms within 2 edits of it (or the
+//! number of edits after the "~"). Any other word containing a "?" or "*" is a
+//! wildcard pattern and a value between "/" characters is a regular expression. These
+//! aren't analyzed. Special characters can be escaped with a "\".
+//!
+//! Ranges can be given in brackets, where a square bracket includes the bound and a
+//! curly bracket excludes it, and a "*" leaves that end of the range open. A value
+//! starting with ">", ">=", "<" or "<=" is also a range. Ranges can only search
+//! integer, datetime and plain string fields.
+//!
+//! Values of datetime fields are date math expressions (see the date_math module),
+//! such as "created:[now-7d/d TO now]". Rounding in the bounds of a range rounds
+//! up or down so the range covers the whole unit, so "created:[now/d TO now/d]"
+//! matches all of today.
+
+use std::ops::Bound;
+
+use chrono::{DateTime, Utc};
+
+use term::Term;
+use schema::{Schema, FieldId, FieldType, FieldInfo};
+use analysis::Analyzer;
+use query::Query;
+use query::multi_term_selector::MultiTermSelector;
+use query::term_scorer::TermScorer;
+use query::automaton::MAX_FUZZY_EDITS;
+use query::date_math::{DateMath, DateMathTimeZone};
+
+/// The operator used to join clauses that don't have one between them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Or,
+    And,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryParseError {
+    /// A character was found where it isn't allowed (byte position, character)
+    UnexpectedCharacter(usize, char),
+
+    /// The query string ended before the query was complete (byte position)
+    UnexpectedEnd(usize),
+
+    /// A field name was used that isn't in the schema (byte position, field name)
+    UnknownField(usize, String),
+
+    /// The default field isn't in the schema
+    FieldDoesntExist(FieldId),
+
+    /// A value couldn't be converted into a term of the field's type (byte position, value, field type)
+    InvalidValue(usize, String, FieldType),
+
+    /// A boost wasn't a valid number (byte position, boost)
+    InvalidBoost(usize, String),
+
+    /// The slop of a phrase or the edits of a fuzzy word weren't a valid number (byte position, slop)
+    InvalidSlop(usize, String),
+
+    /// A regular expression or wildcard pattern failed to compile (byte position, pattern)
+    InvalidPattern(usize, String),
+
+    /// A fuzzy word allows more edits than MAX_FUZZY_EDITS (byte position, edits)
+    TooManyEdits(usize, u8),
+
+    /// Range queries aren't supported on fields of this type (byte position, field type)
+    UnsupportedRange(usize, FieldType),
+}
+
+impl QueryParseError {
+    /// Returns the byte position in the query string where the error occurred
+    pub fn position(&self) -> Option<usize> {
+        match *self {
+            QueryParseError::UnexpectedCharacter(position, _) |
+            QueryParseError::UnexpectedEnd(position) |
+            QueryParseError::UnknownField(position, _) |
+            QueryParseError::InvalidValue(position, _, _) |
+            QueryParseError::InvalidBoost(position, _) |
+            QueryParseError::InvalidSlop(position, _) |
+            QueryParseError::InvalidPattern(position, _) |
+            QueryParseError::TooManyEdits(position, _) |
+            QueryParseError::UnsupportedRange(position, _) => Some(position),
+            QueryParseError::FieldDoesntExist(_) => None,
+        }
+    }
+}
+
+/// How a clause affects the matches of the group it's in
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Occur {
+    Should,
+    Must,
+    MustNot,
+}
+
+/// What a word in a query string searches for
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WordKind {
+    /// The word itself
+    Plain,
+
+    /// Terms starting with the word (it ended with a "*")
+    Prefix,
+
+    /// Terms matching the word as a wildcard pattern
+    Wildcard,
+}
+
+/// Parses query strings into queries
+pub struct QueryParser<'a> {
+    schema: &'a Schema,
+    default_field: FieldId,
+    default_operator: Operator,
+    now: Option<DateTime<Utc>>,
+    timezone: DateMathTimeZone,
+}
+
+impl<'a> QueryParser<'a> {
+    /// Creates a new query parser
+    /// Words that aren't prefixed with a field name search "default_field"
+    pub fn new(schema: &'a Schema, default_field: FieldId) -> QueryParser<'a> {
+        QueryParser {
+            schema: schema,
+            default_field: default_field,
+            default_operator: Operator::Or,
+            now: None,
+            timezone: DateMathTimeZone::utc(),
+        }
+    }
+
+    /// Sets the operator used to join clauses that don't have one between them (defaults to Or)
+    pub fn default_operator(mut self, default_operator: Operator) -> QueryParser<'a> {
+        self.default_operator = default_operator;
+        self
+    }
+
+    /// Sets the time that "now" refers to in date math (defaults to the time the query is parsed)
+    pub fn now(mut self, now: DateTime<Utc>) -> QueryParser<'a> {
+        self.now = Some(now);
+        self
+    }
+
+    /// Sets the time zone of dates without an offset, which date math also rounds in (defaults to UTC)
+    /// This can be a fixed offset (FixedOffset) or a named time zone (date_math::Tz)
+    pub fn timezone<T: Into<DateMathTimeZone>>(mut self, timezone: T) -> QueryParser<'a> {
+        self.timezone = timezone.into();
+        self
+    }
+
+    pub fn parse(&self, query_string: &str) -> Result<Query, QueryParseError> {
+        let mut parser = Parser {
+            query_parser: self,
+            input: query_string,
+            chars: query_string.char_indices().collect(),
+            current: 0,
+            now: self.now.unwrap_or_else(Utc::now),
+        };
+
+        parser.parse_query()
+    }
+}
+
+struct Parser<'a, 'b> {
+    query_parser: &'b QueryParser<'a>,
+    input: &'b str,
+    chars: Vec<(usize, char)>,
+    current: usize,
+    now: DateTime<Utc>,
+}
+
+impl<'a, 'b> Parser<'a, 'b> {
+    fn parse_query(&mut self) -> Result<Query, QueryParseError> {
+        let default_field = self.query_parser.default_field;
+        let query = try!(self.parse_clauses(default_field, false));
+
+        match self.peek() {
+            Some(c) => Err(QueryParseError::UnexpectedCharacter(self.position(), c)),
+            None => Ok(query),
+        }
+    }
+
+    /// The byte position of the next character
+    fn position(&self) -> usize {
+        match self.chars.get(self.current) {
+            Some(&(position, _)) => position,
+            None => self.input.len(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.current).map(|&(_, c)| c)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        if c.is_some() {
+            self.current += 1;
+        }
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map_or(false, char::is_whitespace) {
+            self.current += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), QueryParseError> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.current += 1;
+                Ok(())
+            }
+            Some(c) => Err(QueryParseError::UnexpectedCharacter(self.position(), c)),
+            None => Err(QueryParseError::UnexpectedEnd(self.position())),
+        }
+    }
+
+    /// Consumes the operator if it's next in the input
+    /// Word operators (eg, "AND") must be followed by whitespace or a bracket
+    fn eat_operator(&mut self, operator: &str) -> bool {
+        let rest = &self.input[self.position()..];
+        if !rest.starts_with(operator) {
+            return false;
+        }
+
+        let is_word = operator.chars().all(char::is_alphabetic);
+        if is_word {
+            match rest[operator.len()..].chars().next() {
+                Some(c) if !c.is_whitespace() && c != '(' => return false,
+                _ => {}
+            }
+        }
+
+        self.current += operator.chars().count();
+        true
+    }
+
+    fn parse_clauses(&mut self, field: FieldId, in_group: bool) -> Result<Query, QueryParseError> {
+        let default_occur = match self.query_parser.default_operator {
+            Operator::Or => Occur::Should,
+            Operator::And => Occur::Must,
+        };
+
+        let mut clauses: Vec<(Occur, Query)> = Vec::new();
+        let mut operator = None;
+
+        loop {
+            self.skip_whitespace();
+
+            match self.peek() {
+                None => {
+                    if in_group {
+                        return Err(QueryParseError::UnexpectedEnd(self.position()));
+                    }
+                    break;
+                }
+                Some(')') if in_group => break,
+                _ => {}
+            }
+
+            if self.eat_operator("AND") || self.eat_operator("&&") {
+                // Both sides of an AND are required
+                if let Some(&mut (ref mut occur, _)) = clauses.last_mut() {
+                    if *occur == Occur::Should {
+                        *occur = Occur::Must;
+                    }
+                }
+
+                operator = Some(Operator::And);
+                continue;
+            }
+
+            if self.eat_operator("OR") || self.eat_operator("||") {
+                // Neither side of an OR is required
+                if let Some(&mut (ref mut occur, _)) = clauses.last_mut() {
+                    if *occur == Occur::Must {
+                        *occur = Occur::Should;
+                    }
+                }
+
+                operator = Some(Operator::Or);
+                continue;
+            }
+
+            let mut occur = match operator {
+                Some(Operator::And) => Occur::Must,
+                Some(Operator::Or) => Occur::Should,
+                None => default_occur,
+            };
+
+            if self.eat_operator("NOT") {
+                occur = Occur::MustNot;
+                self.skip_whitespace();
+            } else if self.eat_operator("!") || self.eat_operator("-") {
+                occur = Occur::MustNot;
+            } else if self.eat_operator("+") {
+                occur = Occur::Must;
+            }
+
+            if let Some(query) = try!(self.parse_clause(field)) {
+                clauses.push((occur, query));
+            }
+
+            operator = None;
+        }
+
+        if operator.is_some() {
+            // The query ended with an operator
+            return Err(QueryParseError::UnexpectedEnd(self.position()));
+        }
+
+        Ok(build_query(clauses))
+    }
+
+    /// Parses a single clause, with an optional field name and boost
+    /// Returns None if the clause doesn't produce any terms (eg, it only contains stop words)
+    fn parse_clause(&mut self, default_field: FieldId) -> Result<Option<Query>, QueryParseError> {
+        let mut field = default_field;
+
+        // Field name
+        let start = self.current;
+        let start_position = self.position();
+        let (name, _) = try!(self.parse_word(false));
+        if !name.is_empty() && self.peek() == Some(':') {
+            self.current += 1;
+
+            field = match self.query_parser.schema.get_field_by_name(&name) {
+                Some(field) => field,
+                None => return Err(QueryParseError::UnknownField(start_position, name)),
+            };
+        } else {
+            self.current = start;
+        }
+
+        let query = try!(self.parse_value(field));
+
+        // Boost
+        let before_boost = self.current;
+        self.skip_whitespace();
+        if self.peek() == Some('^') {
+            self.current += 1;
+
+            let boost_position = self.position();
+            let boost = self.parse_number();
+            match boost.parse::<f32>() {
+                Ok(boost) => return Ok(query.map(|query| query.boost(boost))),
+                Err(_) => return Err(QueryParseError::InvalidBoost(boost_position, boost)),
+            }
+        } else {
+            self.current = before_boost;
+        }
+
+        Ok(query)
+    }
+
+    fn parse_value(&mut self, field: FieldId) -> Result<Option<Query>, QueryParseError> {
+        let position = self.position();
+
+        match self.peek() {
+            Some('(') => {
+                self.current += 1;
+                let query = try!(self.parse_clauses(field, true));
+                try!(self.expect(')'));
+                Ok(Some(query))
+            }
+            Some('"') => self.parse_phrase(field),
+            Some('/') => self.parse_regex(field).map(Some),
+            Some('[') | Some('{') => self.parse_range(field).map(Some),
+            Some('>') | Some('<') => self.parse_comparison(field).map(Some),
+            Some(c) => {
+                let (word, kind) = try!(self.parse_word(true));
+                if word.is_empty() && kind == WordKind::Plain {
+                    return Err(QueryParseError::UnexpectedCharacter(position, c));
+                }
+
+                match kind {
+                    WordKind::Plain => {}
+                    WordKind::Prefix => {
+                        let mut value = word.clone();
+                        value.push('*');
+                        return self.multi_term_query(field, MultiTermSelector::Prefix(word), value, position).map(Some);
+                    }
+                    WordKind::Wildcard => {
+                        return self.multi_term_query(field, MultiTermSelector::Wildcard(word.clone()), word, position).map(Some);
+                    }
+                }
+
+                // Fuzzy
+                if self.peek() == Some('~') {
+                    self.current += 1;
+
+                    let edits_position = self.position();
+                    let edits_string = self.parse_number();
+                    let max_edits = if edits_string.is_empty() {
+                        MAX_FUZZY_EDITS
+                    } else {
+                        match edits_string.parse::<u8>() {
+                            Ok(max_edits) => max_edits,
+                            Err(_) => return Err(QueryParseError::InvalidSlop(edits_position, edits_string)),
+                        }
+                    };
+
+                    if max_edits > MAX_FUZZY_EDITS {
+                        return Err(QueryParseError::TooManyEdits(edits_position, max_edits));
+                    }
+
+                    let mut value = word.clone();
+                    value.push('~');
+                    return self.multi_term_query(field, MultiTermSelector::fuzzy(&word, max_edits), value, position).map(Some);
+                }
+
+                self.word_query(field, word, position)
+            }
+            None => Err(QueryParseError::UnexpectedEnd(position)),
+        }
+    }
+
+    /// Reads a word, unescaping any escaped characters
+    ///
+    /// A word ending with an (unescaped) "*" is a prefix and the "*" isn't included.
+    /// Any other word containing an unescaped "?" or "*" is returned as a wildcard
+    /// pattern, where escaped "?", "*" and "\\" characters are kept escaped.
+    fn parse_word(&mut self, allow_colon: bool) -> Result<(String, WordKind), QueryParseError> {
+        let mut word = String::new();
+        let mut pattern = String::new();
+        let mut is_wildcard = false;
+
+        loop {
+            match self.peek() {
+                Some('\\') => {
+                    self.current += 1;
+                    match self.next() {
+                        Some(c) => {
+                            if c == '?' || c == '*' || c == '\\' {
+                                pattern.push('\\');
+                            }
+                            pattern.push(c);
+                            word.push(c);
+                        }
+                        None => return Err(QueryParseError::UnexpectedEnd(self.position())),
+                    }
+                }
+                Some('*') => {
+                    self.current += 1;
+                    if !is_wildcard && self.peek().map_or(true, |c| is_word_boundary(c, allow_colon)) {
+                        return Ok((word, WordKind::Prefix));
+                    }
+                    is_wildcard = true;
+                    pattern.push('*');
+                }
+                Some('?') => {
+                    self.current += 1;
+                    is_wildcard = true;
+                    pattern.push('?');
+                }
+                Some(c) if !is_word_boundary(c, allow_colon) => {
+                    self.current += 1;
+                    pattern.push(c);
+                    word.push(c);
+                }
+                _ => {
+                    if is_wildcard {
+                        return Ok((pattern, WordKind::Wildcard));
+                    } else {
+                        return Ok((word, WordKind::Plain));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads a regular expression between "/" characters
+    /// Escaped "/" characters are unescaped, other escapes are left for the regular expression
+    fn parse_regex(&mut self, field: FieldId) -> Result<Query, QueryParseError> {
+        let position = self.position();
+        try!(self.expect('/'));
+
+        let mut pattern = String::new();
+        loop {
+            match self.next() {
+                Some('\\') => {
+                    match self.next() {
+                        Some('/') => pattern.push('/'),
+                        Some(c) => {
+                            pattern.push('\\');
+                            pattern.push(c);
+                        }
+                        None => return Err(QueryParseError::UnexpectedEnd(self.position())),
+                    }
+                }
+                Some('/') => break,
+                Some(c) => pattern.push(c),
+                None => return Err(QueryParseError::UnexpectedEnd(self.position())),
+            }
+        }
+
+        let value = format!("/{}/", pattern);
+        self.multi_term_query(field, MultiTermSelector::Regex(pattern), value, position)
+    }
+
+    /// Reads a range in brackets ("[a TO b]")
+    /// Square brackets include the bound and curly brackets exclude it
+    fn parse_range(&mut self, field: FieldId) -> Result<Query, QueryParseError> {
+        let position = self.position();
+        let lower_inclusive = self.next() == Some('[');
+
+        self.skip_whitespace();
+        let lower = try!(self.parse_range_value(field));
+
+        self.skip_whitespace();
+        if !self.eat_operator("TO") {
+            return Err(match self.peek() {
+                Some(c) => QueryParseError::UnexpectedCharacter(self.position(), c),
+                None => QueryParseError::UnexpectedEnd(self.position()),
+            });
+        }
+
+        self.skip_whitespace();
+        let upper = try!(self.parse_range_value(field));
+
+        self.skip_whitespace();
+        let upper_position = self.position();
+        let upper_inclusive = match self.next() {
+            Some(']') => true,
+            Some('}') => false,
+            Some(c) => return Err(QueryParseError::UnexpectedCharacter(upper_position, c)),
+            None => return Err(QueryParseError::UnexpectedEnd(upper_position)),
+        };
+
+        self.range_query(field, position, (lower, lower_inclusive), (upper, upper_inclusive))
+    }
+
+    /// Reads a range with one open end (">a", ">=a", "<a" or "<=a")
+    fn parse_comparison(&mut self, field: FieldId) -> Result<Query, QueryParseError> {
+        let position = self.position();
+        let is_lower = self.next() == Some('>');
+
+        let inclusive = self.peek() == Some('=');
+        if inclusive {
+            self.current += 1;
+        }
+
+        let value = try!(self.parse_range_value(field));
+
+        if is_lower {
+            self.range_query(field, position, (value, inclusive), (None, false))
+        } else {
+            self.range_query(field, position, (None, false), (value, inclusive))
+        }
+    }
+
+    /// Reads one end of a range and its byte position
+    /// Returns None if the range is open at this end ("*")
+    fn parse_range_value(&mut self, field: FieldId) -> Result<Option<(String, usize)>, QueryParseError> {
+        let position = self.position();
+        let (value, kind) = try!(self.parse_word(true));
+
+        match kind {
+            WordKind::Plain if value.is_empty() => {
+                match self.peek() {
+                    Some(c) => Err(QueryParseError::UnexpectedCharacter(self.position(), c)),
+                    None => Err(QueryParseError::UnexpectedEnd(self.position())),
+                }
+            }
+            WordKind::Plain => Ok(Some((value, position))),
+            WordKind::Prefix if value.is_empty() => Ok(None),
+            _ => {
+                // Patterns can't be used as bounds
+                let field_info = try!(self.field_info(field));
+                let value = self.input[position..self.position()].to_string();
+                Err(QueryParseError::InvalidValue(position, value, field_info.field_type.clone()))
+            }
+        }
+    }
+
+    /// Reads the characters of a number (for boosts and slops)
+    fn parse_number(&mut self) -> String {
+        let mut number = String::new();
+
+        while let Some(c) = self.peek() {
+            if !(c.is_ascii_digit() || c == '.' || c == '-') {
+                break;
+            }
+
+            number.push(c);
+            self.current += 1;
+        }
+
+        number
+    }
+
+    fn parse_phrase(&mut self, field: FieldId) -> Result<Option<Query>, QueryParseError> {
+        let position = self.position();
+        try!(self.expect('"'));
+
+        let mut text = String::new();
+        loop {
+            match self.next() {
+                Some('\\') => {
+                    match self.next() {
+                        Some(c) => text.push(c),
+                        None => return Err(QueryParseError::UnexpectedEnd(self.position())),
+                    }
+                }
+                Some('"') => break,
+                Some(c) => text.push(c),
+                None => return Err(QueryParseError::UnexpectedEnd(self.position())),
+            }
+        }
+
+        // Slop
+        let mut slop = 0;
+        if self.peek() == Some('~') {
+            self.current += 1;
+
+            let slop_position = self.position();
+            let slop_string = self.parse_number();
+            slop = match slop_string.parse::<u32>() {
+                Ok(slop) => slop,
+                Err(_) => return Err(QueryParseError::InvalidSlop(slop_position, slop_string)),
+            };
+        }
+
+        let field_info = try!(self.field_info(field));
+        if field_info.field_type != FieldType::Text {
+            // Phrases of other fields are just a way to quote values
+            return self.word_query(field, text, position + 1);
+        }
+
+        // Take the first term at each position. Any others are variations of it (eg, synonyms)
+        // Each term keeps its position relative to the first, so any gaps left by removed
+        // stopwords must also be in the documents that match
+        let mut terms: Vec<(u32, Term)> = Vec::new();
+        let mut first_position = None;
+        for token in field_info.build_analyzer().analyze(&text) {
+            let first_position = *first_position.get_or_insert(token.position);
+            let offset = token.position - first_position;
+
+            if terms.last().map(|&(last_offset, _)| last_offset) != Some(offset) {
+                terms.push((offset, token.term));
+            }
+        }
+
+        Ok(match terms.len() {
+            0 => None,
+            1 => Some(Query::term(field, terms.pop().unwrap().1)),
+            _ => Some(Query::Phrase {
+                field: field,
+                terms: terms,
+                slop: slop,
+                scorer: TermScorer::default(),
+            }),
+        })
+    }
+
+    fn field_info(&self, field: FieldId) -> Result<&'a FieldInfo, QueryParseError> {
+        match self.query_parser.schema.get(&field) {
+            Some(field_info) => Ok(field_info),
+            None => Err(QueryParseError::FieldDoesntExist(field)),
+        }
+    }
+
+    fn word_query(&self, field: FieldId, word: String, position: usize) -> Result<Option<Query>, QueryParseError> {
+        let field_info = try!(self.field_info(field));
+
+        if field_info.field_type == FieldType::Text {
+            return Ok(match Query::match_text(field, &field_info.build_analyzer(), &word) {
+                Query::None => None,
+                query => Some(query),
+            });
+        }
+
+        let term = try!(self.value_to_term(field_info, word, position, false));
+        Ok(Some(Query::term(field, term)))
+    }
+
+    /// Converts a value into a term of the field's type, without analyzing it
+    /// "round_up" is set to round date math up to the end of the unit rather than down
+    fn value_to_term(&self, field_info: &FieldInfo, word: String, position: usize, round_up: bool) -> Result<Term, QueryParseError> {
+        Ok(match field_info.field_type {
+            FieldType::Text | FieldType::PlainString => Term::from_string(&word),
+            FieldType::I64 => {
+                match word.parse::<i64>() {
+                    Ok(value) => Term::from_integer(value),
+                    Err(_) => return Err(QueryParseError::InvalidValue(position, word, field_info.field_type.clone())),
+                }
+            }
+            FieldType::Boolean => {
+                match word.as_ref() {
+                    "true" => Term::from_boolean(true),
+                    "false" => Term::from_boolean(false),
+                    _ => return Err(QueryParseError::InvalidValue(position, word, field_info.field_type.clone())),
+                }
+            }
+            FieldType::DateTime => {
+                let value = DateMath::parse(&word).and_then(|date_math| {
+                    date_math.resolve(&self.now, &self.query_parser.timezone, round_up)
+                });
+
+                match value {
+                    Ok(value) => Term::from_datetime(&value),
+                    Err(_) => return Err(QueryParseError::InvalidValue(position, word, field_info.field_type.clone())),
+                }
+            }
+        })
+    }
+
+    /// Creates a range query from the values at each end of the range and whether they're included
+    fn range_query(&self, field: FieldId, position: usize, lower: (Option<(String, usize)>, bool), upper: (Option<(String, usize)>, bool)) -> Result<Query, QueryParseError> {
+        let field_info = try!(self.field_info(field));
+
+        match field_info.field_type {
+            FieldType::I64 | FieldType::DateTime | FieldType::PlainString => {}
+            _ => return Err(QueryParseError::UnsupportedRange(position, field_info.field_type.clone())),
+        }
+
+        // Included upper bounds and excluded lower bounds round up, so the range covers the whole unit
+        let to_bound = |(value, inclusive): (Option<(String, usize)>, bool), round_up: bool| -> Result<Bound<Term>, QueryParseError> {
+            match value {
+                Some((value, position)) => {
+                    let term = try!(self.value_to_term(field_info, value, position, round_up));
+
+                    if inclusive {
+                        Ok(Bound::Included(term))
+                    } else {
+                        Ok(Bound::Excluded(term))
+                    }
+                }
+                None => Ok(Bound::Unbounded),
+            }
+        };
+
+        let lower_round_up = !lower.1;
+        let upper_round_up = upper.1;
+        Ok(Query::range(field, try!(to_bound(lower, lower_round_up)), try!(to_bound(upper, upper_round_up))))
+    }
+
+    /// Creates a query for a multi term selector
+    /// "value" is the text of the selector in the query string, for error messages
+    fn multi_term_query(&self, field: FieldId, term_selector: MultiTermSelector, value: String, position: usize) -> Result<Query, QueryParseError> {
+        let field_info = try!(self.field_info(field));
+
+        match field_info.field_type {
+            FieldType::Text | FieldType::PlainString => {
+                if term_selector.validate().is_err() {
+                    return Err(QueryParseError::InvalidPattern(position, value));
+                }
+
+                Ok(Query::MultiTerm {
+                    field: field,
+                    term_selector: term_selector,
+                    scorer: TermScorer::default(),
+                })
+            }
+            _ => Err(QueryParseError::InvalidValue(position, value, field_info.field_type.clone())),
+        }
+    }
+}
+
+/// Returns true if the character can't be part of a word
+fn is_word_boundary(c: char, allow_colon: bool) -> bool {
+    if c == ':' {
+        return !allow_colon;
+    }
+
+    c.is_whitespace() || "()\"^~[]{}".contains(c)
+}
+
+/// Combines the clauses of a group into a single query
+fn build_query(clauses: Vec<(Occur, Query)>) -> Query {
+    let mut musts = Vec::new();
+    let mut shoulds = Vec::new();
+    let mut must_nots = Vec::new();
+
+    for (occur, query) in clauses {
+        match occur {
+            Occur::Must => musts.push(query),
+            Occur::Should => shoulds.push(query),
+            Occur::MustNot => must_nots.push(query),
+        }
+    }
+
+    let query = if musts.is_empty() {
+        if shoulds.is_empty() {
+            if must_nots.is_empty() {
+                return Query::None;
+            }
+
+            // Only negative clauses, exclude them from everything
+            Query::all()
+        } else {
+            join_queries(shoulds, false)
+        }
+    } else if shoulds.is_empty() {
+        join_queries(musts, true)
+    } else {
+        // The optional clauses only affect the score
+        let required = join_queries(musts.clone(), true);
+        musts.extend(shoulds);
+        join_queries(musts, false).filter(required)
+    };
+
+    if must_nots.is_empty() {
+        query
+    } else {
+        query.exclude(join_queries(must_nots, false))
+    }
+}
+
+fn join_queries(mut queries: Vec<Query>, conjunction: bool) -> Query {
+    if queries.len() == 1 {
+        return queries.pop().unwrap();
+    }
+
+    if conjunction {
+        Query::Conjunction {
+            queries: queries,
+        }
+    } else {
+        Query::Disjunction {
+            queries: queries,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+
+    use chrono::{DateTime, Utc, FixedOffset};
+    use chrono_tz::Tz;
+
+    use term::Term;
+    use schema::{Schema, FieldId, FieldType, FIELD_INDEXED, FIELD_POSITIONS};
+    use analysis::{AnalyzerConfig, TokenFilterConfig, Language};
+    use query::Query;
+    use query::multi_term_selector::MultiTermSelector;
+    use query::term_scorer::TermScorer;
+    use super::{QueryParser, QueryParseError, Operator};
+
+    fn make_schema() -> (Schema, FieldId, FieldId, FieldId) {
+        let mut schema = Schema::new();
+        let title = schema.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED | FIELD_POSITIONS, None).unwrap();
+        let body = schema.add_field("body".to_string(), FieldType::Text, FIELD_INDEXED, None).unwrap();
+        let pk = schema.add_field("pk".to_string(), FieldType::I64, FIELD_INDEXED, None).unwrap();
+
+        (schema, title, body, pk)
+    }
+
+    fn term(field: FieldId, term: &str) -> Query {
+        Query::term(field, Term::from_string(term))
+    }
+
+    #[test]
+    fn test_single_word() {
+        let (schema, _, body, _) = make_schema();
+        let parser = QueryParser::new(&schema, body);
+
+        assert_eq!(parser.parse("Hello"), Ok(term(body, "hello")));
+    }
+
+    #[test]
+    fn test_default_operator() {
+        let (schema, _, body, _) = make_schema();
+
+        assert_eq!(QueryParser::new(&schema, body).parse("hello world"), Ok(Query::Disjunction {
+            queries: vec![term(body, "hello"), term(body, "world")],
+        }));
+
+        assert_eq!(QueryParser::new(&schema, body).default_operator(Operator::And).parse("hello world"), Ok(Query::Conjunction {
+            queries: vec![term(body, "hello"), term(body, "world")],
+        }));
+    }
+
+    #[test]
+    fn test_field() {
+        let (schema, title, body, pk) = make_schema();
+        let parser = QueryParser::new(&schema, body);
+
+        assert_eq!(parser.parse("title:hello"), Ok(term(title, "hello")));
+        assert_eq!(parser.parse("pk:-42"), Ok(Query::term(pk, Term::from_integer(-42))));
+        assert_eq!(parser.parse("title:(hello world)"), Ok(Query::Disjunction {
+            queries: vec![term(title, "hello"), term(title, "world")],
+        }));
+    }
+
+    #[test]
+    fn test_boolean_operators() {
+        let (schema, title, body, _) = make_schema();
+        let parser = QueryParser::new(&schema, body);
+
+        assert_eq!(parser.parse("title:foo AND (bar OR baz) -qux"), Ok(Query::Conjunction {
+            queries: vec![
+                term(title, "foo"),
+                Query::Disjunction {
+                    queries: vec![term(body, "bar"), term(body, "baz")],
+                },
+            ],
+        }.exclude(term(body, "qux"))));
+    }
+
+    #[test]
+    fn test_required_and_optional_clauses() {
+        let (schema, _, body, _) = make_schema();
+        let parser = QueryParser::new(&schema, body);
+
+        assert_eq!(parser.parse("+foo bar"), Ok(Query::Disjunction {
+            queries: vec![term(body, "foo"), term(body, "bar")],
+        }.filter(term(body, "foo"))));
+    }
+
+    #[test]
+    fn test_only_negative_clauses() {
+        let (schema, _, body, _) = make_schema();
+        let parser = QueryParser::new(&schema, body);
+
+        assert_eq!(parser.parse("NOT foo"), Ok(Query::all().exclude(term(body, "foo"))));
+    }
+
+    #[test]
+    fn test_phrase() {
+        let (schema, title, body, _) = make_schema();
+        let parser = QueryParser::new(&schema, body);
+
+        assert_eq!(parser.parse("title:\"Exact Phrase\""), Ok(Query::phrase(title, vec![Term::from_string("exact"), Term::from_string("phrase")])));
+        assert_eq!(parser.parse("title:\"exact phrase\"~2"), Ok(Query::Phrase {
+            field: title,
+            terms: vec![(0, Term::from_string("exact")), (1, Term::from_string("phrase"))],
+            slop: 2,
+            scorer: TermScorer::default(),
+        }));
+        assert_eq!(parser.parse("\"hello\""), Ok(term(body, "hello")));
+    }
+
+    #[test]
+    fn test_phrase_stopword_gaps() {
+        let mut schema = Schema::new();
+        let analyzer = AnalyzerConfig::standard().filter(TokenFilterConfig::Stop { language: Some(Language::English), words: Vec::new() });
+        let title = schema.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED | FIELD_POSITIONS, Some(analyzer)).unwrap();
+        let parser = QueryParser::new(&schema, title);
+
+        assert_eq!(parser.parse("\"united of america\""), Ok(Query::Phrase {
+            field: title,
+            terms: vec![(0, Term::from_string("united")), (2, Term::from_string("america"))],
+            slop: 0,
+            scorer: TermScorer::default(),
+        }));
+
+        // Stopwords at the start of the phrase don't leave a gap
+        assert_eq!(parser.parse("\"the united states\""), Ok(Query::phrase(title, vec![Term::from_string("united"), Term::from_string("states")])));
+    }
+
+    #[test]
+    fn test_prefix() {
+        let (schema, _, body, _) = make_schema();
+        let parser = QueryParser::new(&schema, body);
+
+        assert_eq!(parser.parse("pre*"), Ok(Query::MultiTerm {
+            field: body,
+            term_selector: MultiTermSelector::Prefix("pre".to_string()),
+            scorer: TermScorer::default(),
+        }));
+    }
+
+    #[test]
+    fn test_fuzzy() {
+        let (schema, _, body, _) = make_schema();
+        let parser = QueryParser::new(&schema, body);
+        let fuzzy = |term: &str, max_edits: u8| Query::MultiTerm {
+            field: body,
+            term_selector: MultiTermSelector::fuzzy(term, max_edits),
+            scorer: TermScorer::default(),
+        };
+
+        assert_eq!(parser.parse("quikc~"), Ok(fuzzy("quikc", 2)));
+        assert_eq!(parser.parse("quikc~1"), Ok(fuzzy("quikc", 1)));
+        assert_eq!(parser.parse("quikc~1^2"), Ok(fuzzy("quikc", 1).boost(2.0)));
+        assert_eq!(parser.parse("quikc~0.5"), Err(QueryParseError::InvalidSlop(6, "0.5".to_string())));
+        assert_eq!(parser.parse("quikc~3"), Err(QueryParseError::TooManyEdits(6, 3)));
+        assert_eq!(parser.parse("pk:1~"), Err(QueryParseError::InvalidValue(3, "1~".to_string(), FieldType::I64)));
+    }
+
+    #[test]
+    fn test_wildcard() {
+        let (schema, _, body, _) = make_schema();
+        let parser = QueryParser::new(&schema, body);
+        let wildcard = |pattern: &str| Query::MultiTerm {
+            field: body,
+            term_selector: MultiTermSelector::Wildcard(pattern.to_string()),
+            scorer: TermScorer::default(),
+        };
+
+        assert_eq!(parser.parse("req-*-eu"), Ok(wildcard("req-*-eu")));
+        assert_eq!(parser.parse("qu?ck"), Ok(wildcard("qu?ck")));
+        assert_eq!(parser.parse("qu?ck*"), Ok(wildcard("qu?ck*")));
+        assert_eq!(parser.parse("wh?t\\*\\?"), Ok(wildcard("wh?t\\*\\?")));
+        assert_eq!(parser.parse("what\\?"), Ok(term(body, "what")));
+        assert_eq!(parser.parse("pk:1?"), Err(QueryParseError::InvalidValue(3, "1?".to_string(), FieldType::I64)));
+    }
+
+    #[test]
+    fn test_regex() {
+        let (schema, title, body, _) = make_schema();
+        let parser = QueryParser::new(&schema, body);
+        let regex = |field: FieldId, pattern: &str| Query::MultiTerm {
+            field: field,
+            term_selector: MultiTermSelector::Regex(pattern.to_string()),
+            scorer: TermScorer::default(),
+        };
+
+        assert_eq!(parser.parse("/err(or)?_[0-9]+/"), Ok(regex(body, "err(or)?_[0-9]+")));
+        assert_eq!(parser.parse("title:/a\\/b\\d/^2"), Ok(regex(title, "a/b\\d").boost(2.0)));
+        assert_eq!(parser.parse("/err(or_[0-9]+/"), Err(QueryParseError::InvalidPattern(0, "/err(or_[0-9]+/".to_string())));
+        assert_eq!(parser.parse("/err"), Err(QueryParseError::UnexpectedEnd(4)));
+        assert_eq!(parser.parse("/.*a.{18}/"), Err(QueryParseError::InvalidPattern(0, "/.*a.{18}/".to_string())));
+    }
+
+    #[test]
+    fn test_boost() {
+        let (schema, title, body, _) = make_schema();
+        let parser = QueryParser::new(&schema, body);
+
+        assert_eq!(parser.parse("title:foo^2.0"), Ok(term(title, "foo").boost(2.0)));
+        assert_eq!(parser.parse("(foo bar) ^2.0"), Ok(Query::Disjunction {
+            queries: vec![term(body, "foo"), term(body, "bar")],
+        }.boost(2.0)));
+    }
+
+    #[test]
+    fn test_escaping() {
+        let (schema, _, body, pk) = make_schema();
+        let parser = QueryParser::new(&schema, body);
+
+        // Without the escape, "hello" would be treated as a field name
+        assert_eq!(parser.parse("hello\\:world"), Ok(term(body, "hello:world")));
+        assert_eq!(parser.parse("pk:\\-1"), Ok(Query::term(pk, Term::from_integer(-1))));
+    }
+
+    #[test]
+    fn test_errors() {
+        let (schema, _, body, _) = make_schema();
+        let parser = QueryParser::new(&schema, body);
+
+        assert_eq!(parser.parse("foo AND"), Err(QueryParseError::UnexpectedEnd(7)));
+        assert_eq!(parser.parse("(foo bar"), Err(QueryParseError::UnexpectedEnd(8)));
+        assert_eq!(parser.parse("foo bar)"), Err(QueryParseError::UnexpectedCharacter(7, ')')));
+        assert_eq!(parser.parse("foo \"bar"), Err(QueryParseError::UnexpectedEnd(8)));
+        assert_eq!(parser.parse("foo missing:bar"), Err(QueryParseError::UnknownField(4, "missing".to_string())));
+        assert_eq!(parser.parse("pk:abc"), Err(QueryParseError::InvalidValue(3, "abc".to_string(), FieldType::I64)));
+        assert_eq!(parser.parse("foo^x"), Err(QueryParseError::InvalidBoost(4, "".to_string())));
+        assert_eq!(parser.parse("title:[a TO b]"), Err(QueryParseError::UnsupportedRange(6, FieldType::Text)));
+        assert_eq!(parser.parse("pk:"), Err(QueryParseError::UnexpectedEnd(3)));
+        assert_eq!(QueryParser::new(&schema, FieldId(100)).parse("foo"), Err(QueryParseError::FieldDoesntExist(FieldId(100))));
+        assert_eq!(parser.parse("title:[a TO b]").unwrap_err().position(), Some(6));
+    }
+
+    #[test]
+    fn test_range() {
+        let (mut schema, _, body, pk) = make_schema();
+        let slug = schema.add_field("slug".to_string(), FieldType::PlainString, FIELD_INDEXED, None).unwrap();
+        let created = schema.add_field("created".to_string(), FieldType::DateTime, FIELD_INDEXED, None).unwrap();
+        let parser = QueryParser::new(&schema, body);
+        let int = |value| Term::from_integer(value);
+
+        assert_eq!(parser.parse("pk:[1 TO 10]"), Ok(Query::range(pk, Bound::Included(int(1)), Bound::Included(int(10)))));
+        assert_eq!(parser.parse("pk:{1 TO 10]"), Ok(Query::range(pk, Bound::Excluded(int(1)), Bound::Included(int(10)))));
+        assert_eq!(parser.parse("pk:[ -10 TO 10 }"), Ok(Query::range(pk, Bound::Included(int(-10)), Bound::Excluded(int(10)))));
+        assert_eq!(parser.parse("pk:[* TO 10]"), Ok(Query::range(pk, Bound::Unbounded, Bound::Included(int(10)))));
+        assert_eq!(parser.parse("pk:>10"), Ok(Query::range(pk, Bound::Excluded(int(10)), Bound::Unbounded)));
+        assert_eq!(parser.parse("pk:>=10"), Ok(Query::range(pk, Bound::Included(int(10)), Bound::Unbounded)));
+        assert_eq!(parser.parse("pk:<10^2"), Ok(Query::range(pk, Bound::Unbounded, Bound::Excluded(int(10))).boost(2.0)));
+        assert_eq!(parser.parse("pk:<=10"), Ok(Query::range(pk, Bound::Unbounded, Bound::Included(int(10)))));
+        assert_eq!(parser.parse("slug:[a TO m}"), Ok(Query::range(slug, Bound::Included(Term::from_string("a")), Bound::Excluded(Term::from_string("m")))));
+
+        let date = "2016-07-23T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(parser.parse("created:>=2016-07-23T00:00:00Z"), Ok(Query::range(created, Bound::Included(Term::from_datetime(&date)), Bound::Unbounded)));
+
+        assert_eq!(parser.parse("pk:[1 10]"), Err(QueryParseError::UnexpectedCharacter(6, '1')));
+        assert_eq!(parser.parse("pk:[1 TO 10"), Err(QueryParseError::UnexpectedEnd(11)));
+        assert_eq!(parser.parse("pk:[1 TO x]"), Err(QueryParseError::InvalidValue(9, "x".to_string(), FieldType::I64)));
+        assert_eq!(parser.parse("slug:[a* TO m]"), Err(QueryParseError::InvalidValue(6, "a*".to_string(), FieldType::PlainString)));
+    }
+
+    #[test]
+    fn test_date_math() {
+        let (mut schema, _, body, _) = make_schema();
+        let created = schema.add_field("created".to_string(), FieldType::DateTime, FIELD_INDEXED, None).unwrap();
+        let now = "2016-07-23T16:15:00Z".parse::<DateTime<Utc>>().unwrap();
+        let parser = QueryParser::new(&schema, body).now(now);
+        let date = |value: &str| Term::from_datetime(&value.parse::<DateTime<Utc>>().unwrap());
+
+        assert_eq!(parser.parse("created:[now-7d/d TO now]"), Ok(Query::range(created, Bound::Included(date("2016-07-16T00:00:00Z")), Bound::Included(date("2016-07-23T16:15:00Z")))));
+        assert_eq!(parser.parse("created:[now/d TO now/d]"), Ok(Query::range(created, Bound::Included(date("2016-07-23T00:00:00Z")), Bound::Included(date("2016-07-23T23:59:59.999999Z")))));
+        assert_eq!(parser.parse("created:{now/d TO now/d}"), Ok(Query::range(created, Bound::Excluded(date("2016-07-23T23:59:59.999999Z")), Bound::Excluded(date("2016-07-23T00:00:00Z")))));
+        assert_eq!(parser.parse("created:>=2024-01-01||+1M"), Ok(Query::range(created, Bound::Included(date("2024-02-01T00:00:00Z")), Bound::Unbounded)));
+        assert_eq!(parser.parse("created:now/h"), Ok(Query::term(created, date("2016-07-23T16:00:00Z"))));
+
+        // Rounding happens in the time zone of the parser
+        let parser = QueryParser::new(&schema, body).now(now).timezone(FixedOffset::east_opt(3600).unwrap());
+        assert_eq!(parser.parse("created:<=now/d"), Ok(Query::range(created, Bound::Unbounded, Bound::Included(date("2016-07-23T22:59:59.999999Z")))));
+        assert_eq!(parser.parse("created:>=2016-07-23"), Ok(Query::range(created, Bound::Included(date("2016-07-22T23:00:00Z")), Bound::Unbounded)));
+
+        // Named time zones follow daylight saving time (New York is UTC-4 in July)
+        let parser = QueryParser::new(&schema, body).now(now).timezone(Tz::America__New_York);
+        assert_eq!(parser.parse("created:[now/d TO now/d]"), Ok(Query::range(created, Bound::Included(date("2016-07-23T04:00:00Z")), Bound::Included(date("2016-07-24T03:59:59.999999Z")))));
+
+        assert_eq!(parser.parse("created:>now-7x"), Err(QueryParseError::InvalidValue(9, "now-7x".to_string(), FieldType::DateTime)));
+    }
+
+    #[test]
+    fn test_empty() {
+        let (schema, _, body, _) = make_schema();
+        let parser = QueryParser::new(&schema, body);
+
+        assert_eq!(parser.parse(""), Ok(Query::None));
+        assert_eq!(parser.parse("   "), Ok(Query::None));
+    }
+}
@@ -0,0 +1,152 @@
+use term::Term;
+
+/// A query that matches spans of positions in a field
+///
+/// Spans are searched with the Query::Span query. All the clauses of a span
+/// query are searched against the same field, which must be indexed with
+/// positions (FIELD_POSITIONS).
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpanQuery {
+    /// Matches each position of the term
+    Term {
+        term: Term,
+    },
+
+    /// Matches spans of the clauses that are near each other
+    Near {
+        clauses: Vec<SpanQuery>,
+
+        /// The maximum number of positions allowed between the clauses
+        slop: u32,
+
+        /// If set, the clauses must appear in the order given
+        in_order: bool,
+    },
+
+    /// Matches spans of any of the clauses
+    Or {
+        clauses: Vec<SpanQuery>,
+    },
+
+    /// Matches spans of "include" that don't overlap a span of "exclude"
+    Not {
+        include: Box<SpanQuery>,
+        exclude: Box<SpanQuery>,
+    },
+
+    /// Matches spans of "span" that end within the first "end" positions of the field
+    First {
+        span: Box<SpanQuery>,
+        end: u32,
+    },
+
+    /// Matches spans of "big" that contain a span of "little"
+    Containing {
+        big: Box<SpanQuery>,
+        little: Box<SpanQuery>,
+    },
+}
+
+impl SpanQuery {
+    /// Creates a new span query that matches a term
+    pub fn term(term: Term) -> SpanQuery {
+        SpanQuery::Term {
+            term: term,
+        }
+    }
+
+    /// Creates a new span query that matches the clauses near each other
+    pub fn near(clauses: Vec<SpanQuery>, slop: u32, in_order: bool) -> SpanQuery {
+        SpanQuery::Near {
+            clauses: clauses,
+            slop: slop,
+            in_order: in_order,
+        }
+    }
+
+    /// Creates a new span query that matches any of the clauses
+    pub fn or(clauses: Vec<SpanQuery>) -> SpanQuery {
+        SpanQuery::Or {
+            clauses: clauses,
+        }
+    }
+
+    /// Creates a new span query that removes spans that overlap the "exclude" query
+    pub fn not(include: SpanQuery, exclude: SpanQuery) -> SpanQuery {
+        SpanQuery::Not {
+            include: Box::new(include),
+            exclude: Box::new(exclude),
+        }
+    }
+
+    /// Creates a new span query that only matches spans within the first "end" positions
+    pub fn first(span: SpanQuery, end: u32) -> SpanQuery {
+        SpanQuery::First {
+            span: Box::new(span),
+            end: end,
+        }
+    }
+
+    /// Creates a new span query that matches spans of "big" that contain a span of "little"
+    pub fn containing(big: SpanQuery, little: SpanQuery) -> SpanQuery {
+        SpanQuery::Containing {
+            big: Box::new(big),
+            little: Box::new(little),
+        }
+    }
+
+    /// Returns all the terms in the query
+    pub fn terms(&self) -> Vec<&Term> {
+        let mut terms = Vec::new();
+        self.collect_terms(&mut terms);
+        terms
+    }
+
+    fn collect_terms<'a>(&'a self, terms: &mut Vec<&'a Term>) {
+        match *self {
+            SpanQuery::Term{ref term} => {
+                if !terms.contains(&term) {
+                    terms.push(term);
+                }
+            }
+            SpanQuery::Near{ref clauses, ..} | SpanQuery::Or{ref clauses} => {
+                for clause in clauses.iter() {
+                    clause.collect_terms(terms);
+                }
+            }
+            SpanQuery::Not{ref include, ..} => {
+                // Excluded terms never contribute to a match
+                include.collect_terms(terms);
+            }
+            SpanQuery::First{ref span, ..} => {
+                span.collect_terms(terms);
+            }
+            SpanQuery::Containing{ref big, ref little} => {
+                big.collect_terms(terms);
+                little.collect_terms(terms);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use super::SpanQuery;
+
+    #[test]
+    fn test_terms() {
+        let query = SpanQuery::not(
+            SpanQuery::near(vec![
+                SpanQuery::term(Term::from_string("quick")),
+                SpanQuery::or(vec![
+                    SpanQuery::term(Term::from_string("fox")),
+                    SpanQuery::term(Term::from_string("quick")),
+                ]),
+            ], 2, true),
+            SpanQuery::term(Term::from_string("brown")),
+        );
+
+        assert_eq!(query.terms(), vec![&Term::from_string("quick"), &Term::from_string("fox")]);
+    }
+}
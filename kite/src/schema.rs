@@ -5,10 +5,21 @@ use std::fmt;
 use serde::{Serialize, Deserialize, Serializer, Deserializer};
 use fnv::FnvHashMap;
 
+use analysis::{AnalyzerConfig, AnalyzerConfigError, ChainAnalyzer};
+
 bitflags! {
     pub flags FieldFlags: u32 {
         const FIELD_INDEXED = 0b00000001,
         const FIELD_STORED  = 0b00000010,
+
+        /// Store the positions of each term in the field so they can be used by
+        /// positional queries (eg, phrases). Only has an effect on indexed fields.
+        const FIELD_POSITIONS = 0b00000100,
+
+        /// Also index lower precision terms for each value so wide ranges can be
+        /// searched quickly (see the trie module). Only I64 and DateTime fields can
+        /// have this flag.
+        const FIELD_TRIE = 0b00001000,
     }
 }
 
@@ -26,6 +37,14 @@ impl Serialize for FieldFlags {
             flag_strings.push("STORED");
         }
 
+        if self.contains(FIELD_POSITIONS) {
+            flag_strings.push("POSITIONS");
+        }
+
+        if self.contains(FIELD_TRIE) {
+            flag_strings.push("TRIE");
+        }
+
         serializer.serialize_str(&flag_strings.join("|"))
     }
 }
@@ -56,6 +75,12 @@ impl<'a> Deserialize<'a> for FieldFlags {
                         "STORED" => {
                             flags |= FIELD_STORED;
                         }
+                        "POSITIONS" => {
+                            flags |= FIELD_POSITIONS;
+                        }
+                        "TRIE" => {
+                            flags |= FIELD_TRIE;
+                        }
                         _ => {} // TODO: error
                     }
                 }
@@ -82,14 +107,33 @@ pub struct FieldInfo {
     name: String,
     pub field_type: FieldType,
     pub field_flags: FieldFlags,
+
+    /// The analyzer to convert the values of Text fields into terms with
+    /// If this isn't set, the standard analyzer is used
+    #[serde(default)]
+    pub analyzer: Option<AnalyzerConfig>,
 }
 
 impl FieldInfo {
-    pub fn new(name: String, field_type: FieldType, field_flags: FieldFlags) -> FieldInfo {
+    pub fn new(name: String, field_type: FieldType, field_flags: FieldFlags, analyzer: Option<AnalyzerConfig>) -> FieldInfo {
         FieldInfo {
             name: name,
             field_type: field_type,
             field_flags: field_flags,
+            analyzer: analyzer,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Builds the analyzer for this field
+    /// Use this for analyzing both values being indexed into this field and query text
+    pub fn build_analyzer(&self) -> ChainAnalyzer {
+        match self.analyzer {
+            Some(ref analyzer) => analyzer.build(),
+            None => ChainAnalyzer::standard(),
         }
     }
 }
@@ -136,6 +180,15 @@ impl<'a> Deserialize<'a> for FieldId {
 #[derive(Debug)]
 pub enum AddFieldError {
     FieldAlreadyExists(String),
+
+    /// An analyzer was given for a field type that doesn't get analyzed (only Text fields are)
+    AnalyzerNotSupported(FieldType),
+
+    /// The analyzer config is invalid
+    InvalidAnalyzer(AnalyzerConfigError),
+
+    /// FIELD_TRIE was given for a field type that isn't an integer or datetime
+    TrieNotSupported(FieldType),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -165,13 +218,27 @@ impl Schema {
         self.field_names.get(name).cloned()
     }
 
-    pub fn add_field(&mut self, name: String, field_type: FieldType, field_flags: FieldFlags) -> Result<FieldId, AddFieldError> {
+    pub fn add_field(&mut self, name: String, field_type: FieldType, field_flags: FieldFlags, analyzer: Option<AnalyzerConfig>) -> Result<FieldId, AddFieldError> {
         if self.field_names.contains_key(&name) {
             return Err(AddFieldError::FieldAlreadyExists(name));
         }
 
+        if analyzer.is_some() && field_type != FieldType::Text {
+            return Err(AddFieldError::AnalyzerNotSupported(field_type));
+        }
+
+        if field_flags.contains(FIELD_TRIE) && field_type != FieldType::I64 && field_type != FieldType::DateTime {
+            return Err(AddFieldError::TrieNotSupported(field_type));
+        }
+
+        if let Some(ref analyzer) = analyzer {
+            if let Err(e) = analyzer.validate() {
+                return Err(AddFieldError::InvalidAnalyzer(e));
+            }
+        }
+
         let field_id = self.new_field_id();
-        let field_info = FieldInfo::new(name.clone(), field_type, field_flags);
+        let field_info = FieldInfo::new(name.clone(), field_type, field_flags, analyzer);
 
         self.fields.insert(field_id, field_info);
         self.field_names.insert(name, field_id);
@@ -179,6 +246,18 @@ impl Schema {
         Ok(field_id)
     }
 
+    /// Builds the analyzers of all of the Text fields
+    ///
+    /// Building an analyzer compiles its patterns and parses its synonym lists, so
+    /// when indexing many documents these should be built once and passed into
+    /// Document::from_field_values_with_analyzers.
+    pub fn build_analyzers(&self) -> FnvHashMap<FieldId, ChainAnalyzer> {
+        self.fields.iter()
+            .filter(|&(_, field_info)| field_info.field_type == FieldType::Text)
+            .map(|(field_id, field_info)| (*field_id, field_info.build_analyzer()))
+            .collect()
+    }
+
     pub fn remove_field(&mut self, field_id: &FieldId) -> bool {
         match self.fields.remove(field_id) {
             Some(removed_field) => {
@@ -11,6 +11,10 @@ pub trait Segment {
     fn load_statistic(&self, stat_name: &[u8]) -> Result<Option<i64>, String>;
     fn load_stored_field_value_raw(&self, doc_local_id: u16, field_id: FieldId, value_type: &[u8]) -> Result<Option<Vec<u8>>, String>;
     fn load_term_directory(&self, field_id: FieldId, term_id: TermId) -> Result<Option<RoaringBitmap>, String>;
+
+    /// Loads the positions of a term in a document
+    /// Returns None if the field wasn't indexed with positions or the term isn't in the document
+    fn load_term_positions(&self, doc_local_id: u16, field_id: FieldId, term_id: TermId) -> Result<Option<RoaringBitmap>, String>;
     fn load_deletion_list(&self) -> Result<Option<RoaringBitmap>, String>;
     fn id(&self) -> SegmentId;
 
@@ -1,4 +1,4 @@
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SimilarityModel {
     TfIdf,
     Bm25{k1: f32, b: f32},
@@ -6,8 +6,8 @@ pub enum SimilarityModel {
 
 /// tf(term_frequency) = log(term_frequency + 1.0) + 1.0
 #[inline]
-fn tf(term_frequency: u32) -> f32 {
-    (term_frequency as f32 + 1.0f32).ln() + 1.0
+fn tf(term_frequency: f32) -> f32 {
+    (term_frequency + 1.0f32).ln() + 1.0
 }
 
 /// idf(term_docs, total_docs) = log((total_docs + 1.0) / (term_docs + 1.0)) + 1.0
@@ -18,6 +18,12 @@ fn idf(term_docs: u64, total_docs: u64) -> f32 {
 
 impl SimilarityModel {
     pub fn score(&self, term_frequency: u32, length: f32, total_tokens: u64, total_docs: u64, total_docs_with_term: u64) -> f32 {
+        self.score_sloppy(term_frequency as f32, length, total_tokens, total_docs, total_docs_with_term)
+    }
+
+    /// Scores a match with a fractional frequency
+    /// Used by positional queries which give less weight to matches that are spread out
+    pub fn score_sloppy(&self, term_frequency: f32, length: f32, total_tokens: u64, total_docs: u64, total_docs_with_term: u64) -> f32 {
         match *self {
             SimilarityModel::TfIdf => {
                 let tf = tf(term_frequency);
@@ -75,6 +81,14 @@ mod tests {
         assert!(similarity.score(0, 0.0, 0, 0, 0).is_finite());
     }
 
+    #[test]
+    fn test_tf_idf_sloppy_frequency() {
+        let similarity = SimilarityModel::TfIdf;
+
+        assert!(similarity.score_sloppy(1.0, 40.0, 100, 10, 5) == similarity.score(1, 40.0, 100, 10, 5));
+        assert!(similarity.score_sloppy(0.5, 40.0, 100, 10, 5) < similarity.score(1, 40.0, 100, 10, 5));
+    }
+
     #[test]
     fn test_bm25_higher_term_freq_increases_score() {
         let similarity = SimilarityModel::Bm25 {
@@ -1,11 +1,46 @@
-use chrono::{DateTime, Utc, Timelike};
-use byteorder::{WriteBytesExt, LittleEndian};
+use chrono::{DateTime, Utc, Timelike, TimeZone};
+use byteorder::{ByteOrder, BigEndian};
 
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct TermId(pub u32);
 
 
+/// Encodes an integer into 8 bytes that sort in the same order as the integers
+///
+/// The integer is written big-endian with its sign bit flipped, so negative
+/// numbers sort before positive ones. Integer and DateTime values are encoded
+/// this way in both terms and stored values.
+pub fn encode_sortable_i64(value: i64) -> Vec<u8> {
+    let mut bytes = vec![0; 8];
+    BigEndian::write_u64(&mut bytes, (value as u64) ^ (1 << 63));
+    bytes
+}
+
+/// Decodes an integer that was encoded with encode_sortable_i64
+/// Returns None if there aren't exactly 8 bytes
+pub fn decode_sortable_i64(bytes: &[u8]) -> Option<i64> {
+    if bytes.len() != 8 {
+        return None;
+    }
+
+    Some((BigEndian::read_u64(bytes) ^ (1 << 63)) as i64)
+}
+
+/// Converts a datetime into the number of microseconds since the unix epoch
+pub fn datetime_to_micros(value: &DateTime<Utc>) -> i64 {
+    let timestamp = value.timestamp();
+    let micros = value.nanosecond() / 1000;
+    timestamp * 1000000 + micros as i64
+}
+
+/// Converts a number of microseconds since the unix epoch into a datetime
+pub fn micros_to_datetime(micros: i64) -> Option<DateTime<Utc>> {
+    let seconds = micros.div_euclid(1000000);
+    let nanos = micros.rem_euclid(1000000) * 1000;
+    Utc.timestamp_opt(seconds, nanos as u32).single()
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
 pub struct Term(Vec<u8>);
 
@@ -33,18 +68,22 @@ impl Term {
     }
 
     pub fn from_integer(value: i64) -> Term {
-        let mut bytes = Vec::with_capacity(8);
-        bytes.write_i64::<LittleEndian>(value).unwrap();
-        Term(bytes)
+        Term(encode_sortable_i64(value))
     }
 
+    /// Creates a term from a datetime, with microsecond precision
     pub fn from_datetime(value: &DateTime<Utc>) -> Term {
-        let mut bytes = Vec::with_capacity(0);
-        let timestamp = value.timestamp();
-        let micros = value.nanosecond() / 1000;
-        let timestamp_with_micros = timestamp * 1000000 + micros as i64;
-        bytes.write_i64::<LittleEndian>(timestamp_with_micros).unwrap();
-        Term(bytes)
+        Term(encode_sortable_i64(datetime_to_micros(value)))
+    }
+
+    /// Reads the integer from a term created with from_integer
+    pub fn to_integer(&self) -> Option<i64> {
+        decode_sortable_i64(&self.0)
+    }
+
+    /// Reads the datetime from a term created with from_datetime
+    pub fn to_datetime(&self) -> Option<DateTime<Utc>> {
+        decode_sortable_i64(&self.0).and_then(micros_to_datetime)
     }
 
     pub fn as_bytes(&self) -> &[u8] {
@@ -55,7 +94,7 @@ impl Term {
 #[cfg(test)]
 mod tests {
     use chrono::{DateTime, Utc, Timelike};
-    use super::Term;
+    use super::{Term, encode_sortable_i64, decode_sortable_i64};
 
     #[test]
     fn test_string_to_bytes() {
@@ -98,14 +137,14 @@ mod tests {
     fn test_integer_to_bytes() {
         let term = Term::from_integer(123);
 
-        assert_eq!(term.as_bytes().to_vec(), vec![123, 0, 0, 0, 0, 0, 0, 0])
+        assert_eq!(term.as_bytes().to_vec(), vec![128, 0, 0, 0, 0, 0, 0, 123])
     }
 
     #[test]
     fn test_negative_integer_to_bytes() {
         let term = Term::from_integer(-123);
 
-        assert_eq!(term.as_bytes().to_vec(), vec![133, 255, 255, 255, 255, 255, 255, 255])
+        assert_eq!(term.as_bytes().to_vec(), vec![127, 255, 255, 255, 255, 255, 255, 133])
     }
 
     #[test]
@@ -113,7 +152,7 @@ mod tests {
         let date = "2016-07-23T16:15:00+01:00".parse::<DateTime<Utc>>().unwrap();
         let term = Term::from_datetime(&date);
 
-        assert_eq!(term.as_bytes().to_vec(), vec![0, 101, 191, 3, 79, 56, 5, 0])
+        assert_eq!(term.as_bytes().to_vec(), vec![128, 5, 56, 79, 3, 191, 101, 0])
     }
 
     #[test]
@@ -123,7 +162,7 @@ mod tests {
         let term = Term::from_datetime(&date);
 
         // This is exactly 123123 higher than the result of "test_datetime_to_bytes"
-        assert_eq!(term.as_bytes().to_vec(), vec![243, 69, 193, 3, 79, 56, 5, 0])
+        assert_eq!(term.as_bytes().to_vec(), vec![128, 5, 56, 79, 3, 193, 69, 243])
     }
 
     #[test]
@@ -132,6 +171,39 @@ mod tests {
         let term = Term::from_datetime(&date);
 
         // This is exactly 3_600_000_000 lower than the result of "test_datetime_to_bytes"
-        assert_eq!(term.as_bytes().to_vec(), vec![0, 193, 43, 45, 78, 56, 5, 0])
+        assert_eq!(term.as_bytes().to_vec(), vec![128, 5, 56, 78, 45, 43, 193, 0])
+    }
+
+    #[test]
+    fn test_integers_sort_in_order() {
+        let values = vec![i64::MIN, -1000, -1, 0, 1, 255, 256, 1000, i64::MAX];
+        let terms = values.iter().map(|value| Term::from_integer(*value)).collect::<Vec<Term>>();
+
+        let mut sorted_terms = terms.clone();
+        sorted_terms.sort();
+        assert_eq!(sorted_terms, terms);
+    }
+
+    #[test]
+    fn test_datetimes_sort_in_order() {
+        let before_epoch = "1969-12-31T23:59:59.5Z".parse::<DateTime<Utc>>().unwrap();
+        let epoch = "1970-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let after_epoch = "2016-07-23T16:15:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        assert!(Term::from_datetime(&before_epoch) < Term::from_datetime(&epoch));
+        assert!(Term::from_datetime(&epoch) < Term::from_datetime(&after_epoch));
+    }
+
+    #[test]
+    fn test_decode() {
+        for value in vec![i64::MIN, -123, 0, 123, i64::MAX] {
+            assert_eq!(decode_sortable_i64(&encode_sortable_i64(value)), Some(value));
+            assert_eq!(Term::from_integer(value).to_integer(), Some(value));
+        }
+
+        let date = "1969-07-20T20:17:40.123456Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(Term::from_datetime(&date).to_datetime(), Some(date));
+
+        assert_eq!(Term::from_string("foo").to_integer(), None);
     }
 }
@@ -6,12 +6,49 @@ use roaring::RoaringBitmap;
 use term::Term;
 use token::Token;
 
+/// The terms of a field along with the positions that each term appears in
+///
+/// The byte offsets and payloads of each occurrence of a term are kept separately,
+/// keyed by the term and its position. Dereferences to the map of terms to
+/// positions.
 #[derive(Debug, Clone, PartialEq)]
-pub struct TermVector(HashMap<Term, RoaringBitmap>);
+pub struct TermVector {
+    terms: HashMap<Term, RoaringBitmap>,
+    byte_offsets: HashMap<(Term, u32), (usize, usize)>,
+    payloads: HashMap<(Term, u32), Vec<u8>>,
+}
 
 impl TermVector {
     pub fn new() -> TermVector {
-        TermVector(HashMap::new())
+        TermVector {
+            terms: HashMap::new(),
+            byte_offsets: HashMap::new(),
+            payloads: HashMap::new(),
+        }
+    }
+
+    /// Adds a token to the term vector
+    pub fn add_token(&mut self, token: Token) {
+        if let Some(offsets) = token.byte_offsets {
+            self.byte_offsets.insert((token.term.clone(), token.position), offsets);
+        }
+
+        if let Some(payload) = token.payload {
+            self.payloads.insert((token.term.clone(), token.position), payload);
+        }
+
+        let positions = self.terms.entry(token.term).or_insert_with(RoaringBitmap::new);
+        positions.insert(token.position);
+    }
+
+    /// Returns the byte offsets of the term at the position
+    pub fn get_byte_offsets(&self, term: &Term, position: u32) -> Option<(usize, usize)> {
+        self.byte_offsets.get(&(term.clone(), position)).cloned()
+    }
+
+    /// Returns the payload of the term at the position
+    pub fn get_payload(&self, term: &Term, position: u32) -> Option<&[u8]> {
+        self.payloads.get(&(term.clone(), position)).map(|payload| &payload[..])
     }
 }
 
@@ -19,41 +56,69 @@ impl Deref for TermVector {
     type Target = HashMap<Term, RoaringBitmap>;
 
     fn deref(&self) -> &HashMap<Term, RoaringBitmap> {
-        &self.0
+        &self.terms
     }
 }
 
 impl DerefMut for TermVector {
     fn deref_mut(&mut self) -> &mut HashMap<Term, RoaringBitmap> {
-        &mut self.0
+        &mut self.terms
     }
 }
 
 impl Into<TermVector> for Vec<Token> {
     fn into(self) -> TermVector {
-        let mut map = HashMap::new();
+        let mut term_vector = TermVector::new();
 
         for token in self {
-            let positions = map.entry(token.term).or_insert_with(RoaringBitmap::new);
-            positions.insert(token.position);
+            term_vector.add_token(token);
         }
 
-         TermVector(map)
+        term_vector
     }
 }
 
 impl Into<Vec<Token>> for TermVector {
-    fn into(self) -> Vec<Token> {
+    fn into(mut self) -> Vec<Token> {
         let mut vec = Vec::new();
 
-        for (term, positions) in self.0 {
+        for (term, positions) in self.terms {
             for position in positions {
-                vec.push(Token { term: term.clone(), position: position });
+                let key = (term.clone(), position);
+                let offsets = self.byte_offsets.remove(&key);
+                let payload = self.payloads.remove(&key);
+
+                vec.push(Token { term: key.0, position: position, byte_offsets: offsets, payload: payload });
             }
         }
 
-        vec.sort_by_key(|token| token.position);
+        vec.sort_by(|a, b| (a.position, &a.term).cmp(&(b.position, &b.term)));
 
         vec
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use token::Token;
+    use super::TermVector;
+
+    #[test]
+    fn test_round_trip() {
+        let tokens = vec![
+            Token { term: Term::from_string("hello"), position: 1, byte_offsets: Some((0, 5)), payload: None },
+            Token { term: Term::from_string("world"), position: 2, byte_offsets: Some((6, 11)), payload: Some(vec![1, 2]) },
+            Token { term: Term::from_string("hello"), position: 3, byte_offsets: None, payload: Some(vec![3]) },
+        ];
+
+        let term_vector: TermVector = tokens.clone().into();
+        assert_eq!(term_vector.get(&Term::from_string("hello")).unwrap().iter().collect::<Vec<u32>>(), vec![1, 3]);
+        assert_eq!(term_vector.get_byte_offsets(&Term::from_string("world"), 2), Some((6, 11)));
+        assert_eq!(term_vector.get_payload(&Term::from_string("hello"), 3), Some(&[3][..]));
+        assert_eq!(term_vector.get_payload(&Term::from_string("hello"), 1), None);
+
+        let round_tripped: Vec<Token> = term_vector.into();
+        assert_eq!(round_tripped, tokens);
+    }
+}
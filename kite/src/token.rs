@@ -4,4 +4,57 @@ use term::Term;
 pub struct Token {
     pub term: Term,
     pub position: u32,
+
+    /// The start and end byte offsets of the token in the original text
+    /// These can be used to slice the text (eg, for highlighting). Use char_offsets
+    /// to convert them into character offsets.
+    pub byte_offsets: Option<(usize, usize)>,
+
+    /// Arbitrary data attached to the token (eg, a boost)
+    pub payload: Option<Vec<u8>>,
+}
+
+impl Token {
+    /// Returns the start and end character offsets of the token in the original text
+    ///
+    /// "text" must be the text the token was produced from. Returns None if the
+    /// token doesn't have offsets or they don't fall on character boundaries of the text.
+    pub fn char_offsets(&self, text: &str) -> Option<(usize, usize)> {
+        let (start, end) = match self.byte_offsets {
+            Some(byte_offsets) => byte_offsets,
+            None => return None,
+        };
+
+        if start > end || !text.is_char_boundary(start) || !text.is_char_boundary(end) {
+            return None;
+        }
+
+        let char_start = text[..start].chars().count();
+        Some((char_start, char_start + text[start..end].chars().count()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use term::Term;
+    use super::Token;
+
+    #[test]
+    fn test_char_offsets() {
+        let text = "café au lait";
+        let token = Token { term: Term::from_string("au"), position: 2, byte_offsets: Some((6, 8)), payload: None };
+
+        assert_eq!(&text[6..8], "au");
+        assert_eq!(token.char_offsets(text), Some((5, 7)));
+
+        let token = Token { term: Term::from_string("café"), position: 1, byte_offsets: Some((0, 5)), payload: None };
+        assert_eq!(token.char_offsets(text), Some((0, 4)));
+
+        // Not on a character boundary
+        let token = Token { term: Term::from_string("caf"), position: 1, byte_offsets: Some((0, 4)), payload: None };
+        assert_eq!(token.char_offsets(text), None);
+
+        let token = Token { term: Term::from_string("au"), position: 2, byte_offsets: None, payload: None };
+        assert_eq!(token.char_offsets(text), None);
+    }
 }
@@ -0,0 +1,196 @@
+//! Multi-precision ("trie") indexing of integer and datetime fields
+//!
+//! A range over a field with lots of distinct values matches lots of terms, each of
+//! which has its own term directory to load. Fields indexed with FIELD_TRIE get a
+//! few extra terms for each value, which have the lowest 8, 16, ... 56 bits of the
+//! value cleared. Each of these covers a block of 256, 65536, ... values, so a
+//! range can be matched with a few terms at each level of precision rather than a
+//! term for every value in it.
+//!
+//! Full precision terms are the same as in other fields (see Term::from_integer) so
+//! term queries work as normal. Lower precision terms have an extra byte at the end
+//! with the level of precision so they never clash with a full precision term.
+
+use std::ops::Bound;
+
+use byteorder::{ByteOrder, BigEndian};
+
+use term::Term;
+
+/// The number of bits cleared at each level of precision
+pub const PRECISION_STEP: u32 = 8;
+
+/// Reads the bytes of an integer or datetime term as an unsigned integer
+/// These are in the same order as the terms
+fn term_to_u64(term: &Term) -> Option<u64> {
+    let bytes = term.as_bytes();
+
+    if bytes.len() != 8 {
+        return None;
+    }
+
+    Some(BigEndian::read_u64(bytes))
+}
+
+/// Creates the term that covers all the values that only differ from "value" in
+/// their lowest "shift" bits
+fn precision_term(value: u64, shift: u32) -> Term {
+    if shift == 0 {
+        let mut bytes = [0; 8];
+        BigEndian::write_u64(&mut bytes, value);
+        return Term::from_bytes(&bytes);
+    }
+
+    let mut bytes = [0; 9];
+    BigEndian::write_u64(&mut bytes, value & !((1 << shift) - 1));
+    bytes[8] = (shift / PRECISION_STEP) as u8;
+    Term::from_bytes(&bytes)
+}
+
+/// Returns the lower precision terms to index alongside an integer or datetime term
+/// Terms that aren't integer or datetime terms don't have any
+pub fn lower_precision_terms(term: &Term) -> Vec<Term> {
+    let value = match term_to_u64(term) {
+        Some(value) => value,
+        None => return Vec::new(),
+    };
+
+    (1..64 / PRECISION_STEP)
+        .map(|level| precision_term(value, level * PRECISION_STEP))
+        .collect()
+}
+
+/// Finds the terms of a trie field that together cover all the values in a range
+///
+/// The parts at each end of the range that don't fill a whole block are covered by
+/// terms at the current level of precision and the rest by terms at the next level.
+/// So there are at most 255 terms at each end of each level.
+///
+/// Returns None if either bound isn't an integer or datetime term.
+pub fn range_terms(lower: &Bound<Term>, upper: &Bound<Term>) -> Option<Vec<Term>> {
+    let block_mask = (1 << PRECISION_STEP) - 1;
+
+    // Convert the bounds to an inclusive range
+    let mut min = match *lower {
+        Bound::Included(ref term) => match term_to_u64(term) {
+            Some(value) => value,
+            None => return None,
+        },
+        Bound::Excluded(ref term) => match term_to_u64(term) {
+            Some(value) => match value.checked_add(1) {
+                Some(value) => value,
+                None => return Some(Vec::new()),
+            },
+            None => return None,
+        },
+        Bound::Unbounded => 0,
+    };
+
+    let mut max = match *upper {
+        Bound::Included(ref term) => match term_to_u64(term) {
+            Some(value) => value,
+            None => return None,
+        },
+        Bound::Excluded(ref term) => match term_to_u64(term) {
+            Some(value) => match value.checked_sub(1) {
+                Some(value) => value,
+                None => return Some(Vec::new()),
+            },
+            None => return None,
+        },
+        Bound::Unbounded => u64::MAX,
+    };
+
+    // "min" and "max" are in units of the current level (the values shifted right by "shift")
+    let mut terms = Vec::new();
+    let mut shift = 0;
+    while min <= max {
+        let is_last_level = shift + PRECISION_STEP >= 64;
+        let fills_blocks = min & block_mask == 0 && max & block_mask == block_mask;
+
+        if is_last_level || (min >> PRECISION_STEP == max >> PRECISION_STEP && !fills_blocks) {
+            // The rest of the range can't be covered by any bigger blocks
+            terms.extend((min..=max).map(|unit| precision_term(unit << shift, shift)));
+            break;
+        }
+
+        if min & block_mask != 0 {
+            terms.extend((min..=(min | block_mask)).map(|unit| precision_term(unit << shift, shift)));
+            min = (min >> PRECISION_STEP) + 1;
+        } else {
+            min >>= PRECISION_STEP;
+        }
+
+        if max & block_mask != block_mask {
+            terms.extend(((max & !block_mask)..=max).map(|unit| precision_term(unit << shift, shift)));
+            max = match (max >> PRECISION_STEP).checked_sub(1) {
+                Some(max) => max,
+                None => break,
+            };
+        } else {
+            max >>= PRECISION_STEP;
+        }
+
+        shift += PRECISION_STEP;
+    }
+
+    Some(terms)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+
+    use term::Term;
+    use super::{lower_precision_terms, range_terms};
+
+    /// Finds the values in a small sample that are covered by the terms of a range
+    fn covered_values(lower: Bound<Term>, upper: Bound<Term>, values: &[i64]) -> Vec<i64> {
+        let terms = range_terms(&lower, &upper).unwrap();
+
+        values.iter().cloned().filter(|value| {
+            let term = Term::from_integer(*value);
+            terms.contains(&term) || lower_precision_terms(&term).iter().any(|term| terms.contains(term))
+        }).collect()
+    }
+
+    #[test]
+    fn test_lower_precision_terms() {
+        let terms = lower_precision_terms(&Term::from_integer(0x0102030405060708));
+
+        assert_eq!(terms.len(), 7);
+        assert_eq!(terms[0].as_bytes(), &[0x81, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x00, 1]);
+        assert_eq!(terms[6].as_bytes(), &[0x81, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 7]);
+
+        assert!(lower_precision_terms(&Term::from_string("foo")).is_empty());
+    }
+
+    #[test]
+    fn test_range_terms() {
+        let values = [i64::MIN, -100000, -300, -256, -255, -1, 0, 1, 255, 256, 300, 65535, 65536, 100000, i64::MAX];
+        let int = |value| Term::from_integer(value);
+
+        assert_eq!(covered_values(Bound::Included(int(0)), Bound::Included(int(300)), &values), vec![0, 1, 255, 256, 300]);
+        assert_eq!(covered_values(Bound::Excluded(int(0)), Bound::Excluded(int(300)), &values), vec![1, 255, 256]);
+        assert_eq!(covered_values(Bound::Included(int(-256)), Bound::Excluded(int(256)), &values), vec![-256, -255, -1, 0, 1, 255]);
+        assert_eq!(covered_values(Bound::Included(int(256)), Bound::Included(int(65535)), &values), vec![256, 300, 65535]);
+        assert_eq!(covered_values(Bound::Included(int(300)), Bound::Unbounded, &values), vec![300, 65535, 65536, 100000, i64::MAX]);
+        assert_eq!(covered_values(Bound::Unbounded, Bound::Included(int(-300)), &values), vec![i64::MIN, -100000, -300]);
+        assert_eq!(covered_values(Bound::Unbounded, Bound::Unbounded, &values), values.to_vec());
+        assert_eq!(covered_values(Bound::Included(int(1)), Bound::Included(int(1)), &values), vec![1]);
+        assert_eq!(covered_values(Bound::Included(int(2)), Bound::Included(int(1)), &values), Vec::<i64>::new());
+        assert_eq!(covered_values(Bound::Excluded(int(i64::MAX)), Bound::Unbounded, &values), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn test_wide_range_uses_few_terms() {
+        let terms = range_terms(&Bound::Included(Term::from_integer(-123456789)), &Bound::Included(Term::from_integer(987654321987))).unwrap();
+
+        assert!(terms.len() < 255 * 2 * 8);
+    }
+
+    #[test]
+    fn test_invalid_bounds() {
+        assert_eq!(range_terms(&Bound::Included(Term::from_string("foo")), &Bound::Unbounded), None);
+    }
+}
@@ -24,15 +24,17 @@ fn bench_insert_single_document(b: &mut Bencher) {
     remove_dir_all("test_indices/bench_insert_single_document");
 
     let mut store = RocksDBStore::create("test_indices/bench_insert_single_document").unwrap();
-    let title_field = store.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED).unwrap();
-    let body_field = store.add_field("body".to_string(), FieldType::Text, FIELD_INDEXED).unwrap();
-    let id_field = store.add_field("id".to_string(), FieldType::I64, FIELD_STORED).unwrap();
+    let title_field = store.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED, None).unwrap();
+    let body_field = store.add_field("body".to_string(), FieldType::Text, FIELD_INDEXED, None).unwrap();
+    let id_field = store.add_field("id".to_string(), FieldType::I64, FIELD_STORED, None).unwrap();
 
     let mut tokens = Vec::new();
     for t in 0..500 {
         tokens.push(Token {
             term: Term::from_string(&t.to_string()),
-            position: t
+            position: t,
+            offsets: None,
+            payload: None,
         });
     }
 
@@ -42,7 +44,7 @@ fn bench_insert_single_document(b: &mut Bencher) {
 
         let mut indexed_fields = FnvHashMap::default();
         indexed_fields.insert(body_field, tokens.clone().into());
-        indexed_fields.insert(title_field, vec![Token { term: Term::from_string(&i.to_string()), position: 1}].into());
+        indexed_fields.insert(title_field, vec![Token { term: Term::from_string(&i.to_string()), position: 1, offsets: None, payload: None}].into());
 
         let mut stored_fields = FnvHashMap::default();
         stored_fields.insert(id_field, FieldValue::Integer(i));
@@ -60,15 +62,17 @@ fn bench_insert_documents_parallel(b: &mut Bencher) {
     remove_dir_all("test_indices/bench_insert_single_document_parallel");
 
     let mut store = RocksDBStore::create("test_indices/bench_insert_single_document_parallel").unwrap();
-    let title_field = store.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED).unwrap();
-    let body_field = store.add_field("body".to_string(), FieldType::Text, FIELD_INDEXED).unwrap();
-    let id_field = store.add_field("id".to_string(), FieldType::I64, FIELD_STORED).unwrap();
+    let title_field = store.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED, None).unwrap();
+    let body_field = store.add_field("body".to_string(), FieldType::Text, FIELD_INDEXED, None).unwrap();
+    let id_field = store.add_field("id".to_string(), FieldType::I64, FIELD_STORED, None).unwrap();
 
     let mut tokens = Vec::new();
     for t in 0..500 {
         tokens.push(Token {
             term: Term::from_string(&t.to_string()),
-            position: t
+            position: t,
+            offsets: None,
+            payload: None,
         });
     }
 
@@ -76,7 +80,7 @@ fn bench_insert_documents_parallel(b: &mut Bencher) {
     for i in 0..8 {
         let mut indexed_fields = FnvHashMap::default();
         indexed_fields.insert(body_field, tokens.clone().into());
-        indexed_fields.insert(title_field, vec![Token { term: Term::from_string(&(i + 1).to_string()), position: 1}].into());
+        indexed_fields.insert(title_field, vec![Token { term: Term::from_string(&(i + 1).to_string()), position: 1, offsets: None, payload: None}].into());
 
         let mut stored_fields = FnvHashMap::default();
         stored_fields.insert(id_field, FieldValue::Integer(i));
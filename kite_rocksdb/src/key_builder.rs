@@ -133,6 +133,32 @@ impl KeyBuilder {
         stat_name
     }
 
+    pub fn stored_field_value_term_positions_value_type(term_id: u32) -> Vec<u8> {
+        let mut value_type = Vec::new();
+        for c in b"tp" {
+            value_type.push(*c);
+        }
+
+        for c in term_id.to_string().as_bytes() {
+            value_type.push(*c);
+        }
+
+        value_type
+    }
+
+    pub fn stored_field_value_term_frequency_value_type(term_id: u32) -> Vec<u8> {
+        let mut value_type = Vec::new();
+        for c in b"tf" {
+            value_type.push(*c);
+        }
+
+        for c in term_id.to_string().as_bytes() {
+            value_type.push(*c);
+        }
+
+        value_type
+    }
+
     pub fn segment_del_list(segment: u32) -> KeyBuilder {
         let mut kb = KeyBuilder::new();
         kb.push_char(b'x');
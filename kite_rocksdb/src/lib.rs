@@ -14,6 +14,7 @@ mod segment_stats;
 mod segment_builder;
 mod term_dictionary;
 mod document_index;
+mod migrations;
 mod search;
 
 use std::str;
@@ -24,10 +25,11 @@ use std::sync::Arc;
 use rocksdb::{DB, WriteBatch, Options, MergeOperands, Snapshot};
 use kite::{Document, DocId, TermId};
 use kite::document::FieldValue;
+use kite::term::{decode_sortable_i64, micros_to_datetime};
 use kite::schema::{Schema, FieldType, FieldFlags, FieldId, AddFieldError};
+use kite::analysis::AnalyzerConfig;
 use kite::segment::SegmentId;
 use byteorder::{ByteOrder, LittleEndian};
-use chrono::{NaiveDateTime, DateTime, Utc};
 use fnv::FnvHashMap;
 
 use key_builder::KeyBuilder;
@@ -135,6 +137,9 @@ impl RocksDBStore {
         };
         try!(db.put(b".schema", schema_encoded.as_bytes()));
 
+        // Format version
+        try!(migrations::write_format_version(&db));
+
         // Segment manager
         let segments = try!(SegmentManager::new(&db));
 
@@ -178,22 +183,31 @@ impl RocksDBStore {
         // Document index
         let document_index = try!(DocumentIndexManager::open(&db));
 
-        Ok(RocksDBStore {
+        let store = RocksDBStore {
             schema: Arc::new(schema),
             db: db,
             term_dictionary: term_dictionary,
             segments: segments,
             document_index: document_index,
-        })
+        };
+
+        // Upgrade stores created by older versions
+        try!(store.upgrade_format());
+
+        Ok(store)
     }
 
     pub fn path(&self) -> &Path {
         self.db.path()
     }
 
-    pub fn add_field(&mut self, name: String, field_type: FieldType, field_flags: FieldFlags) -> Result<FieldId, AddFieldError> {
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    pub fn add_field(&mut self, name: String, field_type: FieldType, field_flags: FieldFlags, analyzer: Option<AnalyzerConfig>) -> Result<FieldId, AddFieldError> {
         let mut schema_copy = (*self.schema).clone();
-        let field_id = try!(schema_copy.add_field(name, field_type, field_flags));
+        let field_id = try!(schema_copy.add_field(name, field_type, field_flags, analyzer));
         self.schema = Arc::new(schema_copy);
 
         // FIXME: How do we throw this error?
@@ -220,7 +234,7 @@ impl RocksDBStore {
         // Build segment in memory
         let mut builder = segment_builder::SegmentBuilder::new();
         let doc_key = doc.key.clone();
-        try!(builder.add_document(doc));
+        try!(builder.add_document(&self.schema, doc));
 
         // Write the segment
         let segment = try!(self.write_segment(&builder));
@@ -267,10 +281,35 @@ impl RocksDBStore {
 
         // Write stored fields
         for (&(field_id, doc_id, ref value_type), value) in builder.stored_field_values.iter() {
-            let kb = KeyBuilder::stored_field_value(segment, doc_id, field_id.0, value_type);
+            // Term frequencies are keyed by the builder's TermIds so must be mapped to the real ones
+            let kb = if value_type.starts_with(b"tf") {
+                let term_id = TermId(str::from_utf8(&value_type[2..]).unwrap().parse::<u32>().unwrap());
+                let new_term_id = term_dictionary_map.get(&term_id).expect("TermId not in term_dictionary_map");
+                let value_type = KeyBuilder::stored_field_value_term_frequency_value_type(new_term_id.0);
+                KeyBuilder::stored_field_value(segment, doc_id, field_id.0, &value_type)
+            } else {
+                KeyBuilder::stored_field_value(segment, doc_id, field_id.0, value_type)
+            };
+
             try!(write_batch.put(&kb.key(), value));
         }
 
+        // Write term positions
+        // These are stored alongside the other stored values of the document so they are
+        // moved by segment merges and deleted by segment purges
+        for (&(field_id, term_id, doc_id), positions) in builder.term_positions.iter() {
+            let new_term_id = term_dictionary_map.get(&term_id).expect("TermId not in term_dictionary_map");
+
+            // Serialise
+            let mut positions_bytes = Vec::new();
+            positions.serialize_into(&mut positions_bytes).unwrap();
+
+            // Write
+            let value_type = KeyBuilder::stored_field_value_term_positions_value_type(new_term_id.0);
+            let kb = KeyBuilder::stored_field_value(segment, doc_id, field_id.0, &value_type);
+            try!(write_batch.put(&kb.key(), &positions_bytes));
+        }
+
         // Write statistics
         for (name, value) in builder.statistics.iter() {
             let kb = KeyBuilder::segment_stat(segment, name);
@@ -307,6 +346,7 @@ impl fmt::Debug for RocksDBStore {
     }
 }
 
+#[derive(Debug)]
 pub enum StoredFieldReadError {
     /// The provided FieldId wasn't valid for this index
     InvalidFieldId(FieldId),
@@ -322,6 +362,9 @@ pub enum StoredFieldReadError {
 
     /// An integer/datetime field was read but the value wasn't 8 bytes
     IntegerFieldValueSizeError(usize),
+
+    /// A datetime field was read but the timestamp is out of range
+    DateTimeFieldRangeError(i64),
 }
 
 impl From<rocksdb::Error> for StoredFieldReadError {
@@ -367,11 +410,10 @@ impl<'a> RocksDBReader<'a> {
                         }
                     }
                     FieldType::I64 => {
-                        if value.len() != 8 {
-                            return Err(StoredFieldReadError::IntegerFieldValueSizeError(value.len()));
+                        match decode_sortable_i64(&value) {
+                            Some(value) => Ok(Some(FieldValue::Integer(value))),
+                            None => Err(StoredFieldReadError::IntegerFieldValueSizeError(value.len())),
                         }
-
-                        Ok(Some(FieldValue::Integer(LittleEndian::read_i64(&value))))
                     }
                     FieldType::Boolean => {
                         if value[..] == [b't'] {
@@ -383,16 +425,15 @@ impl<'a> RocksDBReader<'a> {
                         }
                     }
                     FieldType::DateTime => {
-                        if value.len() != 8 {
-                            return Err(StoredFieldReadError::IntegerFieldValueSizeError(value.len()))
+                        let timestamp_with_micros = match decode_sortable_i64(&value) {
+                            Some(value) => value,
+                            None => return Err(StoredFieldReadError::IntegerFieldValueSizeError(value.len())),
+                        };
+
+                        match micros_to_datetime(timestamp_with_micros) {
+                            Some(datetime) => Ok(Some(FieldValue::DateTime(datetime))),
+                            None => Err(StoredFieldReadError::DateTimeFieldRangeError(timestamp_with_micros)),
                         }
-
-                        let timestamp_with_micros = LittleEndian::read_i64(&value);
-                        let timestamp = timestamp_with_micros / 1000000;
-                        let micros = timestamp_with_micros % 1000000;
-                        let nanos = micros * 1000;
-                        let datetime = NaiveDateTime::from_timestamp(timestamp, nanos as u32);
-                        Ok(Some(FieldValue::DateTime(DateTime::from_utc(datetime, Utc))))
                     }
                 }
             }
@@ -405,17 +446,31 @@ impl<'a> RocksDBReader<'a> {
 mod tests {
     use std::fs::remove_dir_all;
     use std::path::Path;
+    use std::ops::Bound;
 
     use rocksdb::DB;
     use fnv::FnvHashMap;
-    use kite::{Term, Token, Document};
+    use kite::{Term, TermId, Token, Document, DocId};
+    use kite::segment::SegmentId;
     use kite::document::FieldValue;
-    use kite::schema::{FieldType, FIELD_INDEXED, FIELD_STORED};
+    use kite::schema::{FieldId, FieldType, FIELD_INDEXED, FIELD_STORED, FIELD_POSITIONS, FIELD_TRIE};
+    use kite::segment::Segment;
     use kite::query::Query;
     use kite::query::term_scorer::TermScorer;
+    use kite::query::multi_term_selector::MultiTermSelector;
+    use kite::query::span::SpanQuery;
+    use kite::query::intervals::Intervals;
     use kite::collectors::top_score::TopScoreCollector;
+    use kite::collectors::total_count::TotalCountCollector;
+    use kite::query::parser::QueryParser;
+    use kite::analysis::{AnalyzerConfig, TokenizerConfig, TokenFilterConfig, Language};
+
+    use roaring::RoaringBitmap;
+    use byteorder::{ByteOrder, LittleEndian};
 
     use super::RocksDBStore;
+    use key_builder::KeyBuilder;
+    use segment::RocksDBSegment;
 
     fn remove_dir_all_ignore_error<P: AsRef<Path>>(path: P) {
         match remove_dir_all(&path) {
@@ -450,25 +505,25 @@ mod tests {
 
     fn make_test_store(path: &str) -> RocksDBStore {
         let mut store = RocksDBStore::create(path).unwrap();
-        let title_field = store.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED).unwrap();
-        let body_field = store.add_field("body".to_string(), FieldType::Text, FIELD_INDEXED).unwrap();
-        let pk_field = store.add_field("pk".to_string(), FieldType::I64, FIELD_STORED).unwrap();
+        let title_field = store.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED, None).unwrap();
+        let body_field = store.add_field("body".to_string(), FieldType::Text, FIELD_INDEXED, None).unwrap();
+        let pk_field = store.add_field("pk".to_string(), FieldType::I64, FIELD_STORED, None).unwrap();
 
 
         let mut indexed_fields = FnvHashMap::default();
         indexed_fields.insert(
             title_field,
             vec![
-                Token { term: Term::from_string("hello"), position: 1 },
-                Token { term: Term::from_string("world"), position: 2 },
+                Token { term: Term::from_string("hello"), position: 1, byte_offsets: None, payload: None },
+                Token { term: Term::from_string("world"), position: 2, byte_offsets: None, payload: None },
             ].into()
         );
         indexed_fields.insert(
             body_field,
             vec![
-                Token { term: Term::from_string("lorem"), position: 1 },
-                Token { term: Term::from_string("ipsum"), position: 2 },
-                Token { term: Term::from_string("dolar"), position: 3 },
+                Token { term: Term::from_string("lorem"), position: 1, byte_offsets: None, payload: None },
+                Token { term: Term::from_string("ipsum"), position: 2, byte_offsets: None, payload: None },
+                Token { term: Term::from_string("dolar"), position: 3, byte_offsets: None, payload: None },
             ].into()
         );
 
@@ -488,16 +543,16 @@ mod tests {
         indexed_fields.insert(
             title_field,
             vec![
-                Token { term: Term::from_string("howdy"), position: 1 },
-                Token { term: Term::from_string("partner"), position: 2 },
+                Token { term: Term::from_string("howdy"), position: 1, byte_offsets: None, payload: None },
+                Token { term: Term::from_string("partner"), position: 2, byte_offsets: None, payload: None },
             ].into()
         );
         indexed_fields.insert(
             body_field,
             vec![
-                Token { term: Term::from_string("lorem"), position: 1 },
-                Token { term: Term::from_string("ipsum"), position: 2 },
-                Token { term: Term::from_string("dolar"), position: 3 },
+                Token { term: Term::from_string("lorem"), position: 1, byte_offsets: None, payload: None },
+                Token { term: Term::from_string("ipsum"), position: 2, byte_offsets: None, payload: None },
+                Token { term: Term::from_string("dolar"), position: 3, byte_offsets: None, payload: None },
             ].into()
         );
 
@@ -586,4 +641,593 @@ mod tests {
         let docs = collector.into_sorted_vec();
         println!("{:?}", docs);
     }
+
+    #[test]
+    fn test_field_analyzer() {
+        remove_dir_all_ignore_error("test_indices/test_field_analyzer");
+
+        {
+            let mut store = RocksDBStore::create("test_indices/test_field_analyzer").unwrap();
+            store.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED, Some(AnalyzerConfig::new(TokenizerConfig::Standard))).unwrap();
+        }
+
+        // Analyzer must survive reopening the store
+        let store = RocksDBStore::open("test_indices/test_field_analyzer").unwrap();
+        let title_field = store.schema().get_field_by_name("title").unwrap();
+        let title_field_info = store.schema().get(&title_field).unwrap();
+        assert_eq!(title_field_info.analyzer, Some(AnalyzerConfig::new(TokenizerConfig::Standard)));
+
+        let mut values = FnvHashMap::default();
+        values.insert(title_field, FieldValue::String("Hello World".to_string()));
+        let doc = Document::from_field_values(store.schema(), "test_doc".to_string(), values).unwrap();
+        store.insert_or_update_document(&doc).unwrap();
+
+        let index_reader = store.reader();
+        let analyzer = title_field_info.build_analyzer();
+
+        let mut collector = TotalCountCollector::new();
+        index_reader.search(&mut collector, &Query::match_text(title_field, &analyzer, "Hello")).unwrap();
+        assert_eq!(collector.get_total_count(), 1);
+
+        // Field analyzer doesn't lowercase
+        let mut collector = TotalCountCollector::new();
+        index_reader.search(&mut collector, &Query::match_text(title_field, &analyzer, "hello")).unwrap();
+        assert_eq!(collector.get_total_count(), 0);
+    }
+
+    #[test]
+    fn test_term_positions() {
+        remove_dir_all_ignore_error("test_indices/test_term_positions");
+
+        let mut store = RocksDBStore::create("test_indices/test_term_positions").unwrap();
+        let title_field = store.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED | FIELD_POSITIONS, None).unwrap();
+        let body_field = store.add_field("body".to_string(), FieldType::Text, FIELD_INDEXED, None).unwrap();
+
+        for (key, text) in vec![("a", "hello world"), ("b", "world hello world")] {
+            let mut values = FnvHashMap::default();
+            values.insert(title_field, FieldValue::String(text.to_string()));
+            values.insert(body_field, FieldValue::String(text.to_string()));
+            let doc = Document::from_field_values(store.schema(), key.to_string(), values).unwrap();
+            store.insert_or_update_document(&doc).unwrap();
+        }
+
+        let world = store.term_dictionary.get(&Term::from_string("world")).unwrap();
+
+        {
+            let reader = store.reader();
+            let segment = RocksDBSegment::new(&reader, 2);
+            let positions = segment.load_term_positions(0, title_field, world).unwrap();
+            assert_eq!(positions, Some(vec![1, 3].into_iter().collect::<RoaringBitmap>()));
+
+            // Field isn't indexed with positions
+            assert_eq!(segment.load_term_positions(0, body_field, world).unwrap(), None);
+        }
+
+        // Positions must follow the documents into the merged segment
+        let merged_segment = store.merge_segments(&vec![1, 2]).unwrap();
+        store.purge_segments(&vec![1, 2]).unwrap();
+
+        let reader = store.reader();
+        let segment = RocksDBSegment::new(&reader, merged_segment);
+        assert_eq!(segment.load_term_positions(0, title_field, world).unwrap(), Some(vec![2].into_iter().collect::<RoaringBitmap>()));
+        assert_eq!(segment.load_term_positions(1, title_field, world).unwrap(), Some(vec![1, 3].into_iter().collect::<RoaringBitmap>()));
+
+        // And be removed from the purged ones
+        let old_segment = RocksDBSegment::new(&reader, 2);
+        assert_eq!(old_segment.load_term_positions(0, title_field, world).unwrap(), None);
+    }
+
+    #[test]
+    fn test_phrase_query() {
+        remove_dir_all_ignore_error("test_indices/test_phrase_query");
+
+        let mut store = RocksDBStore::create("test_indices/test_phrase_query").unwrap();
+        let title_field = store.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED | FIELD_POSITIONS, None).unwrap();
+        let body_field = store.add_field("body".to_string(), FieldType::Text, FIELD_INDEXED, None).unwrap();
+
+        let docs = vec![
+            ("a", "The quick brown fox"),
+            ("b", "The brown quick fox"),
+            ("c", "A quick brown fox and a quick brown dog"),
+        ];
+
+        for (key, text) in docs {
+            let mut values = FnvHashMap::default();
+            values.insert(title_field, FieldValue::String(text.to_string()));
+            values.insert(body_field, FieldValue::String(text.to_string()));
+            let doc = Document::from_field_values(store.schema(), key.to_string(), values).unwrap();
+            store.insert_or_update_document(&doc).unwrap();
+        }
+
+        let index_reader = store.reader();
+        let quick_brown = vec![Term::from_string("quick"), Term::from_string("brown")];
+
+        let mut collector = TopScoreCollector::new(10);
+        index_reader.search(&mut collector, &Query::phrase(title_field, quick_brown.clone())).unwrap();
+        let docs = collector.into_sorted_vec();
+        assert_eq!(docs.len(), 2);
+
+        // The document containing the phrase twice should be first
+        assert_eq!(docs[0].doc_id(), DocId(SegmentId(3), 0).as_u64());
+        assert!(docs[0].score().unwrap() > docs[1].score().unwrap());
+
+        // Terms in the wrong order don't match, even with slop
+        let mut collector = TotalCountCollector::new();
+        index_reader.search(&mut collector, &Query::Phrase {
+            field: title_field,
+            terms: vec![(0, Term::from_string("fox")), (1, Term::from_string("the"))],
+            slop: 10,
+            scorer: TermScorer::default(),
+        }).unwrap();
+        assert_eq!(collector.get_total_count(), 0);
+
+        // Without slop, the terms must be next to each other
+        let mut collector = TotalCountCollector::new();
+        index_reader.search(&mut collector, &Query::phrase(title_field, vec![Term::from_string("quick"), Term::from_string("fox")])).unwrap();
+        assert_eq!(collector.get_total_count(), 1);
+
+        // Slop allows other terms in between
+        let mut collector = TotalCountCollector::new();
+        index_reader.search(&mut collector, &Query::Phrase {
+            field: title_field,
+            terms: vec![(0, Term::from_string("quick")), (1, Term::from_string("fox"))],
+            slop: 1,
+            scorer: TermScorer::default(),
+        }).unwrap();
+        assert_eq!(collector.get_total_count(), 3);
+
+        // Gaps between the terms must match exactly
+        let mut collector = TotalCountCollector::new();
+        index_reader.search(&mut collector, &Query::Phrase {
+            field: title_field,
+            terms: vec![(0, Term::from_string("quick")), (2, Term::from_string("fox"))],
+            slop: 0,
+            scorer: TermScorer::default(),
+        }).unwrap();
+        assert_eq!(collector.get_total_count(), 2);
+
+        // Fields without positions never match
+        let mut collector = TotalCountCollector::new();
+        index_reader.search(&mut collector, &Query::phrase(body_field, quick_brown)).unwrap();
+        assert_eq!(collector.get_total_count(), 0);
+    }
+
+    #[test]
+    fn test_parsed_phrase_with_stopwords() {
+        remove_dir_all_ignore_error("test_indices/test_parsed_phrase_with_stopwords");
+
+        let mut store = RocksDBStore::create("test_indices/test_parsed_phrase_with_stopwords").unwrap();
+        let analyzer = AnalyzerConfig::standard().filter(TokenFilterConfig::Stop { language: Some(Language::English), words: Vec::new() });
+        let title_field = store.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED | FIELD_POSITIONS, Some(analyzer)).unwrap();
+
+        let docs = vec![
+            ("a", "Bank of America"),
+            ("b", "Bank America Corporation"),
+        ];
+
+        for (key, text) in docs {
+            let mut values = FnvHashMap::default();
+            values.insert(title_field, FieldValue::String(text.to_string()));
+            let doc = Document::from_field_values(store.schema(), key.to_string(), values).unwrap();
+            store.insert_or_update_document(&doc).unwrap();
+        }
+
+        let index_reader = store.reader();
+        let query = QueryParser::new(store.schema(), title_field).parse("\"bank of america\"").unwrap();
+
+        // Only the document with a stopword between the terms matches
+        let mut collector = TopScoreCollector::new(10);
+        index_reader.search(&mut collector, &query).unwrap();
+        let docs = collector.into_sorted_vec();
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].doc_id(), DocId(SegmentId(1), 0).as_u64());
+    }
+
+    #[test]
+    fn test_span_query() {
+        remove_dir_all_ignore_error("test_indices/test_span_query");
+
+        let mut store = RocksDBStore::create("test_indices/test_span_query").unwrap();
+        let title_field = store.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED | FIELD_POSITIONS, None).unwrap();
+
+        let docs = vec![
+            ("a", "The quick brown fox"),
+            ("b", "The fox was quick"),
+            ("c", "A brown dog chased the quick red fox"),
+        ];
+
+        for (key, text) in docs {
+            let mut values = FnvHashMap::default();
+            values.insert(title_field, FieldValue::String(text.to_string()));
+            let doc = Document::from_field_values(store.schema(), key.to_string(), values).unwrap();
+            store.insert_or_update_document(&doc).unwrap();
+        }
+
+        let index_reader = store.reader();
+        let count = |span: SpanQuery| {
+            let mut collector = TotalCountCollector::new();
+            index_reader.search(&mut collector, &Query::span(title_field, span)).unwrap();
+            collector.get_total_count()
+        };
+        let term = |term: &str| SpanQuery::term(Term::from_string(term));
+
+        assert_eq!(count(term("fox")), 3);
+
+        // Near
+        assert_eq!(count(SpanQuery::near(vec![term("quick"), term("fox")], 1, true)), 2);
+        assert_eq!(count(SpanQuery::near(vec![term("quick"), term("fox")], 1, false)), 3);
+        assert_eq!(count(SpanQuery::near(vec![term("quick"), term("fox")], 0, true)), 0);
+
+        // Or
+        assert_eq!(count(SpanQuery::near(vec![term("quick"), SpanQuery::or(vec![term("brown"), term("red")])], 0, true)), 2);
+
+        // Not
+        assert_eq!(count(SpanQuery::not(SpanQuery::near(vec![term("quick"), term("fox")], 1, true), term("red"))), 1);
+
+        // First
+        assert_eq!(count(SpanQuery::first(term("fox"), 2)), 1);
+        assert_eq!(count(SpanQuery::first(term("brown"), 3)), 2);
+        assert_eq!(count(SpanQuery::first(term("fox"), u32::MAX)), 3);
+
+        // Containing
+        assert_eq!(count(SpanQuery::containing(SpanQuery::near(vec![term("the"), term("fox")], 5, true), term("brown"))), 1);
+
+        // Unknown terms never match
+        assert_eq!(count(SpanQuery::or(vec![term("wolf")])), 0);
+    }
+
+    #[test]
+    fn test_intervals_query() {
+        remove_dir_all_ignore_error("test_indices/test_intervals_query");
+
+        let mut store = RocksDBStore::create("test_indices/test_intervals_query").unwrap();
+        let title_field = store.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED | FIELD_POSITIONS, None).unwrap();
+
+        let docs = vec![
+            ("a", "The quick brown fox"),
+            ("b", "The fox was very quick"),
+            ("c", "A quick fox"),
+        ];
+
+        for (key, text) in docs {
+            let mut values = FnvHashMap::default();
+            values.insert(title_field, FieldValue::String(text.to_string()));
+            let doc = Document::from_field_values(store.schema(), key.to_string(), values).unwrap();
+            store.insert_or_update_document(&doc).unwrap();
+        }
+
+        let index_reader = store.reader();
+        let term = |term: &str| Intervals::term(Term::from_string(term));
+        let search = |intervals: Intervals| {
+            let mut collector = TopScoreCollector::new(10);
+            index_reader.search(&mut collector, &Query::intervals(title_field, intervals)).unwrap();
+            collector.into_sorted_vec()
+        };
+
+        assert_eq!(search(Intervals::ordered(vec![term("quick"), term("fox")])).len(), 2);
+        assert_eq!(search(Intervals::unordered(vec![term("quick"), term("fox")])).len(), 3);
+        assert_eq!(search(Intervals::max_gaps(Intervals::unordered(vec![term("quick"), term("fox")]), 1)).len(), 2);
+        assert_eq!(search(Intervals::not_containing(Intervals::ordered(vec![term("quick"), term("fox")]), term("brown"))).len(), 1);
+        assert_eq!(search(Intervals::ordered(vec![term("quick"), term("wolf")])).len(), 0);
+
+        // Tighter matches score higher
+        let docs = search(Intervals::unordered(vec![term("quick"), term("fox")]));
+        assert_eq!(docs[0].doc_id(), DocId(SegmentId(3), 0).as_u64());
+        assert_eq!(docs[2].doc_id(), DocId(SegmentId(2), 0).as_u64());
+        assert!(docs[0].score().unwrap() > docs[1].score().unwrap());
+        assert!(docs[1].score().unwrap() > docs[2].score().unwrap());
+    }
+
+    #[test]
+    fn test_fuzzy_query() {
+        remove_dir_all_ignore_error("test_indices/test_fuzzy_query");
+
+        let mut store = RocksDBStore::create("test_indices/test_fuzzy_query").unwrap();
+        let title_field = store.add_field("title".to_string(), FieldType::Text, FIELD_INDEXED, None).unwrap();
+
+        let docs = vec![
+            ("a", "The quick brown fox"),
+            ("b", "A qiuck brown dog"),
+            ("c", "Quack said the duck"),
+            ("d", "Quit it"),
+        ];
+
+        for (key, text) in docs {
+            let mut values = FnvHashMap::default();
+            values.insert(title_field, FieldValue::String(text.to_string()));
+            let doc = Document::from_field_values(store.schema(), key.to_string(), values).unwrap();
+            store.insert_or_update_document(&doc).unwrap();
+        }
+
+        let index_reader = store.reader();
+        let search = |term_selector: MultiTermSelector| {
+            let mut collector = TotalCountCollector::new();
+            index_reader.search(&mut collector, &Query::MultiTerm {
+                field: title_field,
+                term_selector: term_selector,
+                scorer: TermScorer::default(),
+            }).unwrap();
+            collector.get_total_count()
+        };
+
+        assert_eq!(search(MultiTermSelector::fuzzy("quick", 0)), 1);
+        assert_eq!(search(MultiTermSelector::fuzzy("quick", 1)), 3);
+        assert_eq!(search(MultiTermSelector::fuzzy("quick", 2)), 4);
+        assert_eq!(search(MultiTermSelector::Fuzzy {
+            term: "quick".to_string(),
+            max_edits: 1,
+            prefix_length: 0,
+            transpositions: false,
+        }), 2);
+        assert_eq!(search(MultiTermSelector::Fuzzy {
+            term: "quick".to_string(),
+            max_edits: 2,
+            prefix_length: 3,
+            transpositions: true,
+        }), 2);
+        assert_eq!(search(MultiTermSelector::Prefix("qu".to_string())), 3);
+    }
+
+    #[test]
+    fn test_pattern_query() {
+        remove_dir_all_ignore_error("test_indices/test_pattern_query");
+
+        let mut store = RocksDBStore::create("test_indices/test_pattern_query").unwrap();
+        let request_field = store.add_field("request".to_string(), FieldType::PlainString, FIELD_INDEXED, None).unwrap();
+        let error_field = store.add_field("error".to_string(), FieldType::PlainString, FIELD_INDEXED, None).unwrap();
+
+        let docs = vec![
+            ("a", "req-123-eu", "err_1"),
+            ("b", "req-456-us", "error_22"),
+            ("c", "req--eu", "error_"),
+            ("d", "res-789-eu", "warning_3"),
+        ];
+
+        for (key, request, error) in docs {
+            let mut values = FnvHashMap::default();
+            values.insert(request_field, FieldValue::String(request.to_string()));
+            values.insert(error_field, FieldValue::String(error.to_string()));
+            let doc = Document::from_field_values(store.schema(), key.to_string(), values).unwrap();
+            store.insert_or_update_document(&doc).unwrap();
+        }
+
+        let index_reader = store.reader();
+        let search = |field, term_selector: MultiTermSelector| {
+            let mut collector = TotalCountCollector::new();
+            index_reader.search(&mut collector, &Query::MultiTerm {
+                field: field,
+                term_selector: term_selector,
+                scorer: TermScorer::default(),
+            }).unwrap();
+            collector.get_total_count()
+        };
+
+        assert_eq!(search(request_field, MultiTermSelector::Wildcard("req-*-eu".to_string())), 2);
+        assert_eq!(search(request_field, MultiTermSelector::Wildcard("re?-*".to_string())), 4);
+        assert_eq!(search(request_field, MultiTermSelector::Wildcard("req-???-*".to_string())), 2);
+        assert_eq!(search(error_field, MultiTermSelector::Regex("err(or)?_[0-9]+".to_string())), 2);
+        assert_eq!(search(error_field, MultiTermSelector::Regex("[a-z]+_[0-9]".to_string())), 2);
+
+        // Patterns must match whole terms
+        assert_eq!(search(error_field, MultiTermSelector::Regex("err".to_string())), 0);
+
+        // Invalid patterns don't match anything
+        assert_eq!(search(error_field, MultiTermSelector::Regex("err(or".to_string())), 0);
+    }
+
+    #[test]
+    fn test_range_query() {
+        remove_dir_all_ignore_error("test_indices/test_range_query");
+
+        let mut store = RocksDBStore::create("test_indices/test_range_query").unwrap();
+        let price_field = store.add_field("price".to_string(), FieldType::I64, FIELD_INDEXED, None).unwrap();
+        let published_field = store.add_field("published".to_string(), FieldType::DateTime, FIELD_INDEXED, None).unwrap();
+        let slug_field = store.add_field("slug".to_string(), FieldType::PlainString, FIELD_INDEXED, None).unwrap();
+
+        let docs = vec![
+            ("a", -20, "2016-06-01T00:00:00Z", "apple"),
+            ("b", -1, "2016-07-01T00:00:00Z", "banana"),
+            ("c", 0, "2016-07-20T12:00:00Z", "cherry"),
+            ("d", 10, "2016-07-22T23:59:59Z", "damson"),
+            ("e", 35, "2016-07-23T00:00:00Z", "elderberry"),
+            ("f", 50, "2016-07-24T00:00:00Z", "fig"),
+            ("g", 1000, "2017-01-01T00:00:00Z", "grape"),
+        ];
+
+        for (key, price, published, slug) in docs {
+            let mut values = FnvHashMap::default();
+            values.insert(price_field, FieldValue::Integer(price));
+            values.insert(published_field, FieldValue::DateTime(published.parse().unwrap()));
+            values.insert(slug_field, FieldValue::String(slug.to_string()));
+            let doc = Document::from_field_values(store.schema(), key.to_string(), values).unwrap();
+            store.insert_or_update_document(&doc).unwrap();
+        }
+
+        let index_reader = store.reader();
+        let search = |query: Query| {
+            let mut collector = TotalCountCollector::new();
+            index_reader.search(&mut collector, &query).unwrap();
+            collector.get_total_count()
+        };
+        let int = |value| Term::from_integer(value);
+        let date = |value: &str| Term::from_datetime(&value.parse().unwrap());
+
+        assert_eq!(search(Query::range(price_field, Bound::Included(int(10)), Bound::Included(int(50)))), 3);
+        assert_eq!(search(Query::range(price_field, Bound::Excluded(int(10)), Bound::Excluded(int(50)))), 1);
+        assert_eq!(search(Query::range(price_field, Bound::Included(int(-20)), Bound::Excluded(int(0)))), 2);
+        assert_eq!(search(Query::range(price_field, Bound::Unbounded, Bound::Included(int(0)))), 3);
+        assert_eq!(search(Query::range(price_field, Bound::Excluded(int(0)), Bound::Unbounded)), 4);
+        assert_eq!(search(Query::range(price_field, Bound::Unbounded, Bound::Unbounded)), 7);
+        assert_eq!(search(Query::range(price_field, Bound::Included(int(11)), Bound::Included(int(34)))), 0);
+        assert_eq!(search(Query::range(price_field, Bound::Included(int(50)), Bound::Included(int(10)))), 0);
+        assert_eq!(search(Query::range(price_field, Bound::Excluded(int(10)), Bound::Excluded(int(10)))), 0);
+
+        assert_eq!(search(Query::range(published_field, Bound::Included(date("2016-07-01T00:00:00Z")), Bound::Excluded(date("2016-07-23T00:00:00Z")))), 3);
+        assert_eq!(search(Query::range(published_field, Bound::Excluded(date("2016-07-23T00:00:00Z")), Bound::Unbounded)), 2);
+
+        assert_eq!(search(Query::range(slug_field, Bound::Included(Term::from_string("b")), Bound::Excluded(Term::from_string("e")))), 3);
+        assert_eq!(search(Query::range(slug_field, Bound::Excluded(Term::from_string("fig")), Bound::Unbounded)), 1);
+
+        // Ranges only match terms in the field being searched
+        assert_eq!(search(Query::range(slug_field, Bound::Unbounded, Bound::Unbounded)), 7);
+        assert_eq!(search(Query::range(price_field, Bound::Included(int(0)), Bound::Unbounded).filter(
+            Query::range(slug_field, Bound::Unbounded, Bound::Excluded(Term::from_string("e"))))), 2);
+    }
+
+    #[test]
+    fn test_trie_range_query() {
+        remove_dir_all_ignore_error("test_indices/test_trie_range_query");
+
+        let mut store = RocksDBStore::create("test_indices/test_trie_range_query").unwrap();
+        let plain_field = store.add_field("plain".to_string(), FieldType::I64, FIELD_INDEXED, None).unwrap();
+        let trie_field = store.add_field("trie".to_string(), FieldType::I64, FIELD_INDEXED | FIELD_TRIE, None).unwrap();
+        let values = (0..100).map(|i| i * 1237 - 60000).collect::<Vec<i64>>();
+
+        for (i, value) in values.iter().enumerate() {
+            let mut field_values = FnvHashMap::default();
+            field_values.insert(plain_field, FieldValue::Integer(*value));
+            field_values.insert(trie_field, FieldValue::Integer(*value));
+            let doc = Document::from_field_values(store.schema(), i.to_string(), field_values).unwrap();
+            store.insert_or_update_document(&doc).unwrap();
+        }
+
+        let index_reader = store.reader();
+        let search = |query: Query| {
+            let mut collector = TotalCountCollector::new();
+            index_reader.search(&mut collector, &query).unwrap();
+            collector.get_total_count()
+        };
+
+        let ranges = vec![
+            (Bound::Included(-60000), Bound::Included(62463)),
+            (Bound::Excluded(-60000), Bound::Excluded(62463)),
+            (Bound::Included(-1000), Bound::Included(1000)),
+            (Bound::Included(0), Bound::Included(65535)),
+            (Bound::Included(-256), Bound::Excluded(256)),
+            (Bound::Unbounded, Bound::Included(-30000)),
+            (Bound::Included(5), Bound::Unbounded),
+            (Bound::Unbounded, Bound::Unbounded),
+            (Bound::Included(10), Bound::Included(-10)),
+        ];
+
+        for (lower, upper) in ranges {
+            let expected = values.iter().filter(|value| {
+                let above_lower = match lower {
+                    Bound::Included(lower) => **value >= lower,
+                    Bound::Excluded(lower) => **value > lower,
+                    Bound::Unbounded => true,
+                };
+                let below_upper = match upper {
+                    Bound::Included(upper) => **value <= upper,
+                    Bound::Excluded(upper) => **value < upper,
+                    Bound::Unbounded => true,
+                };
+                above_lower && below_upper
+            }).count() as u64;
+
+            let to_term_bound = |bound: Bound<i64>| match bound {
+                Bound::Included(value) => Bound::Included(Term::from_integer(value)),
+                Bound::Excluded(value) => Bound::Excluded(Term::from_integer(value)),
+                Bound::Unbounded => Bound::Unbounded,
+            };
+
+            assert_eq!(search(Query::range(plain_field, to_term_bound(lower), to_term_bound(upper))), expected);
+            assert_eq!(search(Query::range(trie_field, to_term_bound(lower), to_term_bound(upper))), expected);
+        }
+
+        // Term queries only match the full precision terms
+        assert_eq!(search(Query::term(trie_field, Term::from_integer(-60000))), 1);
+
+        // The lower precision terms don't count towards the length of the field
+        let segment = RocksDBSegment::new(&index_reader, 1);
+        let total_tokens = |field_id: FieldId| segment.load_statistic(&KeyBuilder::segment_stat_total_field_tokens_stat_name(field_id.0)).unwrap();
+        assert_eq!(total_tokens(trie_field), Some(1));
+        assert_eq!(total_tokens(trie_field), total_tokens(plain_field));
+    }
+
+    #[test]
+    fn test_upgrade_sortable_integers() {
+        remove_dir_all_ignore_error("test_indices/test_upgrade_sortable_integers");
+
+        {
+            let mut store = RocksDBStore::create("test_indices/test_upgrade_sortable_integers").unwrap();
+            let count_field = store.add_field("count".to_string(), FieldType::I64, FIELD_INDEXED | FIELD_STORED, None).unwrap();
+
+            // Write the documents the way version 1 stores encoded integers (little-endian)
+            for (segment, &(key, count)) in [("a", -5i64), ("b", 300i64)].iter().enumerate() {
+                let mut count_bytes = [0; 8];
+                LittleEndian::write_i64(&mut count_bytes, count);
+
+                // The first document has the term twice so it gets a term frequency
+                let mut tokens = vec![
+                    Token { term: Term::from_bytes(&count_bytes), position: 1, byte_offsets: None, payload: None },
+                ];
+                if segment == 0 {
+                    tokens.push(Token { term: Term::from_bytes(&count_bytes), position: 2, byte_offsets: None, payload: None });
+                }
+
+                let mut indexed_fields = FnvHashMap::default();
+                indexed_fields.insert(count_field, tokens.into());
+
+                store.insert_or_update_document(&Document {
+                    key: key.to_string(),
+                    indexed_fields: indexed_fields,
+                    stored_fields: FnvHashMap::default(),
+                }).unwrap();
+
+                let kb = KeyBuilder::stored_field_value(segment as u32 + 1, 0, count_field.0, b"val");
+                store.db.put(kb.key(), &count_bytes).unwrap();
+            }
+
+            store.db.delete(b".format_version").unwrap();
+        }
+
+        let old_term = {
+            let mut count_bytes = [0; 8];
+            LittleEndian::write_i64(&mut count_bytes, -5);
+            Term::from_bytes(&count_bytes)
+        };
+
+        let store = RocksDBStore::open("test_indices/test_upgrade_sortable_integers").unwrap();
+        let count_field = store.schema().get_field_by_name("count").unwrap();
+
+        // The term frequency must be moved to the new term
+        let old_term_id = store.term_dictionary.get(&old_term).unwrap();
+        let new_term_id = store.term_dictionary.get(&Term::from_integer(-5)).unwrap();
+        {
+            let reader = store.reader();
+            let segment = RocksDBSegment::new(&reader, 1);
+            let term_frequency = |term_id: TermId| {
+                let value_type = KeyBuilder::stored_field_value_term_frequency_value_type(term_id.0);
+                segment.load_stored_field_value_raw(0, count_field, &value_type).unwrap().map(|value| LittleEndian::read_i64(&value))
+            };
+
+            assert_eq!(term_frequency(new_term_id), Some(2));
+            assert_eq!(term_frequency(old_term_id), None);
+        }
+
+        let index_reader = store.reader();
+        let search = |count| {
+            let mut collector = TopScoreCollector::new(10);
+            index_reader.search(&mut collector, &Query::Term {
+                field: count_field,
+                term: Term::from_integer(count),
+                scorer: TermScorer::default(),
+            }).unwrap();
+            collector.into_sorted_vec().iter().map(|doc| doc.doc_id()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(search(-5), vec![DocId(SegmentId(1), 0).as_u64()]);
+        assert_eq!(search(300), vec![DocId(SegmentId(2), 0).as_u64()]);
+
+        match index_reader.read_stored_field(count_field, DocId(SegmentId(1), 0)) {
+            Ok(Some(FieldValue::Integer(-5))) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        match index_reader.read_stored_field(count_field, DocId(SegmentId(2), 0)) {
+            Ok(Some(FieldValue::Integer(300))) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
 }
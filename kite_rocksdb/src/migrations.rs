@@ -0,0 +1,212 @@
+//! Upgrades stores that were created by older versions of kite_rocksdb
+//!
+//! The version of the on-disk format is kept in the ".format_version" key. Stores
+//! that were created before this key was introduced are at version 1.
+//!
+//! Version 2 changed the encoding of integer and datetime terms and stored values
+//! from little-endian to a big-endian encoding with the sign bit flipped so they
+//! sort in numeric order.
+
+use std::str;
+
+use rocksdb::{self, DB, WriteBatch};
+use kite::{Term, TermId};
+use kite::term::encode_sortable_i64;
+use kite::schema::FieldType;
+use byteorder::{ByteOrder, LittleEndian};
+use fnv::{FnvHashMap, FnvHashSet};
+
+use RocksDBStore;
+use key_builder::KeyBuilder;
+
+/// The format version of stores created by this version of kite_rocksdb
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Writes the current format version to the store
+pub fn write_format_version(db: &DB) -> Result<(), rocksdb::Error> {
+    db.put(b".format_version", FORMAT_VERSION.to_string().as_bytes())
+}
+
+/// Reads the format version of the store
+fn read_format_version(db: &DB) -> Result<u32, rocksdb::Error> {
+    match try!(db.get(b".format_version")) {
+        Some(format_version) => Ok(format_version.to_utf8().unwrap().parse::<u32>().unwrap()),
+        None => Ok(1),
+    }
+}
+
+/// Converts a little-endian encoded integer into the sortable encoding
+fn upgrade_integer_bytes(bytes: &[u8]) -> Option<Vec<u8>> {
+    if bytes.len() != 8 {
+        return None;
+    }
+
+    Some(encode_sortable_i64(LittleEndian::read_i64(bytes)))
+}
+
+impl RocksDBStore {
+    /// Upgrades the store to the current format version
+    pub fn upgrade_format(&self) -> Result<(), String> {
+        let format_version = try!(read_format_version(&self.db));
+
+        if format_version > FORMAT_VERSION {
+            return Err(format!("store format version {} is newer than the supported version {}", format_version, FORMAT_VERSION));
+        }
+
+        if format_version < 2 {
+            try!(self.upgrade_sortable_integers());
+        }
+
+        Ok(())
+    }
+
+    /// Re-encodes the terms and stored values of integer and datetime fields
+    ///
+    /// Each re-encoded term gets a new TermId, so the term directories, term document
+    /// frequencies, term frequencies and term positions of these fields are moved to
+    /// the new TermIds.
+    fn upgrade_sortable_integers(&self) -> Result<(), rocksdb::Error> {
+        let fields = self.schema.iter()
+            .filter(|&(_, field_info)| field_info.field_type == FieldType::I64 || field_info.field_type == FieldType::DateTime)
+            .map(|(field_id, _)| field_id.0)
+            .collect::<FnvHashSet<u32>>();
+
+        // The old and new keys may overlap (a new term can have the same bytes as an
+        // old one) so all of the deletes are written to the batch before the puts
+        let mut deletes: Vec<Vec<u8>> = Vec::new();
+        let mut puts: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+
+        if !fields.is_empty() {
+            let terms = self.term_dictionary.terms_by_id();
+            let mut term_id_map: FnvHashMap<u32, u32> = FnvHashMap::default();
+
+            let mut upgrade_term_id = |term_id: u32| -> Result<u32, rocksdb::Error> {
+                if let Some(new_term_id) = term_id_map.get(&term_id) {
+                    return Ok(*new_term_id);
+                }
+
+                let new_term_id = match terms.get(&TermId(term_id)).and_then(|term| upgrade_integer_bytes(term.as_bytes())) {
+                    Some(new_term_bytes) => try!(self.term_dictionary.get_or_create(&self.db, &Term::from_bytes(&new_term_bytes))).0,
+                    None => term_id,
+                };
+
+                term_id_map.insert(term_id, new_term_id);
+                Ok(new_term_id)
+            };
+
+            // Term directories ("d{field}/{term}/{segment}")
+            let mut iter = self.db.raw_iterator();
+            iter.seek(b"d");
+            while iter.valid() {
+                let k = iter.key().unwrap();
+
+                if k[0] != b'd' {
+                    break;
+                }
+
+                let mut nums_iter = k[1..].split(|b| *b == b'/').map(|s| str::from_utf8(s).unwrap().parse::<u32>().unwrap());
+                let (field, term, segment) = (nums_iter.next().unwrap(), nums_iter.next().unwrap(), nums_iter.next().unwrap());
+
+                if fields.contains(&field) {
+                    let new_term = try!(upgrade_term_id(term));
+
+                    if new_term != term {
+                        let kb = KeyBuilder::segment_dir_list(segment, field, new_term);
+                        deletes.push(k.to_vec());
+                        puts.push((kb.key().to_vec(), iter.value().unwrap().to_vec()));
+                    }
+                }
+
+                iter.next();
+            }
+
+            // Term document frequencies ("s{segment}/tdf-{field}-{term}")
+            let mut iter = self.db.raw_iterator();
+            iter.seek(b"s");
+            while iter.valid() {
+                let k = iter.key().unwrap();
+
+                if k[0] != b's' {
+                    break;
+                }
+
+                let mut parts_iter = k[1..].splitn(2, |b| *b == b'/');
+                let segment = str::from_utf8(parts_iter.next().unwrap()).unwrap().parse::<u32>().unwrap();
+                let stat_name = parts_iter.next().unwrap();
+
+                if stat_name.starts_with(b"tdf-") {
+                    let mut nums_iter = stat_name[4..].split(|b| *b == b'-').map(|s| str::from_utf8(s).unwrap().parse::<u32>().unwrap());
+                    let (field, term) = (nums_iter.next().unwrap(), nums_iter.next().unwrap());
+
+                    if fields.contains(&field) {
+                        let new_term = try!(upgrade_term_id(term));
+
+                        if new_term != term {
+                            let stat_name = KeyBuilder::segment_stat_term_doc_frequency_stat_name(field, new_term);
+                            let kb = KeyBuilder::segment_stat(segment, &stat_name);
+                            deletes.push(k.to_vec());
+                            puts.push((kb.key().to_vec(), iter.value().unwrap().to_vec()));
+                        }
+                    }
+                }
+
+                iter.next();
+            }
+
+            // Stored values, term frequencies and term positions ("v{segment}/{doc}/{field}/{value_type}")
+            let mut iter = self.db.raw_iterator();
+            iter.seek(b"v");
+            while iter.valid() {
+                let k = iter.key().unwrap();
+
+                if k[0] != b'v' {
+                    break;
+                }
+
+                let mut parts_iter = k[1..].split(|b| *b == b'/');
+                let segment = str::from_utf8(parts_iter.next().unwrap()).unwrap().parse::<u32>().unwrap();
+                let doc_id = str::from_utf8(parts_iter.next().unwrap()).unwrap().parse::<u16>().unwrap();
+                let field = str::from_utf8(parts_iter.next().unwrap()).unwrap().parse::<u32>().unwrap();
+                let value_type = parts_iter.next().unwrap();
+
+                if fields.contains(&field) {
+                    if value_type == b"val" {
+                        if let Some(new_value) = upgrade_integer_bytes(&iter.value().unwrap()) {
+                            puts.push((k.to_vec(), new_value));
+                        }
+                    } else if value_type.starts_with(b"tp") || value_type.starts_with(b"tf") {
+                        let term = str::from_utf8(&value_type[2..]).unwrap().parse::<u32>().unwrap();
+                        let new_term = try!(upgrade_term_id(term));
+
+                        if new_term != term {
+                            let value_type = if value_type.starts_with(b"tp") {
+                                KeyBuilder::stored_field_value_term_positions_value_type(new_term)
+                            } else {
+                                KeyBuilder::stored_field_value_term_frequency_value_type(new_term)
+                            };
+
+                            let kb = KeyBuilder::stored_field_value(segment, doc_id, field, &value_type);
+                            deletes.push(k.to_vec());
+                            puts.push((kb.key().to_vec(), iter.value().unwrap().to_vec()));
+                        }
+                    }
+                }
+
+                iter.next();
+            }
+        }
+
+        let mut write_batch = WriteBatch::default();
+
+        for key in deletes.iter() {
+            try!(write_batch.delete(key));
+        }
+
+        for &(ref key, ref value) in puts.iter() {
+            try!(write_batch.put(key, value));
+        }
+
+        try!(write_batch.put(b".format_version", FORMAT_VERSION.to_string().as_bytes()));
+        self.db.write(write_batch)
+    }
+}
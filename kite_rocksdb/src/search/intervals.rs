@@ -0,0 +1,54 @@
+use roaring::RoaringBitmap;
+use fnv::FnvHashMap;
+use kite::Term;
+use kite::schema::FieldId;
+use kite::term::TermId;
+use kite::segment::Segment;
+use kite::query::intervals::{Intervals, Interval};
+
+use RocksDBReader;
+
+/// Looks up the term ids of all the terms in an intervals source
+/// Terms that aren't in the term dictionary are left out
+pub fn plan_intervals_terms(index_reader: &RocksDBReader, intervals: &Intervals) -> FnvHashMap<Term, TermId> {
+    let mut term_ids = FnvHashMap::default();
+
+    for term in intervals.all_terms() {
+        if let Some(term_id) = index_reader.store.term_dictionary.get(term) {
+            term_ids.insert(term.clone(), term_id);
+        }
+    }
+
+    term_ids
+}
+
+/// Finds the documents that could contain a match for the intervals source
+/// This only uses the term directories so the candidates still need checking against positions
+pub fn load_intervals_candidates<S: Segment>(segment: &S, field_id: FieldId, intervals: &Intervals, term_ids: &FnvHashMap<Term, TermId>) -> Result<RoaringBitmap, String> {
+    let mut candidates = RoaringBitmap::new();
+
+    for term in intervals.terms() {
+        if let Some(term_id) = term_ids.get(term) {
+            if let Some(term_directory) = try!(segment.load_term_directory(field_id, *term_id)) {
+                candidates.union_with(&term_directory);
+            }
+        }
+    }
+
+    Ok(candidates)
+}
+
+/// Finds the intervals in a document that match the intervals source
+pub fn load_intervals<S: Segment>(segment: &S, doc_id: u16, field_id: FieldId, intervals: &Intervals, term_ids: &FnvHashMap<Term, TermId>) -> Result<Vec<Interval>, String> {
+    intervals.find_intervals(&mut |term| {
+        match term_ids.get(term) {
+            Some(term_id) => segment.load_term_positions(doc_id, field_id, *term_id),
+            None => Ok(None),
+        }
+    })
+}
+
+/// Sums up the weight of each interval, where an interval with no gaps has a weight of 1
+pub fn intervals_frequency(intervals: &[Interval]) -> f32 {
+    intervals.iter().map(|interval| 1.0f32 / (interval.gaps as f32 + 1.0f32)).sum()
+}
@@ -1,8 +1,13 @@
 mod statistics;
 mod planner;
+mod phrase;
+mod span;
+mod intervals;
+mod range;
 
 use roaring::RoaringBitmap;
 use kite::segment::Segment;
+use kite::schema::FieldId;
 use kite::query::Query;
 use kite::collectors::{Collector, DocumentMatch};
 use byteorder::{ByteOrder, LittleEndian};
@@ -12,6 +17,9 @@ use search::statistics::{StatisticsReader, RocksDBStatisticsReader};
 use search::planner::{SearchPlan, plan_query};
 use search::planner::boolean_query::BooleanQueryOp;
 use search::planner::score_function::{CombinatorScorer, ScoreFunctionOp};
+use search::phrase::{load_phrase_positions, phrase_frequency};
+use search::span::{load_span_candidates, load_spans};
+use search::intervals::{load_intervals_candidates, load_intervals, intervals_frequency};
 
 fn run_boolean_query<S: Segment>(boolean_query: &Vec<BooleanQueryOp>, is_negated: bool, segment: &S) -> Result<RoaringBitmap, String> {
     // Execute boolean query
@@ -27,6 +35,55 @@ fn run_boolean_query<S: Segment>(boolean_query: &Vec<BooleanQueryOp>, is_negated
                     None => stack.push(RoaringBitmap::new()),
                 }
             }
+            BooleanQueryOp::PushPhrase(field_id, ref term_ids, slop) => {
+                // Narrow down the candidates to documents that contain all of the terms
+                let mut candidates: Option<RoaringBitmap> = None;
+                for &(_, term_id) in term_ids.iter() {
+                    let term_directory = try!(segment.load_term_directory(field_id, term_id)).unwrap_or_else(RoaringBitmap::new);
+
+                    candidates = Some(match candidates {
+                        Some(mut candidates) => {
+                            candidates.intersect_with(&term_directory);
+                            candidates
+                        }
+                        None => term_directory,
+                    });
+                }
+
+                // Check the positions of the terms in each candidate
+                let mut doc_id_set = RoaringBitmap::new();
+                for doc_id in candidates.unwrap_or_else(RoaringBitmap::new).iter() {
+                    if let Some(phrase_positions) = try!(load_phrase_positions(segment, doc_id as u16, field_id, term_ids)) {
+                        if phrase_frequency(&phrase_positions, slop) > 0 {
+                            doc_id_set.insert(doc_id);
+                        }
+                    }
+                }
+
+                stack.push(doc_id_set);
+            }
+            BooleanQueryOp::PushSpan(field_id, ref span) => {
+                // Check the spans of each document that could match
+                let mut doc_id_set = RoaringBitmap::new();
+                for doc_id in try!(load_span_candidates(segment, field_id, span)).iter() {
+                    if !try!(load_spans(segment, doc_id as u16, field_id, span)).is_empty() {
+                        doc_id_set.insert(doc_id);
+                    }
+                }
+
+                stack.push(doc_id_set);
+            }
+            BooleanQueryOp::PushIntervals(field_id, ref intervals, ref term_ids) => {
+                // Check the intervals of each document that could match
+                let mut doc_id_set = RoaringBitmap::new();
+                for doc_id in try!(load_intervals_candidates(segment, field_id, intervals, term_ids)).iter() {
+                    if !try!(load_intervals(segment, doc_id as u16, field_id, intervals, term_ids)).is_empty() {
+                        doc_id_set.insert(doc_id);
+                    }
+                }
+
+                stack.push(doc_id_set);
+            }
             BooleanQueryOp::PushDeletionList => {
                     match try!(segment.load_deletion_list()) {
                     Some(doc_id_set) => stack.push(doc_id_set),
@@ -76,6 +133,19 @@ fn run_boolean_query<S: Segment>(boolean_query: &Vec<BooleanQueryOp>, is_negated
     Ok(matches)
 }
 
+fn load_field_length<S: Segment>(doc_id: u16, field_id: FieldId, segment: &S) -> Result<f32, String> {
+    let field_length_raw = try!(segment.load_stored_field_value_raw(doc_id, field_id, b"len"));
+    let field_length = match field_length_raw {
+        Some(value) => {
+            let length_sqrt = (value[0] as f32) / 3.0 + 1.0;
+            length_sqrt * length_sqrt
+        }
+        None => 1.0
+    };
+
+    Ok(field_length)
+}
+
 fn score_doc<S: Segment, R: StatisticsReader>(doc_id: u16, score_function: &Vec<ScoreFunctionOp>, segment: &S, stats: &mut R) -> Result<f32, String> {
     // Execute score function
     let mut stack = Vec::new();
@@ -89,14 +159,7 @@ fn score_doc<S: Segment, R: StatisticsReader>(doc_id: u16, score_function: &Vec<
                         if term_directory.contains(doc_id as u32) {
                             // Read field length
                             // TODO: we only need this for BM25
-                            let field_length_raw = try!(segment.load_stored_field_value_raw(doc_id, field_id, b"len"));
-                            let field_length = match field_length_raw {
-                                Some(value) => {
-                                    let length_sqrt = (value[0] as f32) / 3.0 + 1.0;
-                                    length_sqrt * length_sqrt
-                                }
-                                None => 1.0
-                            };
+                            let field_length = try!(load_field_length(doc_id, field_id, segment));
 
                             // Read term frequency
                             let mut value_type = vec![b't', b'f'];
@@ -116,6 +179,63 @@ fn score_doc<S: Segment, R: StatisticsReader>(doc_id: u16, score_function: &Vec<
                     None => stack.push(0.0f32),
                 }
             }
+            ScoreFunctionOp::PhraseScorer(field_id, ref term_ids, slop, ref scorer) => {
+                let phrase_frequency = match try!(load_phrase_positions(segment, doc_id, field_id, term_ids)) {
+                    Some(phrase_positions) => phrase_frequency(&phrase_positions, slop),
+                    None => 0,
+                };
+
+                if phrase_frequency > 0 {
+                    let field_length = try!(load_field_length(doc_id, field_id, segment));
+
+                    // The phrase frequency is used as the term frequency of each term in the phrase
+                    // and the scores are summed. This weights rare phrases by the rarity of their terms
+                    let mut score = 0.0f32;
+                    for &(_, term_id) in term_ids.iter() {
+                        score += scorer.similarity_model.score(phrase_frequency, field_length, try!(stats.total_tokens(field_id)) as u64, try!(stats.total_docs(field_id)) as u64, try!(stats.term_document_frequency(field_id, term_id)) as u64);
+                    }
+
+                    stack.push(score * scorer.boost);
+                } else {
+                    stack.push(0.0f32);
+                }
+            }
+            ScoreFunctionOp::SpanScorer(field_id, ref span, ref term_ids, ref scorer) => {
+                let span_frequency = try!(load_spans(segment, doc_id, field_id, span)).len() as u32;
+
+                if span_frequency > 0 {
+                    let field_length = try!(load_field_length(doc_id, field_id, segment));
+
+                    // Scored the same way as phrases, with the number of matching spans as the frequency
+                    let mut score = 0.0f32;
+                    for term_id in term_ids.iter() {
+                        score += scorer.similarity_model.score(span_frequency, field_length, try!(stats.total_tokens(field_id)) as u64, try!(stats.total_docs(field_id)) as u64, try!(stats.term_document_frequency(field_id, *term_id)) as u64);
+                    }
+
+                    stack.push(score * scorer.boost);
+                } else {
+                    stack.push(0.0f32);
+                }
+            }
+            ScoreFunctionOp::IntervalsScorer(field_id, ref intervals, ref term_ids, ref scorer) => {
+                let intervals_frequency = intervals_frequency(&try!(load_intervals(segment, doc_id, field_id, intervals, term_ids)));
+
+                if intervals_frequency > 0.0f32 {
+                    let field_length = try!(load_field_length(doc_id, field_id, segment));
+
+                    // Each interval counts less towards the frequency the more gaps it has
+                    let mut score = 0.0f32;
+                    for term in intervals.terms() {
+                        if let Some(term_id) = term_ids.get(term) {
+                            score += scorer.similarity_model.score_sloppy(intervals_frequency, field_length, try!(stats.total_tokens(field_id)) as u64, try!(stats.total_docs(field_id)) as u64, try!(stats.term_document_frequency(field_id, *term_id)) as u64);
+                        }
+                    }
+
+                    stack.push(score * scorer.boost);
+                } else {
+                    stack.push(0.0f32);
+                }
+            }
             ScoreFunctionOp::CombinatorScorer(num_vals, ref scorer) => {
                 let score = match *scorer {
                     CombinatorScorer::Avg => {
@@ -0,0 +1,156 @@
+use roaring::RoaringBitmap;
+use kite::schema::FieldId;
+use kite::term::TermId;
+use kite::segment::Segment;
+
+/// Loads the positions of each term of a phrase in a document, along with the
+/// offset of the term in the phrase
+/// Returns None if any of the terms doesn't have positions in the document
+pub fn load_phrase_positions<S: Segment>(segment: &S, doc_id: u16, field_id: FieldId, term_ids: &[(u32, TermId)]) -> Result<Option<Vec<(u32, RoaringBitmap)>>, String> {
+    let mut phrase_positions = Vec::with_capacity(term_ids.len());
+
+    for &(offset, term_id) in term_ids.iter() {
+        match try!(segment.load_term_positions(doc_id, field_id, term_id)) {
+            Some(positions) => phrase_positions.push((offset, positions)),
+            None => return Ok(None),
+        }
+    }
+
+    Ok(Some(phrase_positions))
+}
+
+/// Counts the number of times a phrase occurs, given the offset of each of its
+/// terms in the phrase and their positions in the document
+///
+/// The terms must occur in order, each at least as far after the previous term as it is
+/// in the phrase (so gaps left by removed stopwords are kept). Terms that share an offset
+/// (eg, synonyms) can be at the same position. The number of extra positions between the
+/// first and last term must not exceed "slop".
+/// Each position of the first term starts at most one occurrence.
+pub fn phrase_frequency(phrase_positions: &[(u32, RoaringBitmap)], slop: u32) -> u32 {
+    let (&(first_offset, ref first_positions), other_positions) = match phrase_positions.split_first() {
+        Some(split) => split,
+        None => return 0,
+    };
+
+    let mut frequency = 0;
+
+    'starts: for start in first_positions.iter() {
+        // The earliest position each term can be at gives the shortest occurrence
+        // of the phrase from this start position
+        let mut previous = start;
+        let mut previous_offset = first_offset;
+        let mut phrase_length = 0;
+        for &(offset, ref positions) in other_positions.iter() {
+            let gap = offset.saturating_sub(previous_offset);
+            previous = match positions.iter().find(|position| *position >= previous + gap) {
+                Some(position) => position,
+                None => break 'starts,
+            };
+            previous_offset = offset;
+            phrase_length += gap;
+        }
+
+        let extra_positions = previous - start - phrase_length;
+        if extra_positions <= slop {
+            frequency += 1;
+        }
+    }
+
+    frequency
+}
+
+#[cfg(test)]
+mod tests {
+    use roaring::RoaringBitmap;
+
+    use super::phrase_frequency;
+
+    fn positions(positions: Vec<u32>) -> RoaringBitmap {
+        positions.into_iter().collect()
+    }
+
+    #[test]
+    fn test_exact_phrase() {
+        // "the quick brown fox jumps over the quick dog"
+        let phrase = vec![(0, positions(vec![2, 8])), (1, positions(vec![3]))];
+
+        assert_eq!(phrase_frequency(&phrase, 0), 1);
+    }
+
+    #[test]
+    fn test_phrase_repeated() {
+        // "to be or not to be"
+        let phrase = vec![(0, positions(vec![1, 5])), (1, positions(vec![2, 6]))];
+
+        assert_eq!(phrase_frequency(&phrase, 0), 2);
+    }
+
+    #[test]
+    fn test_phrase_wrong_order() {
+        // "brown quick"
+        let phrase = vec![(0, positions(vec![2])), (1, positions(vec![1]))];
+
+        assert_eq!(phrase_frequency(&phrase, 0), 0);
+        assert_eq!(phrase_frequency(&phrase, 10), 0);
+    }
+
+    #[test]
+    fn test_phrase_slop() {
+        // "quick ... fox" in "the quick brown fox"
+        let phrase = vec![(0, positions(vec![2])), (1, positions(vec![4]))];
+
+        assert_eq!(phrase_frequency(&phrase, 0), 0);
+        assert_eq!(phrase_frequency(&phrase, 1), 1);
+        assert_eq!(phrase_frequency(&phrase, 2), 1);
+    }
+
+    #[test]
+    fn test_phrase_slop_is_shared_between_terms() {
+        // "a x b x c"
+        let phrase = vec![(0, positions(vec![1])), (1, positions(vec![3])), (2, positions(vec![5]))];
+
+        assert_eq!(phrase_frequency(&phrase, 1), 0);
+        assert_eq!(phrase_frequency(&phrase, 2), 1);
+    }
+
+    #[test]
+    fn test_phrase_same_term_twice() {
+        // "very very" in "it is very good"
+        let phrase = vec![(0, positions(vec![3])), (1, positions(vec![3]))];
+
+        assert_eq!(phrase_frequency(&phrase, 5), 0);
+    }
+
+    #[test]
+    fn test_empty_phrase() {
+        assert_eq!(phrase_frequency(&vec![], 0), 0);
+    }
+
+    #[test]
+    fn test_phrase_gap() {
+        // "united [of] america" in "united states of america" and "united of america"
+        let phrase = vec![(0, positions(vec![1])), (2, positions(vec![4]))];
+        assert_eq!(phrase_frequency(&phrase, 0), 0);
+        assert_eq!(phrase_frequency(&phrase, 1), 1);
+
+        let phrase = vec![(0, positions(vec![1])), (2, positions(vec![3]))];
+        assert_eq!(phrase_frequency(&phrase, 0), 1);
+
+        // The gap must be kept, the terms can't be closer together
+        let phrase = vec![(0, positions(vec![1])), (2, positions(vec![2]))];
+        assert_eq!(phrase_frequency(&phrase, 5), 0);
+    }
+
+    #[test]
+    fn test_phrase_shared_offset() {
+        // "[usa|united] states" in "united states" where "usa" is a synonym of "united"
+        let phrase = vec![(0, positions(vec![1])), (0, positions(vec![1])), (1, positions(vec![2]))];
+        assert_eq!(phrase_frequency(&phrase, 0), 1);
+
+        // Terms at the same offset use up slop if they aren't at the same position
+        let phrase = vec![(0, positions(vec![1])), (0, positions(vec![2])), (1, positions(vec![3]))];
+        assert_eq!(phrase_frequency(&phrase, 0), 0);
+        assert_eq!(phrase_frequency(&phrase, 1), 1);
+    }
+}
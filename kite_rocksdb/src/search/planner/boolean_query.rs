@@ -2,14 +2,22 @@ use std::rc::Rc;
 
 use kite::schema::FieldId;
 use kite::term::TermId;
-use kite::Query;
+use kite::{Query, Term};
+use kite::query::intervals::Intervals;
+use fnv::FnvHashMap;
 
 use RocksDBReader;
+use search::span::{SpanPlan, plan_span_query};
+use search::intervals::plan_intervals_terms;
+use search::range::plan_range_terms;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum BooleanQueryOp {
     PushEmpty,
     PushTermDirectory(FieldId, TermId),
+    PushPhrase(FieldId, Vec<(u32, TermId)>, u32),
+    PushSpan(FieldId, SpanPlan),
+    PushIntervals(FieldId, Intervals, FnvHashMap<Term, TermId>),
     PushDeletionList,
     And,
     Or,
@@ -117,6 +125,39 @@ impl BooleanQueryBuilder {
         }));
     }
 
+    pub fn push_phrase(&mut self, field_id: FieldId, term_ids: Vec<(u32, TermId)>, slop: u32) {
+        use self::BooleanQueryOp::*;
+        use self::BooleanQueryBlock::*;
+        use self::BooleanQueryBlockReturnType::*;
+
+        self.stack.push(Rc::new(Leaf{
+            op: PushPhrase(field_id, term_ids, slop),
+            return_type: Sparse,
+        }));
+    }
+
+    pub fn push_span(&mut self, field_id: FieldId, span: SpanPlan) {
+        use self::BooleanQueryOp::*;
+        use self::BooleanQueryBlock::*;
+        use self::BooleanQueryBlockReturnType::*;
+
+        self.stack.push(Rc::new(Leaf{
+            op: PushSpan(field_id, span),
+            return_type: Sparse,
+        }));
+    }
+
+    pub fn push_intervals(&mut self, field_id: FieldId, intervals: Intervals, term_ids: FnvHashMap<Term, TermId>) {
+        use self::BooleanQueryOp::*;
+        use self::BooleanQueryBlock::*;
+        use self::BooleanQueryBlockReturnType::*;
+
+        self.stack.push(Rc::new(Leaf{
+            op: PushIntervals(field_id, intervals, term_ids),
+            return_type: Sparse,
+        }));
+    }
+
     pub fn push_deletion_list(&mut self) {
         use self::BooleanQueryOp::*;
         use self::BooleanQueryBlock::*;
@@ -382,6 +423,41 @@ pub fn plan_boolean_query(index_reader: &RocksDBReader, mut builder: &mut Boolea
                 builder.or_combinator();
             }
         }
+        Query::Range{field, ref lower, ref upper, ..} => {
+            // Get terms
+            builder.push_empty();
+            for term_id in plan_range_terms(index_reader, field, lower, upper) {
+                builder.push_term_directory(field, term_id);
+                builder.or_combinator();
+            }
+        }
+        Query::Phrase{field, ref terms, slop, ..} => {
+            if terms.is_empty() {
+                builder.push_empty();
+                return
+            }
+
+            // Get terms
+            let mut term_ids = Vec::with_capacity(terms.len());
+            for &(offset, ref term) in terms.iter() {
+                match index_reader.store.term_dictionary.get(term) {
+                    Some(term_id) => term_ids.push((offset, term_id)),
+                    None => {
+                        // Term doesn't exist, so the phrase will never match
+                        builder.push_empty();
+                        return
+                    }
+                }
+            }
+
+            builder.push_phrase(field, term_ids, slop);
+        }
+        Query::Span{field, ref span, ..} => {
+            builder.push_span(field, plan_span_query(index_reader, span));
+        }
+        Query::Intervals{field, ref intervals, ..} => {
+            builder.push_intervals(field, intervals.clone(), plan_intervals_terms(index_reader, intervals));
+        }
         Query::Conjunction{ref queries} => {
             plan_boolean_query_combinator(index_reader, &mut builder, queries, |builder| builder.and_combinator());
         }
@@ -454,6 +530,20 @@ mod builder_tests {
         assert_eq!(negated, false);
     }
 
+    #[test]
+    fn test_push_phrase() {
+        let mut builder = BooleanQueryBuilder::new();
+
+        builder.push_phrase(FieldId(1), vec![(0, TermId(1)), (1, TermId(2))], 0);
+
+        let (query, negated) = builder.build();
+
+        assert_eq!(query, vec![
+            BooleanQueryOp::PushPhrase(FieldId(1), vec![(0, TermId(1)), (1, TermId(2))], 0),
+        ]);
+        assert_eq!(negated, false);
+    }
+
     #[test]
     fn test_push_deletion_list() {
         let mut builder = BooleanQueryBuilder::new();
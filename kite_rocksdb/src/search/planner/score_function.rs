@@ -1,9 +1,13 @@
 use kite::schema::FieldId;
 use kite::term::TermId;
-use kite::Query;
+use kite::{Query, Term};
+use kite::query::intervals::Intervals;
+use fnv::FnvHashMap;
 use kite::query::term_scorer::TermScorer;
 
 use RocksDBReader;
+use search::span::{SpanPlan, plan_span_query};
+use search::intervals::plan_intervals_terms;
 
 #[derive(Debug, Clone)]
 pub enum CombinatorScorer {
@@ -15,6 +19,9 @@ pub enum CombinatorScorer {
 pub enum ScoreFunctionOp {
     Literal(f32),
     TermScorer(FieldId, TermId, TermScorer),
+    PhraseScorer(FieldId, Vec<(u32, TermId)>, u32, TermScorer),
+    SpanScorer(FieldId, SpanPlan, Vec<TermId>, TermScorer),
+    IntervalsScorer(FieldId, Intervals, FnvHashMap<Term, TermId>, TermScorer),
     CombinatorScorer(u32, CombinatorScorer),
 }
 
@@ -77,6 +84,36 @@ pub fn plan_score_function(index_reader: &RocksDBReader, mut score_function: &mu
                 _ => score_function.push(ScoreFunctionOp::CombinatorScorer(total_terms, CombinatorScorer::Avg)),
             }
         }
+        Query::Range{score, ..} => {
+            score_function.push(ScoreFunctionOp::Literal(score));
+        }
+        Query::Phrase{field, ref terms, slop, ref scorer} => {
+            // Get terms
+            let mut term_ids = Vec::with_capacity(terms.len());
+            for &(offset, ref term) in terms.iter() {
+                match index_reader.store.term_dictionary.get(term) {
+                    Some(term_id) => term_ids.push((offset, term_id)),
+                    None => {
+                        // Term doesn't exist, so the phrase will never match
+                        score_function.push(ScoreFunctionOp::Literal(0.0f32));
+                        return
+                    }
+                }
+            }
+
+            score_function.push(ScoreFunctionOp::PhraseScorer(field, term_ids, slop, scorer.clone()));
+        }
+        Query::Span{field, ref span, ref scorer} => {
+            // Get terms
+            let term_ids = span.terms().into_iter()
+                .filter_map(|term| index_reader.store.term_dictionary.get(term))
+                .collect();
+
+            score_function.push(ScoreFunctionOp::SpanScorer(field, plan_span_query(index_reader, span), term_ids, scorer.clone()));
+        }
+        Query::Intervals{field, ref intervals, ref scorer} => {
+            score_function.push(ScoreFunctionOp::IntervalsScorer(field, intervals.clone(), plan_intervals_terms(index_reader, intervals), scorer.clone()));
+        }
         Query::Conjunction{ref queries} => {
             plan_score_function_combinator(index_reader, &mut score_function, queries, CombinatorScorer::Avg);
         }
@@ -0,0 +1,29 @@
+use std::ops::Bound;
+
+use kite::Term;
+use kite::schema::{FieldId, FIELD_TRIE};
+use kite::term::TermId;
+use kite::trie::range_terms;
+
+use RocksDBReader;
+
+/// Looks up the term ids of the terms in a field that cover a range
+///
+/// Fields indexed with FIELD_TRIE are covered by terms at lower levels of precision
+/// where possible. For other fields, every term in the range is selected.
+pub fn plan_range_terms(index_reader: &RocksDBReader, field_id: FieldId, lower: &Bound<Term>, upper: &Bound<Term>) -> Vec<TermId> {
+    let term_dictionary = &index_reader.store.term_dictionary;
+    let is_trie = index_reader.store.schema.get(&field_id).map_or(false, |field_info| field_info.field_flags.contains(FIELD_TRIE));
+
+    if !is_trie {
+        return term_dictionary.select_range(lower, upper);
+    }
+
+    match range_terms(lower, upper) {
+        Some(terms) => terms.iter().filter_map(|term| term_dictionary.get(term)).collect(),
+        None => {
+            // The bounds aren't integer terms, so can't match any values in the field
+            Vec::new()
+        }
+    }
+}
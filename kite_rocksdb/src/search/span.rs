@@ -0,0 +1,305 @@
+use roaring::RoaringBitmap;
+use kite::schema::FieldId;
+use kite::term::TermId;
+use kite::segment::Segment;
+use kite::query::span::SpanQuery;
+
+use RocksDBReader;
+
+/// A range of positions in a field. The end position is exclusive
+pub type Span = (u32, u32);
+
+/// A span query with its terms resolved to term ids
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpanPlan {
+    /// Matches nothing (used for terms that aren't in the term dictionary)
+    Empty,
+    Term(TermId),
+    Near(Vec<SpanPlan>, u32, bool),
+    Or(Vec<SpanPlan>),
+    Not(Box<SpanPlan>, Box<SpanPlan>),
+    First(Box<SpanPlan>, u32),
+    Containing(Box<SpanPlan>, Box<SpanPlan>),
+}
+
+pub fn plan_span_query(index_reader: &RocksDBReader, span: &SpanQuery) -> SpanPlan {
+    match *span {
+        SpanQuery::Term{ref term} => {
+            match index_reader.store.term_dictionary.get(term) {
+                Some(term_id) => SpanPlan::Term(term_id),
+                None => SpanPlan::Empty,
+            }
+        }
+        SpanQuery::Near{ref clauses, slop, in_order} => {
+            SpanPlan::Near(clauses.iter().map(|clause| plan_span_query(index_reader, clause)).collect(), slop, in_order)
+        }
+        SpanQuery::Or{ref clauses} => {
+            SpanPlan::Or(clauses.iter().map(|clause| plan_span_query(index_reader, clause)).collect())
+        }
+        SpanQuery::Not{ref include, ref exclude} => {
+            SpanPlan::Not(Box::new(plan_span_query(index_reader, include)), Box::new(plan_span_query(index_reader, exclude)))
+        }
+        SpanQuery::First{ref span, end} => {
+            SpanPlan::First(Box::new(plan_span_query(index_reader, span)), end)
+        }
+        SpanQuery::Containing{ref big, ref little} => {
+            SpanPlan::Containing(Box::new(plan_span_query(index_reader, big)), Box::new(plan_span_query(index_reader, little)))
+        }
+    }
+}
+
+/// Finds the documents that could contain a match for the span query
+/// This only uses the term directories so the candidates still need checking against positions
+pub fn load_span_candidates<S: Segment>(segment: &S, field_id: FieldId, span: &SpanPlan) -> Result<RoaringBitmap, String> {
+    match *span {
+        SpanPlan::Empty => Ok(RoaringBitmap::new()),
+        SpanPlan::Term(term_id) => {
+            Ok(try!(segment.load_term_directory(field_id, term_id)).unwrap_or_else(RoaringBitmap::new))
+        }
+        SpanPlan::Near(ref clauses, ..) => {
+            let mut candidates: Option<RoaringBitmap> = None;
+            for clause in clauses.iter() {
+                let clause_candidates = try!(load_span_candidates(segment, field_id, clause));
+
+                candidates = Some(match candidates {
+                    Some(mut candidates) => {
+                        candidates.intersect_with(&clause_candidates);
+                        candidates
+                    }
+                    None => clause_candidates,
+                });
+            }
+
+            Ok(candidates.unwrap_or_else(RoaringBitmap::new))
+        }
+        SpanPlan::Or(ref clauses) => {
+            let mut candidates = RoaringBitmap::new();
+            for clause in clauses.iter() {
+                candidates.union_with(&try!(load_span_candidates(segment, field_id, clause)));
+            }
+
+            Ok(candidates)
+        }
+        SpanPlan::Not(ref include, _) => load_span_candidates(segment, field_id, include),
+        SpanPlan::First(ref span, _) => load_span_candidates(segment, field_id, span),
+        SpanPlan::Containing(ref big, ref little) => {
+            let mut candidates = try!(load_span_candidates(segment, field_id, big));
+            candidates.intersect_with(&try!(load_span_candidates(segment, field_id, little)));
+            Ok(candidates)
+        }
+    }
+}
+
+/// Finds all the spans in a document that match the span query
+/// The returned spans are sorted and contain no duplicates
+pub fn load_spans<S: Segment>(segment: &S, doc_id: u16, field_id: FieldId, span: &SpanPlan) -> Result<Vec<Span>, String> {
+    let spans = match *span {
+        SpanPlan::Empty => Vec::new(),
+        SpanPlan::Term(term_id) => {
+            match try!(segment.load_term_positions(doc_id, field_id, term_id)) {
+                Some(positions) => positions.iter().map(|position| (position, position + 1)).collect(),
+                None => Vec::new(),
+            }
+        }
+        SpanPlan::Near(ref clauses, slop, in_order) => {
+            let mut clause_spans = Vec::with_capacity(clauses.len());
+            for clause in clauses.iter() {
+                clause_spans.push(try!(load_spans(segment, doc_id, field_id, clause)));
+            }
+
+            if in_order {
+                near_ordered(&clause_spans, slop)
+            } else {
+                near_unordered(&clause_spans, slop)
+            }
+        }
+        SpanPlan::Or(ref clauses) => {
+            let mut spans = Vec::new();
+            for clause in clauses.iter() {
+                spans.extend(try!(load_spans(segment, doc_id, field_id, clause)));
+            }
+
+            spans
+        }
+        SpanPlan::Not(ref include, ref exclude) => {
+            let include = try!(load_spans(segment, doc_id, field_id, include));
+            let exclude = try!(load_spans(segment, doc_id, field_id, exclude));
+
+            include.into_iter()
+                .filter(|span| !exclude.iter().any(|exclude_span| overlaps(span, exclude_span)))
+                .collect()
+        }
+        SpanPlan::First(ref span, end) => {
+            // Positions start at 1 so a span ending at "end + 1" is within the first "end" positions
+            try!(load_spans(segment, doc_id, field_id, span)).into_iter()
+                .filter(|&(_, span_end)| span_end <= end.saturating_add(1))
+                .collect()
+        }
+        SpanPlan::Containing(ref big, ref little) => {
+            let big = try!(load_spans(segment, doc_id, field_id, big));
+            let little = try!(load_spans(segment, doc_id, field_id, little));
+
+            big.into_iter()
+                .filter(|span| little.iter().any(|little_span| contains(span, little_span)))
+                .collect()
+        }
+    };
+
+    Ok(sort_spans(spans))
+}
+
+fn sort_spans(mut spans: Vec<Span>) -> Vec<Span> {
+    spans.sort();
+    spans.dedup();
+    spans
+}
+
+fn overlaps(a: &Span, b: &Span) -> bool {
+    a.0 < b.1 && b.0 < a.1
+}
+
+fn contains(big: &Span, little: &Span) -> bool {
+    big.0 <= little.0 && little.1 <= big.1
+}
+
+/// Finds matches of the clauses in order, without overlapping each other, where
+/// the total number of positions between them is no more than "slop"
+///
+/// For each span of the first clause, only the match that ends earliest is returned.
+fn near_ordered(clause_spans: &[Vec<Span>], slop: u32) -> Vec<Span> {
+    let (first_spans, other_spans) = match clause_spans.split_first() {
+        Some(split) => split,
+        None => return Vec::new(),
+    };
+
+    let mut matches = Vec::new();
+
+    'starts: for first_span in first_spans.iter() {
+        let mut previous_end = first_span.1;
+        let mut gaps = 0;
+
+        for spans in other_spans.iter() {
+            let next_span = spans.iter()
+                .filter(|span| span.0 >= previous_end)
+                .min_by_key(|span| (span.1, span.0));
+
+            match next_span {
+                Some(next_span) => {
+                    gaps += next_span.0 - previous_end;
+                    previous_end = next_span.1;
+                }
+                None => continue 'starts,
+            }
+        }
+
+        if gaps <= slop {
+            matches.push((first_span.0, previous_end));
+        }
+    }
+
+    sort_spans(matches)
+}
+
+/// Finds matches of the clauses in any order, where the total number of positions
+/// in the match that aren't covered by one of the clauses is no more than "slop"
+///
+/// For each span, only the match that starts with that span and ends earliest is returned.
+fn near_unordered(clause_spans: &[Vec<Span>], slop: u32) -> Vec<Span> {
+    let mut matches = Vec::new();
+
+    for (anchor_clause, anchor_spans) in clause_spans.iter().enumerate() {
+        'anchors: for anchor_span in anchor_spans.iter() {
+            let mut end = anchor_span.1;
+            let mut covered = anchor_span.1 - anchor_span.0;
+
+            for (clause, spans) in clause_spans.iter().enumerate() {
+                if clause == anchor_clause {
+                    continue;
+                }
+
+                // The anchor span must be the first span in the match
+                let next_span = spans.iter()
+                    .filter(|span| span.0 >= anchor_span.0)
+                    .min_by_key(|span| (span.1, span.0));
+
+                match next_span {
+                    Some(next_span) => {
+                        if next_span.1 > end {
+                            end = next_span.1;
+                        }
+                        covered += next_span.1 - next_span.0;
+                    }
+                    None => continue 'anchors,
+                }
+            }
+
+            let width = end - anchor_span.0;
+            if width <= covered + slop {
+                matches.push((anchor_span.0, end));
+            }
+        }
+    }
+
+    sort_spans(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{near_ordered, near_unordered};
+
+    #[test]
+    fn test_near_ordered() {
+        // "the quick brown fox"
+        let quick = vec![(2, 3)];
+        let fox = vec![(4, 5)];
+
+        assert_eq!(near_ordered(&vec![quick.clone(), fox.clone()], 0), vec![]);
+        assert_eq!(near_ordered(&vec![quick.clone(), fox.clone()], 1), vec![(2, 5)]);
+        assert_eq!(near_ordered(&vec![fox, quick], 10), vec![]);
+    }
+
+    #[test]
+    fn test_near_ordered_picks_nearest() {
+        // "a b a b"
+        let a = vec![(1, 2), (3, 4)];
+        let b = vec![(2, 3), (4, 5)];
+
+        assert_eq!(near_ordered(&vec![a, b], 0), vec![(1, 3), (3, 5)]);
+    }
+
+    #[test]
+    fn test_near_ordered_with_nested_spans() {
+        // "quick brown" followed by "fox" in "the quick brown fox"
+        let quick_brown = vec![(2, 4)];
+        let fox = vec![(4, 5)];
+
+        assert_eq!(near_ordered(&vec![quick_brown, fox], 0), vec![(2, 5)]);
+    }
+
+    #[test]
+    fn test_near_ordered_spans_cannot_overlap() {
+        // "quick" twice in "quick brown"
+        let quick = vec![(1, 2)];
+
+        assert_eq!(near_ordered(&vec![quick.clone(), quick], 10), vec![]);
+    }
+
+    #[test]
+    fn test_near_unordered() {
+        // "the quick brown fox"
+        let quick = vec![(2, 3)];
+        let fox = vec![(4, 5)];
+
+        assert_eq!(near_unordered(&vec![fox.clone(), quick.clone()], 0), vec![]);
+        assert_eq!(near_unordered(&vec![fox.clone(), quick.clone()], 1), vec![(2, 5)]);
+        assert_eq!(near_unordered(&vec![quick, fox], 1), vec![(2, 5)]);
+    }
+
+    #[test]
+    fn test_near_missing_clause() {
+        let quick = vec![(2, 3)];
+
+        assert_eq!(near_ordered(&vec![quick.clone(), vec![]], 10), vec![]);
+        assert_eq!(near_unordered(&vec![quick, vec![]], 10), vec![]);
+    }
+}
@@ -46,6 +46,13 @@ impl<'a> Segment for RocksDBSegment<'a> {
         Ok(doc_id_set)
     }
 
+    fn load_term_positions(&self, doc_local_id: u16, field_id: FieldId, term_id: TermId) -> Result<Option<RoaringBitmap>, String> {
+        let value_type = KeyBuilder::stored_field_value_term_positions_value_type(term_id.0);
+        let kb = KeyBuilder::stored_field_value(self.id, doc_local_id, field_id.0, &value_type);
+        let positions = try!(self.reader.snapshot.get(&kb.key())).map(|positions| RoaringBitmap::deserialize_from(Cursor::new(&positions[..])).unwrap());
+        Ok(positions)
+    }
+
     fn load_deletion_list(&self) -> Result<Option<RoaringBitmap>, String> {
         let kb = KeyBuilder::segment_del_list(self.id);
         let doc_id_set = try!(self.reader.snapshot.get(&kb.key())).map(|doc_id_set| RoaringBitmap::deserialize_from(Cursor::new(&doc_id_set[..])).unwrap());
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use kite::{Document, Term, TermId};
-use kite::schema::FieldId;
+use kite::schema::{Schema, FieldId, FIELD_POSITIONS};
 use kite::segment::{SegmentId, Segment};
 use byteorder::{LittleEndian, WriteBytesExt};
 use roaring::RoaringBitmap;
@@ -17,6 +17,7 @@ pub struct SegmentBuilder {
     pub term_directories: FnvHashMap<(FieldId, TermId), RoaringBitmap>,
     pub statistics: FnvHashMap<Vec<u8>, i64>,
     pub stored_field_values: FnvHashMap<(FieldId, u16, Vec<u8>), Vec<u8>>,
+    pub term_positions: FnvHashMap<(FieldId, TermId, u16), RoaringBitmap>,
 }
 
 #[derive(Debug)]
@@ -34,6 +35,7 @@ impl SegmentBuilder {
             term_directories: FnvHashMap::default(),
             statistics: FnvHashMap::default(),
             stored_field_values: FnvHashMap::default(),
+            term_positions: FnvHashMap::default(),
         }
     }
 
@@ -50,7 +52,7 @@ impl SegmentBuilder {
         term_id
     }
 
-    pub fn add_document(&mut self, doc: &Document) -> Result<u16, DocumentInsertError> {
+    pub fn add_document(&mut self, schema: &Schema, doc: &Document) -> Result<u16, DocumentInsertError> {
         // Get document ord
         let doc_id = self.current_doc;
         self.current_doc += 1;
@@ -60,10 +62,14 @@ impl SegmentBuilder {
         let mut term_frequencies = FnvHashMap::default();
         for (field_id, tokens) in doc.indexed_fields.iter() {
             let mut field_token_count = 0;
+            let store_positions = schema.get(field_id).map_or(false, |field_info| field_info.field_flags.contains(FIELD_POSITIONS));
 
             for (term, positions) in tokens.iter() {
                 let frequency = positions.len();
-                field_token_count += frequency;
+
+                // Terms at position 0 (eg, the lower precision terms of trie fields) aren't
+                // part of the field's text so don't count towards its length
+                field_token_count += if positions.contains(0) { frequency - 1 } else { frequency };
 
                 // Get term ref
                 let term_id = self.get_term_id(term);
@@ -88,6 +94,11 @@ impl SegmentBuilder {
                     self.stored_field_values.insert((*field_id, doc_id, value_type), frequency_bytes);
                 }
 
+                // Write term positions
+                if store_positions {
+                    self.term_positions.insert((*field_id, term_id, doc_id), positions.clone());
+                }
+
                 // Increment term document frequency
                 let stat_name = KeyBuilder::segment_stat_term_doc_frequency_stat_name(field_id.0, term_id.0);
                 let stat = self.statistics.entry(stat_name).or_insert(0);
@@ -149,6 +160,10 @@ impl Segment for SegmentBuilder {
         Ok(self.term_directories.get(&(field_id, term_id)).cloned())
     }
 
+    fn load_term_positions(&self, doc_local_id: u16, field_id: FieldId, term_id: TermId) -> Result<Option<RoaringBitmap>, String> {
+        Ok(self.term_positions.get(&(field_id, term_id, doc_local_id)).cloned())
+    }
+
     fn load_deletion_list(&self) -> Result<Option<RoaringBitmap>, String> {
         Ok(None)
     }
@@ -1,9 +1,11 @@
 use std::str;
 use std::sync::{Mutex, RwLock};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::ops::Bound;
 
 use rocksdb::{self, DB};
+use fnv::FnvHashMap;
 use kite::{Term, TermId};
 use kite::query::multi_term_selector::MultiTermSelector;
 
@@ -16,9 +18,11 @@ use key_builder::KeyBuilder;
 ///
 /// The term dictionary is a mapping between terms and their internal IDs
 /// (aka. TermId). It is entirely held in memory and persisted to the disk.
+/// Terms are kept sorted so multi term selectors can skip over ranges of terms
+/// that can't match.
 pub struct TermDictionaryManager {
     next_term_id: AtomicUsize,
-    terms: RwLock<HashMap<Term, TermId>>,
+    terms: RwLock<BTreeMap<Term, TermId>>,
     write_lock: Mutex<i32>,
 }
 
@@ -31,7 +35,7 @@ impl TermDictionaryManager {
 
         Ok(TermDictionaryManager {
             next_term_id: AtomicUsize::new(1),
-            terms: RwLock::new(HashMap::new()),
+            terms: RwLock::new(BTreeMap::new()),
             write_lock: Mutex::new(0),
         })
     }
@@ -46,7 +50,7 @@ impl TermDictionaryManager {
         };
 
         // Read dictionary
-        let mut terms = HashMap::new();
+        let mut terms = BTreeMap::new();
         let mut iter = db.raw_iterator();
         iter.seek(b"t");
         while iter.valid() {
@@ -69,18 +73,92 @@ impl TermDictionaryManager {
         })
     }
 
+    /// Builds a mapping from each TermId to its term
+    pub fn terms_by_id(&self) -> FnvHashMap<TermId, Term> {
+        self.terms.read().unwrap().iter()
+            .map(|(term, term_id)| (*term_id, term.clone()))
+            .collect()
+    }
+
     /// Retrieves the TermId for the given term
     pub fn get(&self, term: &Term) -> Option<TermId> {
         self.terms.read().unwrap().get(term).cloned()
     }
 
-    /// Iterates over terms in the dictionary which match the selector
+    /// Finds the terms in the dictionary which match the selector
+    ///
+    /// This runs the selector's automaton over the terms in order. When the automaton
+    /// rejects a term part way through, all the other terms that start with the bytes
+    /// read so far are skipped.
     pub fn select(&self, term_selector: &MultiTermSelector) -> Vec<TermId> {
-        self.terms.read().unwrap().iter()
-            .filter(|&(term, _term_id)| {
-                term_selector.matches(term)
-            })
-            .map(|(_term, term_id)| *term_id)
+        let automaton = match term_selector.automaton() {
+            Ok(automaton) => automaton,
+            Err(_) => {
+                // Selector is invalid (see MultiTermSelector::validate), so will never match
+                return Vec::new();
+            }
+        };
+        let terms = self.terms.read().unwrap();
+        let mut term_ids = Vec::new();
+        let mut next_term = Some(Term::from_bytes(b""));
+
+        while let Some(from) = next_term.take() {
+            let (term, term_id) = match terms.range(from..).next() {
+                Some(entry) => entry,
+                None => break,
+            };
+
+            let bytes = term.as_bytes();
+            let mut state = Some(automaton.start());
+            let mut read = 0;
+            while let Some(current_state) = state {
+                if read == bytes.len() {
+                    break;
+                }
+
+                state = automaton.accept(current_state, bytes[read]);
+                read += 1;
+            }
+
+            match state {
+                Some(state) => {
+                    if automaton.is_match(state) {
+                        term_ids.push(*term_id);
+                    }
+
+                    // Move on to the next term. Appending a zero byte gives the
+                    // smallest term that sorts after this one
+                    let mut next_bytes = bytes.to_vec();
+                    next_bytes.push(0);
+                    next_term = Some(Term::from_bytes(&next_bytes));
+                }
+                None => {
+                    next_term = prefix_successor(&bytes[..read]).map(|bytes| Term::from_bytes(&bytes));
+                }
+            }
+        }
+
+        term_ids
+    }
+
+    /// Finds the terms in the dictionary which are within the range
+    pub fn select_range(&self, lower: &Bound<Term>, upper: &Bound<Term>) -> Vec<TermId> {
+        // BTreeMap::range panics if the range ends before it starts
+        let is_empty = match (lower, upper) {
+            (&Bound::Included(ref lower), &Bound::Included(ref upper)) => lower > upper,
+            (&Bound::Included(ref lower), &Bound::Excluded(ref upper)) |
+            (&Bound::Excluded(ref lower), &Bound::Included(ref upper)) |
+            (&Bound::Excluded(ref lower), &Bound::Excluded(ref upper)) => lower >= upper,
+            _ => false,
+        };
+
+        if is_empty {
+            return Vec::new();
+        }
+
+        self.terms.read().unwrap()
+            .range((lower.as_ref(), upper.as_ref()))
+            .map(|(_, term_id)| *term_id)
             .collect()
     }
 
@@ -123,3 +201,18 @@ impl TermDictionaryManager {
         Ok(term_id)
     }
 }
+
+/// Returns the smallest byte string that sorts after every byte string starting
+/// with the prefix, or None if there isn't one
+fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut successor = prefix.to_vec();
+
+    while let Some(last) = successor.pop() {
+        if last < 255 {
+            successor.push(last + 1);
+            return Some(successor);
+        }
+    }
+
+    None
+}